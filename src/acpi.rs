@@ -0,0 +1,264 @@
+//! ACPI MADT description of the emulated interrupt controller.
+//!
+//! Subtables are returned as raw little-endian bytes, following the layouts of ACPI 6.3.
+//! The hypervisor appends them to the MADT header and fixes up the table checksum, e.g.
+//! with [`acpi_checksum`].
+
+use alloc::vec::Vec;
+
+use axaddrspace::GuestPhysAddr;
+
+use crate::config::GicVersion;
+use crate::consts::*;
+use crate::v2m::Gicv2mFrame;
+use crate::vgicv3::Vgicv3;
+
+// MADT interrupt controller structure types.
+const MADT_TYPE_GICC: u8 = 0x0b;
+const MADT_TYPE_GICD: u8 = 0x0c;
+const MADT_TYPE_GIC_MSI_FRAME: u8 = 0x0d;
+const MADT_TYPE_GICR: u8 = 0x0e;
+const MADT_TYPE_GIC_ITS: u8 = 0x0f;
+
+/// `Enabled` flag of the GICC structure.
+const GICC_FLAGS_ENABLED: u32 = 1 << 0;
+/// `SPI Count/Base Select` flag of the GIC MSI frame structure.
+const MSI_FRAME_FLAGS_SPI_SELECT: u32 = 1 << 0;
+
+/// Little-endian writer for a single MADT subtable.
+struct Subtable(Vec<u8>);
+
+impl Subtable {
+    /// Starts a subtable of `len` bytes with the given type.
+    fn new(ty: u8, len: u8) -> Self {
+        let mut bytes = Vec::with_capacity(len as usize);
+        bytes.extend([ty, len, 0, 0]);
+        Self(bytes)
+    }
+
+    fn u8(mut self, value: u8) -> Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u16(mut self, value: u16) -> Self {
+        self.0.extend(value.to_le_bytes());
+        self
+    }
+
+    fn u32(mut self, value: u32) -> Self {
+        self.0.extend(value.to_le_bytes());
+        self
+    }
+
+    fn u64(mut self, value: u64) -> Self {
+        self.0.extend(value.to_le_bytes());
+        self
+    }
+
+    /// Finishes the subtable, checking its length against the header.
+    fn finish(self) -> Vec<u8> {
+        debug_assert_eq!(self.0.len(), self.0[1] as usize);
+        self.0
+    }
+}
+
+/// Returns the byte that makes the sum of `bytes` plus itself zero, as required for the
+/// `Checksum` field of ACPI tables. The field itself must be zero in `bytes`.
+pub fn acpi_checksum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+        .wrapping_neg()
+}
+
+impl Vgicv3 {
+    /// Returns the MADT subtables describing the emulated interrupt controller.
+    ///
+    /// This is one GICC structure per vCPU, the GICD structure and, with
+    /// [`GicVersion::V3`], a GICR structure covering the redistributors of all vCPUs.
+    /// MSI controllers can be appended, see [`Gicv2mFrame::madt_entry`] and
+    /// [`its_madt_entry`].
+    ///
+    /// # Arguments
+    /// * `maintenance_intid` - The INTID of the virtual maintenance interrupt, advertised to
+    ///   guests that run their own hypervisor
+    pub fn madt_entries(&self, maintenance_intid: u32) -> Vec<u8> {
+        let config = &self.config;
+        let gicc_base = if config.supports_legacy() {
+            config.gicc_base.as_usize() as u64
+        } else {
+            0
+        };
+        let mut bytes = Vec::new();
+        for vcpu_id in 0..config.vcpu_num {
+            let gicc = Subtable::new(MADT_TYPE_GICC, 80)
+                .u32(vcpu_id as u32) // CPU Interface Number
+                .u32(vcpu_id as u32) // ACPI Processor UID
+                .u32(GICC_FLAGS_ENABLED)
+                .u32(0) // Parking Protocol Version
+                .u32(0) // Performance Interrupt GSIV
+                .u64(0) // Parked Address
+                .u64(gicc_base)
+                .u64(0) // GICV
+                .u64(0) // GICH
+                .u32(maintenance_intid)
+                .u64(0) // GICR Base Address, described by the GICR structure
+                .u64(config.vcpu_affinity(vcpu_id))
+                .u8(0) // Processor Power Efficiency Class
+                .u8(0)
+                .u16(0); // SPE Overflow Interrupt
+            bytes.extend(gicc.finish());
+        }
+
+        let version = match config.version {
+            GicVersion::V2 => 2,
+            GicVersion::V3 => 3,
+        };
+        let gicd = Subtable::new(MADT_TYPE_GICD, 24)
+            .u32(0) // GIC ID
+            .u64(config.gicd_base.as_usize() as u64)
+            .u32(0) // System Vector Base
+            .u8(version)
+            .u8(0)
+            .u16(0);
+        bytes.extend(gicd.finish());
+
+        if config.version == GicVersion::V3 {
+            let gicr = Subtable::new(MADT_TYPE_GICR, 16)
+                .u64(config.gicr_base.as_usize() as u64)
+                .u32((config.vcpu_num * GICR_STRIDE) as u32);
+            bytes.extend(gicr.finish());
+        }
+        bytes
+    }
+}
+
+impl Gicv2mFrame {
+    /// Returns the GIC MSI frame subtable of this frame, with the given frame ID.
+    pub fn madt_entry(&self, frame_id: u32) -> Vec<u8> {
+        Subtable::new(MADT_TYPE_GIC_MSI_FRAME, 24)
+            .u32(frame_id)
+            .u64(self.base.as_usize() as u64)
+            .u32(MSI_FRAME_FLAGS_SPI_SELECT)
+            .u16(self.spi_num as u16)
+            .u16(self.spi_base as u16)
+            .finish()
+    }
+}
+
+/// Returns the GIC ITS subtable of an ITS at `base`, with the given ITS ID.
+///
+/// The ITS itself is not emulated by this crate; this describes one provided by the
+/// hypervisor.
+pub fn its_madt_entry(its_id: u32, base: GuestPhysAddr) -> Vec<u8> {
+    Subtable::new(MADT_TYPE_GIC_ITS, 20)
+        .u32(its_id)
+        .u64(base.as_usize() as u64)
+        .u32(0)
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::mock;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn entries(config: Vgicv3Config) -> Vec<u8> {
+        let host = mock::MockHost::with_gicv(mock::MockHost::default_vtr());
+        Vgicv3::new(config, host).unwrap().madt_entries(25)
+    }
+
+    #[test]
+    fn gicv3_subtables() {
+        let bytes = entries(Vgicv3Config {
+            vcpu_num: 18,
+            ..Default::default()
+        });
+        assert_eq!(bytes.len(), 18 * 80 + 24 + 16);
+
+        for vcpu_id in [0, 17] {
+            let gicc = &bytes[80 * vcpu_id..80 * (vcpu_id + 1)];
+            assert_eq!(gicc[..4], [0x0b, 80, 0, 0]);
+            assert_eq!(u32_at(gicc, 4), vcpu_id as u32);
+            assert_eq!(u32_at(gicc, 8), vcpu_id as u32);
+            assert_eq!(u32_at(gicc, 12), 1);
+            // Physical Base Address, without legacy support.
+            assert_eq!(u64_at(gicc, 32), 0);
+            assert_eq!(u32_at(gicc, 56), 25);
+            assert_eq!(u64_at(gicc, 60), 0);
+        }
+        // MPIDR at offset 68: Aff1 = 1, Aff0 = 1 for vCPU 17.
+        assert_eq!(u64_at(&bytes[80..], 68), 0x1);
+        assert_eq!(u64_at(&bytes[17 * 80..], 68), 0x101);
+
+        let gicd = &bytes[18 * 80..18 * 80 + 24];
+        assert_eq!(gicd[..4], [0x0c, 24, 0, 0]);
+        assert_eq!(u64_at(gicd, 8), 0x800_0000);
+        assert_eq!(gicd[20..], [3, 0, 0, 0]);
+
+        let gicr = &bytes[18 * 80 + 24..];
+        assert_eq!(gicr[..4], [0x0e, 16, 0, 0]);
+        assert_eq!(u64_at(gicr, 4), 0x80a_0000);
+        assert_eq!(u32_at(gicr, 12), 18 * 0x2_0000);
+    }
+
+    #[test]
+    fn gicv2_subtables() {
+        let bytes = entries(Vgicv3Config {
+            version: GicVersion::V2,
+            vcpu_num: 2,
+            ..Default::default()
+        });
+        // No GICR structure.
+        assert_eq!(bytes.len(), 2 * 80 + 24);
+        assert_eq!(u64_at(&bytes[80..], 32), 0x801_0000);
+        assert_eq!(bytes[2 * 80 + 20], 2);
+    }
+
+    #[test]
+    fn msi_subtables() {
+        let config = Vgicv3Config {
+            spi_num: 64,
+            ..Default::default()
+        };
+        let vgic = Arc::new(mock::vgic(config).0);
+        let frame = Gicv2mFrame::new(vgic, 0x802_0000.into(), 64, 32).unwrap();
+        let entry = frame.madt_entry(3);
+        assert_eq!(
+            entry,
+            [
+                0x0d, 24, 0, 0, 3, 0, 0, 0, 0x00, 0x00, 0x02, 0x08, 0, 0, 0, 0, 1, 0, 0, 0, 32, 0,
+                64, 0,
+            ]
+        );
+
+        let entry = its_madt_entry(1, 0x808_0000.into());
+        assert_eq!(
+            entry,
+            [
+                0x0f, 20, 0, 0, 1, 0, 0, 0, 0x00, 0x00, 0x08, 0x08, 0, 0, 0, 0, 0, 0, 0, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn checksum_zeroes_the_sum() {
+        let bytes = [0x12, 0x34, 0xff, 0x00];
+        let sum = bytes
+            .iter()
+            .fold(acpi_checksum(&bytes), |sum, &byte| sum.wrapping_add(byte));
+        assert_eq!(sum, 0);
+    }
+}
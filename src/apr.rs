@@ -0,0 +1,268 @@
+//! Save, restore and migration of the active priority registers.
+
+use core::array;
+
+use axerrno::{AxResult, ax_err, ax_err_type};
+
+use crate::consts::{GICH_APR_NUM_MAX, GICH_PRE_BITS};
+use crate::regs::gich::VtrInfo;
+use crate::vgicv3::Vgicv3;
+
+/// Active priorities of a vCPU, as saved from the host virtual CPU interface.
+///
+/// Bit `n` of the concatenated registers of a group is set if an interrupt is active at
+/// preemption level `n`, that is at priority `n << (8 - pre_bits)`. Registers not
+/// implemented with `pre_bits` preemption bits are zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AprState {
+    /// Number of preemption bits of the host the registers were saved on.
+    pub pre_bits: u8,
+    /// Group 0 active priorities (`ICH_AP0R<n>_EL2`).
+    pub ap0r: [u32; GICH_APR_NUM_MAX],
+    /// Group 1 active priorities (`ICH_AP1R<n>_EL2`, `GICH_APR<n>`).
+    pub ap1r: [u32; GICH_APR_NUM_MAX],
+}
+
+impl AprState {
+    /// Returns the registers reconstructed for a host implementing `pre_bits` preemption
+    /// bits.
+    ///
+    /// Levels map exactly onto a host with more preemption bits. With fewer, each active
+    /// level must correspond to a level of the destination: merging two levels would leave
+    /// the guest with fewer priority drops than active interrupts.
+    ///
+    /// # Returns
+    /// - `Ok(AprState)` with the converted registers
+    /// - `Err(AxError)` if either number of preemption bits is invalid, bits are set for
+    ///   unimplemented levels, or an active level cannot be represented
+    pub fn convert(&self, pre_bits: u8) -> AxResult<AprState> {
+        if !GICH_PRE_BITS.contains(&self.pre_bits) || !GICH_PRE_BITS.contains(&pre_bits) {
+            return ax_err!(InvalidInput, "invalid number of preemption bits");
+        }
+        let convert = |regs: &[u32; GICH_APR_NUM_MAX]| -> AxResult<[u32; GICH_APR_NUM_MAX]> {
+            let mut levels = regs
+                .iter()
+                .rev()
+                .fold(0u128, |acc, &reg| (acc << 32) | reg as u128);
+            if levels.checked_shr(1 << self.pre_bits).unwrap_or(0) != 0 {
+                return ax_err!(
+                    InvalidData,
+                    "active priority set for an unimplemented level"
+                );
+            }
+            let mut converted = 0u128;
+            while levels != 0 {
+                let level = levels.trailing_zeros();
+                levels &= levels - 1;
+                converted |= if pre_bits >= self.pre_bits {
+                    1 << (level << (pre_bits - self.pre_bits))
+                } else {
+                    let shift = self.pre_bits - pre_bits;
+                    if level & ((1 << shift) - 1) != 0 {
+                        return ax_err!(
+                            Unsupported,
+                            "active priority cannot be represented with the preemption bits of this host"
+                        );
+                    }
+                    1 << (level >> shift)
+                };
+            }
+            Ok(array::from_fn(|n| (converted >> (32 * n)) as u32))
+        };
+        Ok(AprState {
+            pre_bits,
+            ap0r: convert(&self.ap0r)?,
+            ap1r: convert(&self.ap1r)?,
+        })
+    }
+}
+
+impl Vgicv3 {
+    /// Saves the active priority registers of the vCPU that has just exited on the
+    /// current physical CPU.
+    ///
+    /// Only the registers implemented according to `GICH_VTR.PREbits` are read.
+    ///
+    /// # Returns
+    /// - `Ok(AprState)` on success
+    /// - `Err(AxError)` with the software CPU interface, which has no host registers, or if
+    ///   the host does not give access to all the implemented registers, see
+    ///   [`GichOps::apr_count`](crate::GichOps::apr_count)
+    pub fn save_apr(&self) -> AxResult<AprState> {
+        let vtr = self.host_vtr()?;
+        let mut state = AprState {
+            pre_bits: vtr.pre_bits,
+            ..Default::default()
+        };
+        for n in 0..vtr.apr_count() {
+            state.ap0r[n] = self.host.read_ap0r(n);
+            state.ap1r[n] = self.host.read_apr(n);
+        }
+        Ok(state)
+    }
+
+    /// Restores active priority registers saved with [`Vgicv3::save_apr`] on the current
+    /// physical CPU, before entering the vCPU.
+    ///
+    /// Registers saved on a host with a different number of preemption bits, e.g. before
+    /// migrating the vCPU, are converted with [`AprState::convert`]. Nothing is written if
+    /// they cannot be, or if the host does not give access to all the registers
+    /// implemented according to `GICH_VTR.PREbits`.
+    pub fn restore_apr(&self, state: &AprState) -> AxResult {
+        let vtr = self.host_vtr()?;
+        let state = if state.pre_bits == vtr.pre_bits {
+            *state
+        } else {
+            state.convert(vtr.pre_bits)?
+        };
+        for n in 0..vtr.apr_count() {
+            self.host.write_ap0r(n, state.ap0r[n]);
+            self.host.write_apr(n, state.ap1r[n]);
+        }
+        Ok(())
+    }
+
+    /// Returns the `GICH_VTR` of the host virtual CPU interface, checking that all the
+    /// active priority registers it implements are accessible.
+    fn host_vtr(&self) -> AxResult<VtrInfo> {
+        let vtr = self.vtr.ok_or(ax_err_type!(
+            Unsupported,
+            "the software CPU interface has no host registers"
+        ))?;
+        if self.host.apr_count() < vtr.apr_count() {
+            return ax_err!(
+                Unsupported,
+                "host does not give access to all its active priority registers"
+            );
+        }
+        Ok(vtr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::hal::GichOps;
+    use crate::mock::{self, MockHost};
+
+    fn state(pre_bits: u8, ap0r: [u32; 4], ap1r: [u32; 4]) -> AprState {
+        AprState {
+            pre_bits,
+            ap0r,
+            ap1r,
+        }
+    }
+
+    #[test]
+    fn up_conversion_spreads_levels() {
+        // Levels 0, 5 and 31 of 32 map to levels 0, 10 and 62 of 64, and 0, 20 and 124
+        // of 128.
+        let saved = state(5, [0x8000_0021, 0, 0, 0], [0x2, 0, 0, 0]);
+        assert_eq!(
+            saved.convert(6).unwrap(),
+            state(6, [0x0000_0401, 0x4000_0000, 0, 0], [0x4, 0, 0, 0])
+        );
+        assert_eq!(
+            saved.convert(7).unwrap(),
+            state(7, [0x0010_0001, 0, 0, 0x1000_0000], [0x10, 0, 0, 0])
+        );
+        assert_eq!(saved.convert(5).unwrap(), saved);
+    }
+
+    #[test]
+    fn aligned_down_conversion_merges_nothing() {
+        let saved = state(7, [0x0010_0001, 0, 0, 0x1000_0000], [0x10, 0, 0, 0]);
+        assert_eq!(
+            saved.convert(5).unwrap(),
+            state(5, [0x8000_0021, 0, 0, 0], [0x2, 0, 0, 0])
+        );
+        assert_eq!(
+            saved.convert(6).unwrap(),
+            state(6, [0x0000_0401, 0x4000_0000, 0, 0], [0x4, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn lossy_down_conversion_is_rejected() {
+        // Level 1 of 64 falls between levels 0 and 1 of 32.
+        let saved = state(6, [0, 0, 0, 0], [0x2, 0, 0, 0]);
+        assert!(saved.convert(5).is_err());
+        // Level 73 of 128 falls between levels 36 and 37 of 64.
+        let saved = state(7, [0, 0, 0x200, 0], [0, 0, 0, 0]);
+        assert!(saved.convert(6).is_err());
+    }
+
+    #[test]
+    fn invalid_states_are_rejected() {
+        assert!(state(4, [0; 4], [0; 4]).convert(5).is_err());
+        assert!(state(5, [0; 4], [0; 4]).convert(8).is_err());
+        // Only one register is implemented with 5 preemption bits.
+        assert!(state(5, [0, 1, 0, 0], [0; 4]).convert(7).is_err());
+    }
+
+    fn vtr(pre_bits: u8) -> VtrInfo {
+        VtrInfo {
+            pre_bits,
+            pri_bits: 8,
+            ..MockHost::default_vtr()
+        }
+    }
+
+    #[test]
+    fn save_and_restore_all_implemented_registers() {
+        let (vgic, host) = mock::vgic_with_vtr(Vgicv3Config::default(), vtr(7));
+        for n in 0..4 {
+            host.write_ap0r(n, 0x10 << n);
+            host.write_apr(n, 0x100 << n);
+        }
+        let saved = vgic.save_apr().unwrap();
+        assert_eq!(
+            saved,
+            state(7, [0x10, 0x20, 0x40, 0x80], [0x100, 0x200, 0x400, 0x800])
+        );
+
+        for n in 0..4 {
+            host.write_ap0r(n, 0);
+            host.write_apr(n, 0);
+        }
+        vgic.restore_apr(&saved).unwrap();
+        let regs = host.regs.lock();
+        assert_eq!(regs.ap0r, saved.ap0r);
+        assert_eq!(regs.apr, saved.ap1r);
+    }
+
+    #[test]
+    fn restore_converts_between_hosts() {
+        let (vgic, host) = mock::vgic_with_vtr(Vgicv3Config::default(), vtr(6));
+        vgic.restore_apr(&state(5, [0x8000_0001, 0, 0, 0], [0; 4]))
+            .unwrap();
+        assert_eq!(host.regs.lock().ap0r, [0x1, 0x4000_0000, 0, 0]);
+
+        host.write_apr(0, 0xdead);
+        assert!(vgic.restore_apr(&state(7, [0x2, 0, 0, 0], [0; 4])).is_err());
+        assert_eq!(host.read_apr(0), 0xdead);
+    }
+
+    #[test]
+    fn hosts_without_all_registers_are_refused() {
+        let (vgic, host) = mock::vgic_with_vtr(Vgicv3Config::default(), vtr(7));
+        host.regs.lock().apr_count = 1;
+        assert!(vgic.save_apr().is_err());
+        assert!(vgic.restore_apr(&AprState::default()).is_err());
+
+        let (vgic, host) = mock::vgic_with_vtr(Vgicv3Config::default(), vtr(5));
+        host.regs.lock().apr_count = 1;
+        assert_eq!(vgic.save_apr().unwrap().pre_bits, 5);
+    }
+
+    #[test]
+    fn software_interface_has_no_host_registers() {
+        let config = Vgicv3Config {
+            software_cpu_interface: true,
+            ..Default::default()
+        };
+        let (vgic, _host) = mock::vgic(config);
+        assert!(vgic.save_apr().is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Capabilities of the host virtual CPU interface.
+
+use axerrno::{AxResult, ax_err};
+
+use crate::config::Vgicv3Config;
+use crate::consts::{GIC_PRIORITY_MASK, GICH_LR_INTID_LIMIT, GICH_PRE_BITS};
+use crate::hal::GichOps;
+use crate::regs::gich::VtrInfo;
+
+/// Features of the host virtual CPU interface a virtual machine can rely on.
+///
+/// Probed with [`HostGicCaps::probe`] before creating a [`Vgicv3`](crate::Vgicv3), and
+/// checked against its configuration with [`HostGicCaps::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostGicCaps {
+    /// Number of implemented List registers (`GICH_VTR.ListRegs + 1`).
+    pub list_regs: usize,
+    /// Number of virtual priority bits (`GICH_VTR.PRIbits + 1`).
+    pub pri_bits: u8,
+    /// Number of virtual preemption bits (`GICH_VTR.PREbits + 1`).
+    pub pre_bits: u8,
+    /// Number of physical INTID bits supported: 16 or 24 (`GICH_VTR.IDbits`).
+    pub id_bits: u8,
+    /// The CPU interface supports local generation of SEIs (`GICH_VTR.SEIS`).
+    pub seis: bool,
+    /// The virtual CPU interface supports non-zero Aff3 values (`GICH_VTR.A3V`).
+    pub a3v: bool,
+    /// The List registers implement the NMI attribute, see [`GichOps::supports_nmi`].
+    pub nmi: bool,
+    /// The host implements legacy operation, see [`GichOps::gicv_base`].
+    pub legacy: bool,
+    /// The List registers are accessible as `ICH_LR<n>_EL2`, see
+    /// [`GichOps::supports_ich_lr`].
+    pub ich_lr: bool,
+}
+
+impl HostGicCaps {
+    /// Probes the virtual CPU interface of the physical CPU the caller is running on.
+    ///
+    /// # Returns
+    /// - `Ok(HostGicCaps)` on success
+    /// - `Err(AxError::InvalidData)` if `GICH_VTR` holds a reserved or inconsistent value
+    pub fn probe(host: &dyn GichOps) -> AxResult<HostGicCaps> {
+        Self::from_vtr(VtrInfo::from(host.read_vtr()), host)
+    }
+
+    /// Builds the capabilities from an already decoded `GICH_VTR`.
+    pub(crate) fn from_vtr(vtr: VtrInfo, host: &dyn GichOps) -> AxResult<HostGicCaps> {
+        let id_bits = match vtr.id_bits {
+            0 => 16,
+            1 => 24,
+            _ => return ax_err!(InvalidData, "reserved GICH_VTR.IDbits value"),
+        };
+        if !GICH_PRE_BITS.contains(&vtr.pre_bits) {
+            return ax_err!(InvalidData, "GICH_VTR.PREbits out of range");
+        }
+        if vtr.pre_bits > vtr.pri_bits {
+            return ax_err!(
+                InvalidData,
+                "GICH_VTR reports more preemption bits than priority bits"
+            );
+        }
+        Ok(HostGicCaps {
+            list_regs: vtr.list_regs,
+            pri_bits: vtr.pri_bits,
+            pre_bits: vtr.pre_bits,
+            id_bits,
+            seis: vtr.seis,
+            a3v: vtr.a3v,
+            nmi: host.supports_nmi(),
+            legacy: host.gicv_base().is_some(),
+            ich_lr: host.supports_ich_lr(),
+        })
+    }
+
+    /// Checks that a virtual GIC with the given configuration can be emulated with this
+    /// host virtual CPU interface.
+    ///
+    /// The software CPU interface does not use the host interface, so any configuration
+    /// requesting it passes. LPIs are out of scope: the distributor never advertises them
+    /// (`GICD_TYPER.LPIS` is 0) and virtual interrupts are never linked to physical ones,
+    /// so the guest's INTIDs are bounded by the `vINTID` field of the List registers
+    /// rather than by `id_bits`: Extended SPIs and PPIs need `ICH_LR<n>_EL2`.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the configuration can be emulated
+    /// - `Err(AxError::Unsupported)` naming the first feature the host lacks
+    pub fn validate(&self, config: &Vgicv3Config) -> AxResult {
+        if config.software_cpu_interface {
+            return Ok(());
+        }
+        if self.list_regs == 0 {
+            return ax_err!(Unsupported, "host implements no List registers");
+        }
+        if self.pri_bits < GIC_PRIORITY_MASK.count_ones() as u8 {
+            return ax_err!(
+                Unsupported,
+                "host implements fewer virtual priority bits than the vGIC presents"
+            );
+        }
+        if 1usize << config.intid_bits() > GICH_LR_INTID_LIMIT && !self.ich_lr {
+            return ax_err!(
+                Unsupported,
+                "extended INTIDs do not fit in GICH_LR and the host gives no access to ICH_LR"
+            );
+        }
+        if config.aff3 != 0 && !self.a3v {
+            return ax_err!(
+                Unsupported,
+                "vCPU affinities use Aff3, which the host virtual CPU interface does not support"
+            );
+        }
+        if config.nmi && !self.nmi {
+            return ax_err!(
+                Unsupported,
+                "host GIC does not support NMIs in List registers"
+            );
+        }
+        if config.supports_legacy() && !self.legacy {
+            return ax_err!(Unsupported, "host GIC does not implement legacy operation");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockHost;
+
+    fn caps() -> HostGicCaps {
+        HostGicCaps {
+            list_regs: 4,
+            pri_bits: 5,
+            pre_bits: 5,
+            id_bits: 16,
+            seis: false,
+            a3v: false,
+            nmi: false,
+            legacy: false,
+            ich_lr: false,
+        }
+    }
+
+    #[test]
+    fn probe_decodes_vtr() {
+        let vtr = VtrInfo {
+            list_regs: 16,
+            pre_bits: 6,
+            pri_bits: 7,
+            id_bits: 1,
+            seis: true,
+            a3v: true,
+        };
+        let caps = HostGicCaps::probe(&*MockHost::new(vtr)).unwrap();
+        assert_eq!(
+            caps,
+            HostGicCaps {
+                list_regs: 16,
+                pri_bits: 7,
+                pre_bits: 6,
+                id_bits: 24,
+                seis: true,
+                a3v: true,
+                nmi: false,
+                legacy: false,
+                ich_lr: false,
+            }
+        );
+    }
+
+    #[test]
+    fn probe_rejects_inconsistent_vtr() {
+        let probe = |vtr| HostGicCaps::probe(&*MockHost::new(vtr));
+        let vtr = MockHost::default_vtr();
+        assert!(probe(VtrInfo { id_bits: 2, ..vtr }).is_err());
+        assert!(
+            probe(VtrInfo {
+                pre_bits: 4,
+                pri_bits: 4,
+                ..vtr
+            })
+            .is_err()
+        );
+        assert!(probe(VtrInfo { pre_bits: 6, ..vtr }).is_err());
+    }
+
+    #[test]
+    fn validate_accepts_supported_configurations() {
+        assert_eq!(caps().validate(&Vgicv3Config::default()), Ok(()));
+        let config = Vgicv3Config {
+            aff3: 1,
+            nmi: true,
+            legacy_support: true,
+            ..Default::default()
+        };
+        let caps = HostGicCaps {
+            a3v: true,
+            nmi: true,
+            legacy: true,
+            ..caps()
+        };
+        assert_eq!(caps.validate(&config), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_missing_list_registers() {
+        let caps = HostGicCaps {
+            list_regs: 0,
+            ..caps()
+        };
+        assert!(caps.validate(&Vgicv3Config::default()).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_missing_priority_bits() {
+        let caps = HostGicCaps {
+            pri_bits: 4,
+            pre_bits: 4,
+            ..caps()
+        };
+        assert!(caps.validate(&Vgicv3Config::default()).is_err());
+    }
+
+    #[test]
+    fn validate_accepts_extended_intids_with_ich_lr() {
+        let config = Vgicv3Config {
+            espi_num: 1024,
+            eppi_num: 64,
+            ..Default::default()
+        };
+        let caps = HostGicCaps {
+            ich_lr: true,
+            ..caps()
+        };
+        assert_eq!(caps.validate(&config), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_extended_intids_without_ich_lr() {
+        for (espi_num, eppi_num) in [(32, 0), (0, 32)] {
+            let config = Vgicv3Config {
+                espi_num,
+                eppi_num,
+                ..Default::default()
+            };
+            assert!(caps().validate(&config).is_err());
+            // Whatever the number of physical INTID bits.
+            let caps = HostGicCaps {
+                id_bits: 24,
+                ..caps()
+            };
+            assert!(caps.validate(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn validate_rejects_aff3_without_a3v() {
+        let config = Vgicv3Config {
+            aff3: 2,
+            ..Default::default()
+        };
+        assert!(caps().validate(&config).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_nmi_and_legacy_without_host_support() {
+        let nmi = Vgicv3Config {
+            nmi: true,
+            ..Default::default()
+        };
+        assert!(caps().validate(&nmi).is_err());
+        let legacy = Vgicv3Config {
+            legacy_support: true,
+            ..Default::default()
+        };
+        assert!(caps().validate(&legacy).is_err());
+    }
+
+    #[test]
+    fn software_interface_needs_no_host_feature() {
+        let config = Vgicv3Config {
+            software_cpu_interface: true,
+            aff3: 2,
+            espi_num: 32,
+            ..Default::default()
+        };
+        let caps = HostGicCaps {
+            list_regs: 0,
+            ..caps()
+        };
+        assert_eq!(caps.validate(&config), Ok(()));
+    }
+}
@@ -0,0 +1,149 @@
+use axaddrspace::GuestPhysAddr;
+
+/// Architecture version of the interrupt controller presented to the guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GicVersion {
+    /// GICv2 compatibility mode.
+    ///
+    /// The guest sees a GICv2 distributor (`GICD_ITARGETSR`, `GICD_SGIR`, CPU target lists)
+    /// and a GICv2 CPU interface, which is backed directly by the host GICV frame. This
+    /// requires the host GICv3 to implement legacy operation (FEAT_GICv3_LEGACY).
+    V2,
+    /// Native GICv3.
+    ///
+    /// Affinity routing is enabled unless [`Vgicv3Config::legacy_support`] allows the guest
+    /// to clear `GICD_CTLR.ARE`.
+    V3,
+}
+
+/// Security states implemented by the virtual GIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityModel {
+    /// A single security state (`GICD_CTLR.DS == 1`).
+    ///
+    /// Interrupts are either Group 0 or Group 1 and every access can configure both.
+    SingleSecurityState,
+    /// Two security states (`GICD_CTLR.DS == 0`).
+    ///
+    /// Interrupts are Group 0, Secure Group 1 or Non-secure Group 1. Accesses are Non-secure
+    /// unless [`VgicHostOps::current_access_secure`](crate::VgicHostOps::current_access_secure)
+    /// reports otherwise, and Non-secure accesses cannot observe Secure interrupts.
+    TwoSecurityStates,
+}
+
+/// Static configuration of a [`Vgicv3`](crate::Vgicv3) instance.
+#[derive(Debug, Clone)]
+pub struct Vgicv3Config {
+    /// Architecture version presented to the guest.
+    pub version: GicVersion,
+    /// Security states implemented by the distributor.
+    pub security: SecurityModel,
+    /// Number of vCPUs of the virtual machine.
+    pub vcpu_num: usize,
+    /// Aff3 field of the affinity of every vCPU, see [`Vgicv3Config::vcpu_affinity`].
+    ///
+    /// A non-zero value is advertised with `GICD_TYPER.A3V`, and requires a host whose
+    /// virtual CPU interface supports Aff3 (`GICH_VTR.A3V`) unless the CPU interface is
+    /// emulated in software.
+    pub aff3: u8,
+    /// Number of SPIs implemented by the distributor, rounded up to a multiple of 32.
+    pub spi_num: usize,
+    /// Number of Extended SPIs (INTIDs from 4096), a multiple of 32 up to 1024.
+    ///
+    /// Requires [`GicVersion::V3`], and either the software CPU interface or a host giving
+    /// access to `ICH_LR<n>_EL2` ([`GichOps::supports_ich_lr`](crate::GichOps::supports_ich_lr)):
+    /// extended INTIDs do not fit in the `vINTID` field of `GICH_LR<n>`.
+    pub espi_num: usize,
+    /// Number of Extended PPIs per vCPU (INTIDs from 1056): 0, 32 or 64.
+    ///
+    /// Requires [`GicVersion::V3`] and the same host support as `espi_num`.
+    pub eppi_num: usize,
+    /// Whether interrupts can be configured as non-maskable (GICv3.3 NMI).
+    ///
+    /// Requires [`GicVersion::V3`] and a host whose List registers implement the NMI
+    /// attribute, see [`GichOps::supports_nmi`](crate::GichOps::supports_nmi).
+    pub nmi: bool,
+    /// Whether SPIs can be generated by writes to `GICD_SETSPI_NSR` and `GICD_CLRSPI_NSR`
+    /// (`GICD_TYPER.MBIS`), e.g. for MSIs without an ITS. Requires [`GicVersion::V3`].
+    pub mbis: bool,
+    /// Guest physical base address of the distributor frame.
+    pub gicd_base: GuestPhysAddr,
+    /// Guest physical base address of the first redistributor, used in [`GicVersion::V3`] mode.
+    pub gicr_base: GuestPhysAddr,
+    /// Guest physical base address of the GICv2 CPU interface, used in [`GicVersion::V2`]
+    /// mode and for legacy operation.
+    pub gicc_base: GuestPhysAddr,
+    /// Whether a [`GicVersion::V3`] distributor supports legacy operation.
+    ///
+    /// If set, `GICD_CTLR.ARE` resets to 0 and the guest may enable affinity routing itself;
+    /// until it does, the distributor behaves as a GICv2 distributor and the CPU interface is
+    /// the host GICV frame, as in [`GicVersion::V2`] mode. Otherwise `ARE` is RAO/WI.
+    pub legacy_support: bool,
+    /// Whether the CPU interface is emulated in software instead of through the host
+    /// List registers, for hosts without the virtualization extensions of the GIC.
+    ///
+    /// The hypervisor traps the guest's `ICC_*_EL1` accesses and forwards them to
+    /// [`Vgicv3::handle_icc_read`](crate::Vgicv3::handle_icc_read) and
+    /// [`Vgicv3::handle_icc_write`](crate::Vgicv3::handle_icc_write), and signals the
+    /// exception reported by
+    /// [`Vgicv3::pending_exception`](crate::Vgicv3::pending_exception) to the vCPU. The
+    /// [`GichOps`](crate::GichOps) methods of the host are never called. Requires
+    /// [`GicVersion::V3`] without legacy support or NMIs.
+    pub software_cpu_interface: bool,
+}
+
+impl Default for Vgicv3Config {
+    fn default() -> Self {
+        Self {
+            version: GicVersion::V3,
+            security: SecurityModel::SingleSecurityState,
+            vcpu_num: 1,
+            aff3: 0,
+            spi_num: 64,
+            espi_num: 0,
+            eppi_num: 0,
+            nmi: false,
+            mbis: false,
+            gicd_base: GuestPhysAddr::from(0x800_0000),
+            gicr_base: GuestPhysAddr::from(0x80a_0000),
+            gicc_base: GuestPhysAddr::from(0x801_0000),
+            legacy_support: false,
+            software_cpu_interface: false,
+        }
+    }
+}
+
+impl Vgicv3Config {
+    /// Returns the total number of interrupt IDs covered by the distributor (SGIs, PPIs and SPIs).
+    pub fn irq_num(&self) -> usize {
+        crate::consts::SPI_ID_BASE + self.spi_num.next_multiple_of(32)
+    }
+
+    /// Returns whether the guest can operate the distributor without affinity routing.
+    pub fn supports_legacy(&self) -> bool {
+        match self.version {
+            GicVersion::V2 => true,
+            GicVersion::V3 => self.legacy_support,
+        }
+    }
+
+    /// Returns the number of interrupt ID bits reported in `GICD_TYPER.IDbits`.
+    pub(crate) fn intid_bits(&self) -> u32 {
+        if self.espi_num != 0 {
+            13
+        } else if self.eppi_num != 0 {
+            11
+        } else {
+            10
+        }
+    }
+
+    /// Returns the `MPIDR_EL1` affinity value of the given vCPU.
+    ///
+    /// vCPUs are laid out with up to 16 PEs per Aff1 cluster, so that every vCPU can be
+    /// addressed by the 16-bit target list of `ICC_SGI1R_EL1`, below the configured
+    /// [`Vgicv3Config::aff3`].
+    pub fn vcpu_affinity(&self, vcpu_id: usize) -> u64 {
+        (self.aff3 as u64) << 32 | (((vcpu_id >> 4) & 0xff) << 8 | (vcpu_id & 0xf)) as u64
+    }
+}
@@ -0,0 +1,179 @@
+//! Interrupt ID ranges and distributor register offsets used by the emulator.
+
+use core::mem::offset_of;
+
+use crate::regs::{GicdRegs, GicrRdRegs, GicrSgiRegs};
+
+/// Number of Software Generated Interrupts (INTIDs 0-15).
+pub const SGI_NUM: usize = 16;
+/// Number of private interrupts (SGIs and PPIs, INTIDs 0-31) banked per vCPU.
+pub const PRIVATE_IRQ_NUM: usize = 32;
+/// First Shared Peripheral Interrupt ID.
+pub const SPI_ID_BASE: usize = 32;
+/// One past the largest Shared Peripheral Interrupt ID (INTIDs 1020-1023 are special).
+pub const SPI_ID_MAX: usize = 1020;
+/// First Extended PPI ID (GICv3.1).
+pub const EPPI_ID_BASE: usize = 1056;
+/// Maximum number of Extended PPIs per redistributor.
+pub const EPPI_NUM_MAX: usize = 64;
+/// First Extended SPI ID (GICv3.1).
+pub const ESPI_ID_BASE: usize = 4096;
+/// Maximum number of Extended SPIs.
+pub const ESPI_NUM_MAX: usize = 1024;
+/// One past the largest INTID a `GICH_LR<n>.vINTID` field can hold.
+pub const GICH_LR_INTID_LIMIT: usize = 1024;
+
+/// Maximum number of List registers a GICH frame can implement.
+pub const GICH_LR_NUM_MAX: usize = 16;
+/// Maximum number of active priority registers of each group.
+pub const GICH_APR_NUM_MAX: usize = 4;
+/// Range of the number of virtual preemption bits a host can implement.
+pub const GICH_PRE_BITS: core::ops::RangeInclusive<u8> = 5..=7;
+/// Number of line level events each vCPU can hold for `Vgicv3::post_irq_level`.
+pub const INJECT_QUEUE_LEN: usize = 64;
+/// Maximum number of CPU interfaces addressable through GICv2 target lists.
+pub const GICV2_CPU_NUM_MAX: usize = 8;
+
+/// Size of the emulated distributor frame.
+pub const GICD_FRAME_SIZE: usize = 0x10000;
+/// Size of the frames of a single redistributor (`RD_base` and `SGI_base`).
+pub const GICR_STRIDE: usize = 0x20000;
+/// Offset of the `SGI_base` frame within a redistributor.
+pub const GICR_SGI_BASE: usize = 0x10000;
+/// Size of a GICv2 CPU interface (and of the host GICV frame backing it).
+pub const GICC_FRAME_SIZE: usize = 0x2000;
+
+/// Size of a GICv2m MSI frame.
+pub const V2M_FRAME_SIZE: usize = 0x1000;
+/// Maximum number of SPIs `MSI_TYPER` can describe.
+pub const V2M_SPI_NUM_MAX: usize = 0x3ff;
+
+/// Value reported in `GICD_IIDR`: ARM as implementer, product ID 'V'.
+pub const VGIC_IIDR: u32 = 0x5600_043b;
+
+// Distributor register offsets, taken from the `GicdRegs` register block.
+pub const GICD_CTLR: usize = offset_of!(GicdRegs, CTLR);
+pub const GICD_TYPER: usize = offset_of!(GicdRegs, TYPER);
+pub const GICD_IIDR: usize = offset_of!(GicdRegs, IIDR);
+pub const GICD_SETSPI_NSR: usize = offset_of!(GicdRegs, SETSPI_NSR);
+pub const GICD_CLRSPI_NSR: usize = offset_of!(GicdRegs, CLRSPI_NSR);
+pub const GICD_SETSPI_SR: usize = offset_of!(GicdRegs, SETSPI_SR);
+pub const GICD_CLRSPI_SR: usize = offset_of!(GicdRegs, CLRSPI_SR);
+pub const GICD_IGROUPR: usize = offset_of!(GicdRegs, IGROUPR);
+pub const GICD_ISENABLER: usize = offset_of!(GicdRegs, ISENABLER);
+pub const GICD_ICENABLER: usize = offset_of!(GicdRegs, ICENABLER);
+pub const GICD_ISPENDR: usize = offset_of!(GicdRegs, ISPENDR);
+pub const GICD_ICPENDR: usize = offset_of!(GicdRegs, ICPENDR);
+pub const GICD_ISACTIVER: usize = offset_of!(GicdRegs, ISACTIVER);
+pub const GICD_ICACTIVER: usize = offset_of!(GicdRegs, ICACTIVER);
+pub const GICD_IPRIORITYR: usize = offset_of!(GicdRegs, IPRIORITYR);
+pub const GICD_ITARGETSR: usize = offset_of!(GicdRegs, ITARGETSR);
+pub const GICD_ICFGR: usize = offset_of!(GicdRegs, ICFGR);
+pub const GICD_IGRPMODR: usize = offset_of!(GicdRegs, IGRPMODR);
+pub const GICD_SGIR: usize = offset_of!(GicdRegs, SGIR);
+pub const GICD_CPENDSGIR: usize = offset_of!(GicdRegs, CPENDSGIR);
+pub const GICD_SPENDSGIR: usize = offset_of!(GicdRegs, SPENDSGIR);
+pub const GICD_INMIR: usize = offset_of!(GicdRegs, INMIR);
+pub const GICD_IROUTER: usize = offset_of!(GicdRegs, IROUTER);
+// Extended SPI register banks, with the layout of the corresponding regular banks.
+pub const GICD_IGROUPRNE: usize = offset_of!(GicdRegs, IGROUPRE);
+pub const GICD_ISENABLERNE: usize = offset_of!(GicdRegs, ISENABLERE);
+pub const GICD_ICENABLERNE: usize = offset_of!(GicdRegs, ICENABLERE);
+pub const GICD_ISPENDRNE: usize = offset_of!(GicdRegs, ISPENDRE);
+pub const GICD_ICPENDRNE: usize = offset_of!(GicdRegs, ICPENDRE);
+pub const GICD_ISACTIVERNE: usize = offset_of!(GicdRegs, ISACTIVERE);
+pub const GICD_ICACTIVERNE: usize = offset_of!(GicdRegs, ICACTIVERE);
+pub const GICD_IPRIORITYRNE: usize = offset_of!(GicdRegs, IPRIORITYRE);
+pub const GICD_ICFGRNE: usize = offset_of!(GicdRegs, ICFGRE);
+pub const GICD_IGRPMODRNE: usize = offset_of!(GicdRegs, IGRPMODRE);
+pub const GICD_INMIRNE: usize = offset_of!(GicdRegs, INMIRE);
+pub const GICD_IROUTERNE: usize = offset_of!(GicdRegs, IROUTERE);
+/// `GICD_PIDR2` in a GICv2 distributor frame.
+pub const GICD_PIDR2_V2: usize = 0x0fe8;
+/// `GICD_PIDR2` in a GICv3 distributor frame.
+pub const GICD_PIDR2_V3: usize = offset_of!(GicdRegs, PIDR2);
+
+// Redistributor register offsets, relative to `RD_base`, taken from `GicrRdRegs`.
+pub const GICR_CTLR: usize = offset_of!(GicrRdRegs, CTLR);
+pub const GICR_IIDR: usize = offset_of!(GicrRdRegs, IIDR);
+pub const GICR_TYPER: usize = offset_of!(GicrRdRegs, TYPER);
+pub const GICR_TYPER_HIGH: usize = GICR_TYPER + 4;
+pub const GICR_STATUSR: usize = offset_of!(GicrRdRegs, STATUSR);
+pub const GICR_WAKER: usize = offset_of!(GicrRdRegs, WAKER);
+pub const GICR_PIDR2: usize = offset_of!(GicrRdRegs, PIDR2);
+/// `GICR_NSACR`, relative to `SGI_base`.
+pub const GICR_NSACR: usize = offset_of!(GicrSgiRegs, NSACR);
+
+/// `GICR_TYPER.Last`: last redistributor of the region.
+pub const GICR_TYPER_LAST: u32 = 1 << 4;
+/// `GICR_WAKER.ProcessorSleep`.
+pub const GICR_WAKER_PROCESSOR_SLEEP: u32 = 1 << 1;
+/// `GICR_WAKER.ChildrenAsleep`.
+pub const GICR_WAKER_CHILDREN_ASLEEP: u32 = 1 << 2;
+
+// GICv2m MSI frame register offsets.
+pub const V2M_MSI_TYPER: usize = 0x0008;
+pub const V2M_MSI_SETSPI_NS: usize = 0x0040;
+pub const V2M_MSI_IIDR: usize = 0x0fcc;
+
+/// Value reported in `MSI_IIDR`: ARM as implementer, product ID 'M'.
+pub const V2M_IIDR: u32 = 0x4d00_043b;
+
+/// Size in bytes of each one-bit-per-interrupt register bank (`GICD_ISENABLER<n>` etc.).
+pub const GICD_BITMAP_BANK_SIZE: usize = 0x80;
+/// Size in bytes of each one-byte-per-interrupt register bank (`GICD_IPRIORITYR<n>` etc.).
+pub const GICD_BYTEMAP_BANK_SIZE: usize = 0x400;
+/// Size in bytes of the two-bits-per-interrupt `GICD_ICFGR<n>` bank.
+pub const GICD_ICFGR_BANK_SIZE: usize = 0x100;
+/// Size in bytes of the `GICD_CPENDSGIR<n>` and `GICD_SPENDSGIR<n>` banks.
+pub const GICD_SGI_PENDING_BANK_SIZE: usize = 0x10;
+/// Size in bytes of the `GICD_IROUTER<n>` bank.
+pub const GICD_IROUTER_BANK_SIZE: usize = 0x2000;
+
+// GICD_CTLR bits, in the layout of the Secure (or only) security state.
+pub const GICD_CTLR_ENABLE_GRP0: u32 = 1 << 0;
+pub const GICD_CTLR_ENABLE_GRP1NS: u32 = 1 << 1;
+pub const GICD_CTLR_ENABLE_GRP1S: u32 = 1 << 2;
+/// `ARE` with a single security state, `ARE_S` in the Secure view, `ARE_NS` in the
+/// Non-secure view.
+pub const GICD_CTLR_ARE: u32 = 1 << 4;
+pub const GICD_CTLR_ARE_S: u32 = 1 << 4;
+pub const GICD_CTLR_ARE_NS: u32 = 1 << 5;
+pub const GICD_CTLR_DS: u32 = 1 << 6;
+
+/// `GICD_TYPER.SecurityExtn`: two security states are implemented.
+pub const GICD_TYPER_SECURITY_EXTN: u32 = 1 << 10;
+/// `GICD_TYPER.ESPI`: the Extended SPI range is implemented.
+pub const GICD_TYPER_ESPI: u32 = 1 << 8;
+/// `GICD_TYPER.NMI`: non-maskable interrupts are supported.
+pub const GICD_TYPER_NMI: u32 = 1 << 9;
+/// `GICD_TYPER.MBIS`: message-based SPIs are supported.
+pub const GICD_TYPER_MBIS: u32 = 1 << 16;
+/// `GICD_TYPER.A3V`: the distributor supports non-zero Aff3 values.
+pub const GICD_TYPER_A3V: u32 = 1 << 24;
+/// `INTID` field of `GICD_SETSPI_NSR` and friends.
+pub const GICD_SETSPI_INTID_MASK: u32 = 0x1fff;
+
+/// Implemented priority bits, matching the 5-bit priority field of `GICH_LR<n>`.
+pub const GIC_PRIORITY_MASK: u8 = 0xf8;
+/// Number of low-order bits of a priority that are not implemented.
+pub const GIC_PRIORITY_SHIFT: u32 = 3;
+/// INTID returned by acknowledge and highest priority pending reads when no interrupt
+/// qualifies.
+pub const INTID_SPURIOUS: u32 = 1023;
+
+/// `ICC_CTLR_EL1.CBPR`: `ICC_BPR0_EL1` determines the preemption of Group 1 interrupts.
+pub const ICC_CTLR_CBPR: u64 = 1 << 0;
+/// `ICC_CTLR_EL1.EOImode`: EOIs only drop the running priority.
+pub const ICC_CTLR_EOIMODE: u64 = 1 << 1;
+/// `ICC_CTLR_EL1.PRIbits`, one less than the number of implemented priority bits.
+pub const ICC_CTLR_PRIBITS: u64 = 4 << 8;
+/// Minimum value of `ICC_BPR0_EL1` with 5 priority bits; `ICC_BPR1_EL1` is one more.
+pub const ICC_BPR0_MIN: u32 = 2;
+/// `INTID` field of `ICC_EOIR<n>_EL1` and `ICC_DIR_EL1`.
+pub const ICC_INTID_MASK: u64 = 0xff_ffff;
+
+/// `GICD_IROUTER.Interrupt_Routing_Mode`: route to any participating PE.
+pub const GICD_IROUTER_IRM: u64 = 1 << 31;
+/// Affinity fields (Aff3, Aff2, Aff1, Aff0) of `GICD_IROUTER` and `MPIDR_EL1`.
+pub const MPIDR_AFFINITY_MASK: u64 = 0xff_00ff_ffff;
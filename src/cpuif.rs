@@ -0,0 +1,561 @@
+//! Software emulation of the GICv3 CPU interface, for hosts without List registers.
+//!
+//! The priority mask, binary points, EOI mode and group enables of each vCPU are kept in
+//! the `GICH_VMCR` layout, and its active priorities in the `GICH_APR0` layout, so that
+//! the state reads the same as with the hardware interface.
+
+use alloc::vec::Vec;
+
+use axerrno::{AxResult, ax_err};
+use log::warn;
+use tock_registers::LocalRegisterCopy;
+
+use crate::config::SecurityModel;
+use crate::consts::*;
+use crate::interrupt::IrqGroup;
+use crate::regs::gich::GICH_VMCR;
+use crate::vcpu::CpuLists;
+use crate::vgicv3::{Access, Vgicv3};
+
+/// CPU interface system registers emulated by the software CPU interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IccReg {
+    /// `ICC_IAR0_EL1`, read-only.
+    Iar0,
+    /// `ICC_IAR1_EL1`, read-only.
+    Iar1,
+    /// `ICC_EOIR0_EL1`, write-only.
+    Eoir0,
+    /// `ICC_EOIR1_EL1`, write-only.
+    Eoir1,
+    /// `ICC_DIR_EL1`, write-only.
+    Dir,
+    /// `ICC_RPR_EL1`, read-only.
+    Rpr,
+    /// `ICC_HPPIR0_EL1`, read-only.
+    Hppir0,
+    /// `ICC_HPPIR1_EL1`, read-only.
+    Hppir1,
+    /// `ICC_PMR_EL1`.
+    Pmr,
+    /// `ICC_BPR0_EL1`.
+    Bpr0,
+    /// `ICC_BPR1_EL1`.
+    Bpr1,
+    /// `ICC_CTLR_EL1`.
+    Ctlr,
+    /// `ICC_IGRPEN0_EL1`.
+    Igrpen0,
+    /// `ICC_IGRPEN1_EL1`.
+    Igrpen1,
+}
+
+/// A pending interrupt selected by the software CPU interface.
+#[derive(Debug, Clone, Copy)]
+struct Hppi {
+    intid: u32,
+    priority: u8,
+    group: IrqGroup,
+}
+
+impl CpuLists {
+    /// Returns a copy of the `GICH_VMCR` view of the software CPU interface.
+    fn vmcr(&self) -> LocalRegisterCopy<u32, GICH_VMCR::Register> {
+        LocalRegisterCopy::new(self.vmcr)
+    }
+
+    /// Returns the running priority: the priority of the highest preemption level with an
+    /// active interrupt, or `0xff` if there is none.
+    fn running_priority(&self) -> u8 {
+        match self.apr[0] | self.apr[1] {
+            0 => 0xff,
+            active => (active.trailing_zeros() << GIC_PRIORITY_SHIFT) as u8,
+        }
+    }
+
+    /// Returns the group priority of `priority`, the part of it that determines
+    /// preemption, according to the binary point of the group of the interrupt.
+    fn group_priority(&self, priority: u8, group1: bool) -> u8 {
+        let vmcr = self.vmcr();
+        let shift = if group1 && !vmcr.is_set(GICH_VMCR::VCBPR) {
+            vmcr.read(GICH_VMCR::VBPR1)
+        } else {
+            vmcr.read(GICH_VMCR::VBPR0) + 1
+        };
+        priority & (0xffu32 << shift) as u8
+    }
+
+    /// Returns whether the CPU interface forwards interrupts of `group`.
+    fn group_enabled(&self, group: IrqGroup) -> bool {
+        match group {
+            IrqGroup::Group0 => self.vmcr().is_set(GICH_VMCR::VENG0),
+            _ => self.vmcr().is_set(GICH_VMCR::VENG1),
+        }
+    }
+
+    /// Returns whether `hppi` has sufficient priority to be signalled: higher than the
+    /// priority mask and, by group priority, than the running priority.
+    fn can_signal(&self, hppi: &Hppi) -> bool {
+        let priority_mask = self.vmcr().read(GICH_VMCR::VPMR) as u8;
+        let group1 = hppi.group != IrqGroup::Group0;
+        hppi.priority < priority_mask
+            && self.group_priority(hppi.priority, group1) < self.running_priority()
+    }
+}
+
+impl Vgicv3 {
+    /// Emulates a read of a CPU interface register by the current vCPU.
+    ///
+    /// Reads of `ICC_IAR<n>_EL1` acknowledge the highest priority pending interrupt if it
+    /// belongs to the group of the register and has sufficient priority, and return 1023
+    /// otherwise. `ICC_HPPIR<n>_EL1` likewise read 1023 if the highest priority pending
+    /// interrupt belongs to another group.
+    ///
+    /// # Returns
+    /// - `Ok(u64)` containing the register value on success
+    /// - `Err(AxError)` if the software CPU interface is not in use or the register is
+    ///   write-only, in which case the access is UNDEFINED
+    pub fn handle_icc_read(&self, reg: IccReg) -> AxResult<u64> {
+        let access = self.soft_access()?;
+        let mut lists = self.cpus[access.vcpu_id].lists.lock();
+        let vmcr = lists.vmcr();
+        let value = match reg {
+            IccReg::Iar0 | IccReg::Iar1 => {
+                let group = self.icc_group(access, reg == IccReg::Iar1);
+                self.acknowledge(access.vcpu_id, &mut lists, group) as u64
+            }
+            IccReg::Hppir0 | IccReg::Hppir1 => {
+                let group = self.icc_group(access, reg == IccReg::Hppir1);
+                self.soft_hppi(access.vcpu_id, &mut lists)
+                    .filter(|hppi| hppi.group == group)
+                    .map_or(INTID_SPURIOUS, |hppi| hppi.intid) as u64
+            }
+            IccReg::Rpr => lists.running_priority() as u64,
+            IccReg::Pmr => vmcr.read(GICH_VMCR::VPMR) as u64,
+            IccReg::Bpr0 => vmcr.read(GICH_VMCR::VBPR0) as u64,
+            IccReg::Bpr1 if vmcr.is_set(GICH_VMCR::VCBPR) => {
+                (vmcr.read(GICH_VMCR::VBPR0) + 1).min(7) as u64
+            }
+            IccReg::Bpr1 => vmcr.read(GICH_VMCR::VBPR1) as u64,
+            IccReg::Ctlr => {
+                let mut ctlr = ICC_CTLR_PRIBITS;
+                if vmcr.is_set(GICH_VMCR::VCBPR) {
+                    ctlr |= ICC_CTLR_CBPR;
+                }
+                if vmcr.is_set(GICH_VMCR::VEOIM) {
+                    ctlr |= ICC_CTLR_EOIMODE;
+                }
+                ctlr
+            }
+            IccReg::Igrpen0 => vmcr.read(GICH_VMCR::VENG0) as u64,
+            IccReg::Igrpen1 => vmcr.read(GICH_VMCR::VENG1) as u64,
+            IccReg::Eoir0 | IccReg::Eoir1 | IccReg::Dir => {
+                return ax_err!(InvalidInput, "read of a write-only CPU interface register");
+            }
+        };
+        vgic_trace!("vgicv3: vCPU {} {reg:?} -> {value:#x}", access.vcpu_id);
+        Ok(value)
+    }
+
+    /// Emulates a write to a CPU interface register by the current vCPU.
+    ///
+    /// Writes to `ICC_EOIR<n>_EL1` drop the running priority and, unless
+    /// `ICC_CTLR_EL1.EOImode` is set, deactivate the interrupt; writes to `ICC_DIR_EL1`
+    /// deactivate it.
+    ///
+    /// # Returns
+    /// - `Ok(())` on success
+    /// - `Err(AxError)` if the software CPU interface is not in use or the register is
+    ///   read-only, in which case the access is UNDEFINED
+    pub fn handle_icc_write(&self, reg: IccReg, value: u64) -> AxResult {
+        let access = self.soft_access()?;
+        vgic_trace!("vgicv3: vCPU {} {reg:?} <- {value:#x}", access.vcpu_id);
+        let intid = (value & ICC_INTID_MASK) as u32;
+        let mut lists = self.cpus[access.vcpu_id].lists.lock();
+        let mut vmcr = lists.vmcr();
+        let deactivate = match reg {
+            IccReg::Eoir0 | IccReg::Eoir1 => {
+                Self::drop_priority(&mut lists, reg == IccReg::Eoir1);
+                !vmcr.is_set(GICH_VMCR::VEOIM)
+            }
+            IccReg::Dir if !vmcr.is_set(GICH_VMCR::VEOIM) => {
+                warn!("vgicv3: ICC_DIR_EL1 write with EOImode == 0");
+                false
+            }
+            IccReg::Dir => true,
+            IccReg::Pmr => {
+                let priority_mask = value as u8 & GIC_PRIORITY_MASK;
+                vmcr.modify(GICH_VMCR::VPMR.val(priority_mask as u32));
+                false
+            }
+            IccReg::Bpr0 => {
+                vmcr.modify(GICH_VMCR::VBPR0.val((value as u32 & 0x7).max(ICC_BPR0_MIN)));
+                false
+            }
+            IccReg::Bpr1 if vmcr.is_set(GICH_VMCR::VCBPR) => false,
+            IccReg::Bpr1 => {
+                let bpr1 = (value as u32 & 0x7).max(ICC_BPR0_MIN + 1);
+                vmcr.modify(GICH_VMCR::VBPR1.val(bpr1));
+                false
+            }
+            IccReg::Ctlr => {
+                vmcr.modify(
+                    GICH_VMCR::VCBPR.val((value & ICC_CTLR_CBPR != 0) as u32)
+                        + GICH_VMCR::VEOIM.val((value & ICC_CTLR_EOIMODE != 0) as u32),
+                );
+                false
+            }
+            IccReg::Igrpen0 => {
+                vmcr.modify(GICH_VMCR::VENG0.val(value as u32 & 1));
+                false
+            }
+            IccReg::Igrpen1 => {
+                vmcr.modify(GICH_VMCR::VENG1.val(value as u32 & 1));
+                false
+            }
+            IccReg::Iar0 | IccReg::Iar1 | IccReg::Hppir0 | IccReg::Hppir1 | IccReg::Rpr => {
+                return ax_err!(InvalidInput, "write of a read-only CPU interface register");
+            }
+        };
+        lists.vmcr = vmcr.get();
+        drop(lists);
+
+        if deactivate {
+            self.soft_deactivate(access.vcpu_id, intid);
+        }
+        if matches!(reg, IccReg::Igrpen0 | IccReg::Igrpen1) {
+            let mut kicks = Vec::new();
+            self.set_group_enables(access.vcpu_id, vmcr, &mut kicks);
+            self.kick_vcpus(&kicks);
+        }
+        Ok(())
+    }
+
+    /// Returns the group of the highest priority pending interrupt of `vcpu_id`, if it has
+    /// sufficient priority to be signalled.
+    ///
+    /// The hypervisor must signal a virtual FIQ for Group 0 interrupts and, to a
+    /// Non-secure guest, Secure Group 1 interrupts, and a virtual IRQ otherwise. It must
+    /// re-evaluate the exception after each exit, as any emulated access may change it.
+    /// Events posted with [`Vgicv3::post_irq_level`] are only taken into account after
+    /// [`Vgicv3::flush_lrs`] has applied them.
+    pub fn pending_exception(&self, vcpu_id: usize) -> Option<IrqGroup> {
+        let mut lists = self.cpus.get(vcpu_id)?.lists.lock();
+        let hppi = self.soft_hppi(vcpu_id, &mut lists)?;
+        lists.can_signal(&hppi).then_some(hppi.group)
+    }
+
+    /// Describes the current access to the software CPU interface.
+    fn soft_access(&self) -> AxResult<Access> {
+        if !self.config.software_cpu_interface {
+            return ax_err!(Unsupported, "the CPU interface is not emulated in software");
+        }
+        let access = self.current_access();
+        if access.vcpu_id >= self.config.vcpu_num {
+            return ax_err!(InvalidInput, "CPU interface access from an invalid vCPU");
+        }
+        Ok(access)
+    }
+
+    /// Returns the interrupt group handled by the Group 0 (`group1 == false`) or Group 1
+    /// registers for the security state of `access`.
+    fn icc_group(&self, access: Access, group1: bool) -> IrqGroup {
+        match self.config.security {
+            _ if !group1 => IrqGroup::Group0,
+            SecurityModel::TwoSecurityStates if access.secure => IrqGroup::Group1Secure,
+            _ => IrqGroup::Group1NonSecure,
+        }
+    }
+
+    /// Returns the highest priority pending interrupt of a vCPU among the groups its CPU
+    /// interface has enabled, or `None` if there is none.
+    ///
+    /// Interrupts that are no longer queued on the vCPU are dropped from its ap_list.
+    fn soft_hppi(&self, vcpu_id: usize, lists: &mut CpuLists) -> Option<Hppi> {
+        let enabled = [
+            lists.group_enabled(IrqGroup::Group0),
+            lists.group_enabled(IrqGroup::Group1NonSecure),
+        ];
+        let mut hppi: Option<Hppi> = None;
+        lists.ap_list.retain(|&intid| {
+            let Some(irq) = self.irq(vcpu_id, intid) else {
+                return false;
+            };
+            let irq = irq.lock();
+            if self.irq_target(vcpu_id, &irq) != Some(vcpu_id) {
+                return false;
+            }
+            let candidate = Hppi {
+                intid,
+                priority: irq.priority,
+                group: irq.group(),
+            };
+            // An active interrupt is not signalled again until it has been deactivated.
+            if !irq.active
+                && enabled[(candidate.group != IrqGroup::Group0) as usize]
+                && hppi.is_none_or(|best| (candidate.priority, intid) < (best.priority, best.intid))
+            {
+                hppi = Some(candidate);
+            }
+            true
+        });
+        hppi
+    }
+
+    /// Acknowledges the highest priority pending interrupt if it belongs to `group`,
+    /// returning its INTID, or 1023 if it belongs to another group, has insufficient
+    /// priority or there is none.
+    fn acknowledge(&self, vcpu_id: usize, lists: &mut CpuLists, group: IrqGroup) -> u32 {
+        let Some(hppi) = self.soft_hppi(vcpu_id, lists) else {
+            return INTID_SPURIOUS;
+        };
+        if hppi.group != group || !lists.can_signal(&hppi) {
+            return INTID_SPURIOUS;
+        }
+        let mut irq = self.irq(vcpu_id, hppi.intid).unwrap().lock();
+        irq.active = true;
+        irq.active_vcpu = vcpu_id;
+        irq.pending_latch = false;
+        if irq.is_sgi() && irq.sgi_sources != 0 {
+            irq.sgi_sources &= irq.sgi_sources - 1;
+        }
+        drop(irq);
+
+        let group1 = group != IrqGroup::Group0;
+        let level = lists.group_priority(hppi.priority, group1) >> GIC_PRIORITY_SHIFT;
+        lists.apr[group1 as usize] |= 1 << level;
+        vgic_trace!("vgicv3: vCPU {vcpu_id} acknowledged INTID {}", hppi.intid);
+        hppi.intid
+    }
+
+    /// Drops the running priority of a vCPU on an EOI of Group 0 (`group1 == false`) or
+    /// Group 1.
+    fn drop_priority(lists: &mut CpuLists, group1: bool) {
+        let active = lists.apr[0] | lists.apr[1];
+        if active == 0 {
+            warn!("vgicv3: EOI with no active priority");
+            return;
+        }
+        let highest = 1 << active.trailing_zeros();
+        let apr = &mut lists.apr[group1 as usize];
+        if *apr & highest == 0 {
+            warn!(
+                "vgicv3: EOI of Group {} with a higher priority active in the other group",
+                group1 as u8
+            );
+            return;
+        }
+        *apr &= !highest;
+    }
+
+    /// Deactivates an interrupt of a vCPU, notifying its resampler and queueing it again
+    /// if it is still pending.
+    fn soft_deactivate(&self, vcpu_id: usize, intid: u32) {
+        let Some(irq) = self.irq(vcpu_id, intid) else {
+            warn!("vgicv3: deactivation of unimplemented INTID {intid}");
+            return;
+        };
+        {
+            let mut irq = irq.lock();
+            if !irq.active {
+                return;
+            }
+            irq.active = false;
+            irq.count_eoi();
+        }
+        let mut notify = Vec::new();
+        self.resample_eoi(vcpu_id, intid, &mut notify);
+        let mut kicks = Vec::new();
+        self.update_irq(vcpu_id, intid, &mut kicks);
+        self.kick_vcpus(&kicks);
+        Self::notify_resamplers(notify);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::mock;
+
+    /// Creates a single vCPU vGIC with the software CPU interface, both groups enabled and
+    /// no priority masked.
+    fn soft_vgic() -> Vgicv3 {
+        let config = Vgicv3Config {
+            software_cpu_interface: true,
+            ..Default::default()
+        };
+        let (vgic, _host) = mock::vgic(config);
+        vgic.handle_icc_write(IccReg::Igrpen0, 1).unwrap();
+        vgic.handle_icc_write(IccReg::Igrpen1, 1).unwrap();
+        vgic.handle_icc_write(IccReg::Pmr, 0xff).unwrap();
+        vgic
+    }
+
+    /// Makes `intid` a pending SPI of Group 0 or Group 1 with the given priority.
+    fn raise(vgic: &Vgicv3, intid: u32, priority: u8, group1: bool) {
+        mock::enable_spi(vgic, intid, priority, 0);
+        if !group1 {
+            let reg = GICD_IGROUPR + 4 * (intid as usize / 32);
+            let group = vgic.handle_read32(reg).unwrap();
+            vgic.handle_write32(reg, group & !(1 << (intid % 32)));
+        }
+        vgic.set_irq_level(0, intid, true).unwrap();
+    }
+
+    fn read(vgic: &Vgicv3, reg: IccReg) -> u64 {
+        vgic.handle_icc_read(reg).unwrap()
+    }
+
+    #[test]
+    fn acknowledge_eoi_restores_running_priority() {
+        let vgic = soft_vgic();
+        raise(&vgic, 40, 0x80, true);
+        assert_eq!(read(&vgic, IccReg::Rpr), 0xff);
+        assert_eq!(vgic.pending_exception(0), Some(IrqGroup::Group1NonSecure));
+        assert_eq!(read(&vgic, IccReg::Hppir1), 40);
+
+        assert_eq!(read(&vgic, IccReg::Iar1), 40);
+        vgic.set_irq_level(0, 40, false).unwrap();
+        assert_eq!(read(&vgic, IccReg::Rpr), 0x80);
+        assert!(vgic.irq(0, 40).unwrap().lock().active);
+        assert_eq!(vgic.pending_exception(0), None);
+
+        vgic.handle_icc_write(IccReg::Eoir1, 40).unwrap();
+        assert_eq!(read(&vgic, IccReg::Rpr), 0xff);
+        assert!(!vgic.irq(0, 40).unwrap().lock().active);
+        assert_eq!(vgic.pending_exception(0), None);
+        assert_eq!(read(&vgic, IccReg::Hppir1), INTID_SPURIOUS as u64);
+    }
+
+    #[test]
+    fn group1_registers_do_not_skip_higher_priority_group0() {
+        let vgic = soft_vgic();
+        raise(&vgic, 40, 0x80, true);
+        raise(&vgic, 41, 0x40, false);
+        assert_eq!(read(&vgic, IccReg::Hppir1), INTID_SPURIOUS as u64);
+        assert_eq!(read(&vgic, IccReg::Iar1), INTID_SPURIOUS as u64);
+        assert!(!vgic.irq(0, 40).unwrap().lock().active);
+        assert_eq!(read(&vgic, IccReg::Hppir0), 41);
+
+        assert_eq!(read(&vgic, IccReg::Iar0), 41);
+        vgic.set_irq_level(0, 41, false).unwrap();
+        // The Group 1 interrupt cannot preempt the active Group 0 one.
+        assert_eq!(read(&vgic, IccReg::Iar1), INTID_SPURIOUS as u64);
+        vgic.handle_icc_write(IccReg::Eoir0, 41).unwrap();
+        assert_eq!(read(&vgic, IccReg::Iar1), 40);
+    }
+
+    #[test]
+    fn disabled_group_does_not_hide_the_other() {
+        let vgic = soft_vgic();
+        raise(&vgic, 40, 0x80, true);
+        raise(&vgic, 41, 0x40, false);
+        vgic.handle_icc_write(IccReg::Igrpen0, 0).unwrap();
+        assert_eq!(read(&vgic, IccReg::Hppir1), 40);
+        assert_eq!(read(&vgic, IccReg::Iar1), 40);
+        assert_eq!(read(&vgic, IccReg::Iar0), INTID_SPURIOUS as u64);
+    }
+
+    #[test]
+    fn split_eoi_mode_deactivates_on_dir() {
+        let vgic = soft_vgic();
+        vgic.handle_icc_write(IccReg::Ctlr, ICC_CTLR_EOIMODE)
+            .unwrap();
+        raise(&vgic, 40, 0x80, true);
+        assert_eq!(read(&vgic, IccReg::Iar1), 40);
+        vgic.set_irq_level(0, 40, false).unwrap();
+
+        vgic.handle_icc_write(IccReg::Eoir1, 40).unwrap();
+        assert_eq!(read(&vgic, IccReg::Rpr), 0xff);
+        assert!(vgic.irq(0, 40).unwrap().lock().active);
+
+        vgic.handle_icc_write(IccReg::Dir, 40).unwrap();
+        assert!(!vgic.irq(0, 40).unwrap().lock().active);
+    }
+
+    #[test]
+    fn nested_acknowledge_drops_highest_priority_first() {
+        let vgic = soft_vgic();
+        raise(&vgic, 40, 0x80, true);
+        assert_eq!(read(&vgic, IccReg::Iar1), 40);
+        raise(&vgic, 41, 0x40, false);
+        assert_eq!(vgic.pending_exception(0), Some(IrqGroup::Group0));
+        assert_eq!(read(&vgic, IccReg::Iar0), 41);
+        assert_eq!(read(&vgic, IccReg::Rpr), 0x40);
+
+        vgic.handle_icc_write(IccReg::Eoir0, 41).unwrap();
+        assert_eq!(read(&vgic, IccReg::Rpr), 0x80);
+        vgic.handle_icc_write(IccReg::Eoir1, 40).unwrap();
+        assert_eq!(read(&vgic, IccReg::Rpr), 0xff);
+    }
+
+    /// Acknowledges an interrupt at priority 0x88, then returns whether one at 0x80
+    /// preempts it with the given binary point configuration.
+    fn preempts(group1: bool, bpr_reg: IccReg, bpr: u64, cbpr: bool) -> bool {
+        let vgic = soft_vgic();
+        let ctlr = if cbpr { ICC_CTLR_CBPR } else { 0 };
+        vgic.handle_icc_write(IccReg::Ctlr, ctlr).unwrap();
+        vgic.handle_icc_write(bpr_reg, bpr).unwrap();
+        let iar = if group1 { IccReg::Iar1 } else { IccReg::Iar0 };
+        raise(&vgic, 40, 0x88, group1);
+        assert_eq!(read(&vgic, iar), 40);
+        raise(&vgic, 41, 0x80, group1);
+        let preempts = vgic.pending_exception(0).is_some();
+        assert_eq!(read(&vgic, iar) == 41, preempts);
+        preempts
+    }
+
+    #[test]
+    fn binary_points_group_priorities() {
+        // BPR0: group priority [7:3] with the minimum binary point of 2, [7:4] with 3.
+        assert!(preempts(false, IccReg::Bpr0, 2, false));
+        assert!(!preempts(false, IccReg::Bpr0, 3, false));
+        // BPR1: group priority [7:3] with the minimum binary point of 3, [7:4] with 4.
+        assert!(preempts(true, IccReg::Bpr1, 3, false));
+        assert!(!preempts(true, IccReg::Bpr1, 4, false));
+        // Values below the minimum are raised to it.
+        assert!(preempts(true, IccReg::Bpr1, 0, false));
+        // With ICC_CTLR_EL1.CBPR, BPR0 plus one applies to Group 1 too.
+        assert!(preempts(true, IccReg::Bpr0, 2, true));
+        assert!(!preempts(true, IccReg::Bpr0, 3, true));
+    }
+
+    #[test]
+    fn binary_point_registers_read_back() {
+        let vgic = soft_vgic();
+        assert_eq!(read(&vgic, IccReg::Bpr0), ICC_BPR0_MIN as u64);
+        assert_eq!(read(&vgic, IccReg::Bpr1), ICC_BPR0_MIN as u64 + 1);
+        vgic.handle_icc_write(IccReg::Bpr0, 5).unwrap();
+        vgic.handle_icc_write(IccReg::Ctlr, ICC_CTLR_CBPR).unwrap();
+        assert_eq!(read(&vgic, IccReg::Bpr1), 6);
+        // BPR1 is not writable while it is an alias of BPR0.
+        vgic.handle_icc_write(IccReg::Bpr1, 3).unwrap();
+        vgic.handle_icc_write(IccReg::Ctlr, 0).unwrap();
+        assert_eq!(read(&vgic, IccReg::Bpr1), ICC_BPR0_MIN as u64 + 1);
+    }
+
+    #[test]
+    fn acknowledge_is_spurious_without_interrupt_above_pmr() {
+        let vgic = soft_vgic();
+        assert_eq!(read(&vgic, IccReg::Iar1), INTID_SPURIOUS as u64);
+
+        raise(&vgic, 40, 0x80, true);
+        vgic.handle_icc_write(IccReg::Pmr, 0x80).unwrap();
+        assert_eq!(vgic.pending_exception(0), None);
+        assert_eq!(read(&vgic, IccReg::Iar1), INTID_SPURIOUS as u64);
+        assert!(!vgic.irq(0, 40).unwrap().lock().active);
+
+        // Group 0 registers do not acknowledge Group 1 interrupts.
+        vgic.handle_icc_write(IccReg::Pmr, 0x88).unwrap();
+        assert_eq!(read(&vgic, IccReg::Iar0), INTID_SPURIOUS as u64);
+        assert_eq!(read(&vgic, IccReg::Iar1), 40);
+    }
+
+    #[test]
+    fn registers_require_the_software_interface() {
+        let (vgic, _host) = mock::vgic(Vgicv3Config::default());
+        assert!(vgic.handle_icc_read(IccReg::Iar1).is_err());
+        assert!(vgic.handle_icc_write(IccReg::Pmr, 0).is_err());
+    }
+}
@@ -5,36 +5,44 @@ use axaddrspace::GuestPhysAddr;
 use axerrno::AxResult;
 use memory_addr::AddrRange;
 
-use crate::vgic::Vgic;
+use crate::config::GicVersion;
+use crate::consts::{GICD_FRAME_SIZE, V2M_FRAME_SIZE};
+use crate::v2m::Gicv2mFrame;
+use crate::vgicr::Vgicr;
+use crate::vgicv3::Vgicv3;
 
 impl BaseDeviceOps for Vgicv3 {
     /// Gets the emulator type of the current device.
     ///
-    /// This function returns the emulator device type of the current instance. Specifically, it always returns `EmuDeviceType::EmuDeviceTGicdV2`,
-    /// indicating that the emulator device is of type `EmuDeviceTGicdV2`.
+    /// This function returns the emulator device type of the current instance: `EmuDeviceType::EmuDeviceTGPPT`,
+    /// the GICv3 distributor, unless the distributor is presented as a GICv2 one, in which case it returns `EmuDeviceType::EmuDeviceTGicdV2`.
     ///
     /// # Returns
     /// - Returns an instance of the `EmuDeviceType` enum, representing the specific type of the emulator device.
     fn emu_type(&self) -> EmuDeviceType {
-        EmuDeviceType::EmuDeviceTGicdV2
+        match self.config.version {
+            GicVersion::V3 => EmuDeviceType::EmuDeviceTGPPT,
+            GicVersion::V2 => EmuDeviceType::EmuDeviceTGicdV2,
+        }
     }
 
     /// Returns the address range for the device.
     ///
-    /// This function defines the address range accessible to the device, starting from `0x800_0000`,
-    /// with a length of `0x10000` (64KB). It is used to specify where the device can read or write in memory.
+    /// This function defines the address range accessible to the device, starting from the configured
+    /// distributor base, with a length of `0x10000` (64KB). It is used to specify where the device can read or write in memory.
     ///
     /// # Returns
-    /// An `AddrRange` instance representing the address range from `0x800_0000` to `0x800_FFFF`.
+    /// An `AddrRange` instance covering the distributor frame.
     fn address_range(&self) -> AddrRange<GuestPhysAddr> {
-        AddrRange::new(0x800_0000.into(), (0x800_0000 + 0x10000).into())
+        let base = self.config.gicd_base;
+        AddrRange::new(base, (base.as_usize() + GICD_FRAME_SIZE).into())
     }
 
     /// Handles memory read operations.
     ///
     /// Based on the given physical address and read width, performs the corresponding read operation.
-    /// Supports reading 1 byte, 2 bytes, and 4 bytes. This function dereferences the provided physical
-    /// address and calls the specific read function based on the width parameter.
+    /// Supports reading 1 byte, 2 bytes, 4 bytes and 8 bytes. This function converts the provided physical
+    /// address to an offset within the distributor frame and calls the specific read function based on the width parameter.
     ///
     /// Parameters:
     /// - `addr`: The physical address to read from.
@@ -43,22 +51,26 @@ impl BaseDeviceOps for Vgicv3 {
     /// Returns:
     /// - `AxResult<usize>`: The result of the read operation, including any errors and the size of the data read.
     fn handle_read(&self, addr: GuestPhysAddr, width: usize) -> AxResult<usize> {
-        // Perform bitwise operation to ensure the address is aligned to byte boundaries
-        let addr = addr.as_usize() & 0xfff;
+        // Convert the address to an offset within the distributor frame
+        let addr = addr.as_usize() - self.config.gicd_base.as_usize();
 
         // Match different read operations based on the width parameter
         match width {
             1 => {
                 // Handle 1-byte read
-                return self.handle_read8(addr);
+                self.handle_read8(addr)
             }
             2 => {
                 // Handle 2-byte read
-                return self.handle_read16(addr);
+                self.handle_read16(addr)
             }
             4 => {
                 // Handle 4-byte read
-                return self.handle_read32(addr);
+                self.handle_read32(addr)
+            }
+            8 => {
+                // Handle 8-byte read
+                self.handle_read64(addr)
             }
             // Return success for unsupported widths without performing any operation
             _ => Ok(0),
@@ -67,16 +79,16 @@ impl BaseDeviceOps for Vgicv3 {
     /// Handles write operations of different widths.
     ///
     /// This function performs a write operation based on the given physical address, width, and value.
-    /// It first converts the physical address to a `usize` and applies a mask to ensure proper alignment.
+    /// It first converts the physical address to an offset within the distributor frame.
     /// Then, depending on the width parameter, it calls the corresponding write handling function.
     ///
     /// Parameters:
     /// - `addr`: The physical address to write to.
-    /// - `width`: The byte width of the data to be written (1, 2, 4 for 8-bit, 16-bit, and 32-bit data respectively).
+    /// - `width`: The byte width of the data to be written (1, 2, 4, 8 for 8-bit, 16-bit, 32-bit and 64-bit data respectively).
     /// - `val`: The value to be written.
     fn handle_write(&self, addr: GuestPhysAddr, width: usize, val: usize) {
-        // Convert the physical address to a `usize` and apply a mask to ensure proper alignment
-        let addr = addr.as_usize() & 0xfff;
+        // Convert the address to an offset within the distributor frame
+        let addr = addr.as_usize() - self.config.gicd_base.as_usize();
 
         // Depending on the width parameter, perform the corresponding write operation
         match width {
@@ -92,8 +104,70 @@ impl BaseDeviceOps for Vgicv3 {
                 // Handle 32-bit write operation
                 self.handle_write32(addr, val);
             }
+            8 => {
+                // Handle 64-bit write operation
+                self.handle_write64(addr, val);
+            }
             // For other width values, do nothing
             _ => {}
         }
     }
 }
+
+impl BaseDeviceOps for Vgicr {
+    /// Gets the emulator type of the redistributor region, `EmuDeviceType::EmuDeviceTGICR`.
+    fn emu_type(&self) -> EmuDeviceType {
+        EmuDeviceType::EmuDeviceTGICR
+    }
+
+    /// Returns the address range covering the redistributors of all vCPUs.
+    fn address_range(&self) -> AddrRange<GuestPhysAddr> {
+        let base = self.vgic.config.gicr_base;
+        AddrRange::new(base, (base.as_usize() + self.size()).into())
+    }
+
+    /// Handles memory read operations of 1, 2, 4 or 8 bytes.
+    fn handle_read(&self, addr: GuestPhysAddr, width: usize) -> AxResult<usize> {
+        let addr = addr.as_usize() - self.vgic.config.gicr_base.as_usize();
+        Vgicr::handle_read(self, addr, width)
+    }
+
+    /// Handles memory write operations of 1, 2, 4 or 8 bytes.
+    fn handle_write(&self, addr: GuestPhysAddr, width: usize, val: usize) {
+        let addr = addr.as_usize() - self.vgic.config.gicr_base.as_usize();
+        Vgicr::handle_write(self, addr, width, val);
+    }
+}
+
+impl BaseDeviceOps for Gicv2mFrame {
+    /// Gets the emulator type of the MSI frame.
+    ///
+    /// There is no dedicated device type for MSI frames, so `EmuDeviceType::EmuDeviceTMeta`
+    /// is reported.
+    fn emu_type(&self) -> EmuDeviceType {
+        EmuDeviceType::EmuDeviceTMeta
+    }
+
+    /// Returns the 4KB address range of the MSI frame.
+    fn address_range(&self) -> AddrRange<GuestPhysAddr> {
+        AddrRange::new(self.base, (self.base.as_usize() + V2M_FRAME_SIZE).into())
+    }
+
+    /// Handles memory read operations. Only 32-bit reads are supported.
+    fn handle_read(&self, addr: GuestPhysAddr, width: usize) -> AxResult<usize> {
+        let addr = addr.as_usize() - self.base.as_usize();
+        match width {
+            4 => self.handle_read32(addr),
+            _ => Ok(0),
+        }
+    }
+
+    /// Handles memory write operations. Writes of 16 and 32 bits are accepted, as some
+    /// devices signal MSIs with halfword writes.
+    fn handle_write(&self, addr: GuestPhysAddr, width: usize, val: usize) {
+        let addr = addr.as_usize() - self.base.as_usize();
+        if let 2 | 4 = width {
+            self.handle_write32(addr, val);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Human-readable dump of the emulated interrupt controller state.
+
+use core::fmt::{self, Write};
+use core::sync::atomic::Ordering;
+
+use tock_registers::LocalRegisterCopy;
+
+use crate::consts::*;
+use crate::interrupt::{IrqGroup, TriggerMode, VirtIrq};
+use crate::regs::gich::{GICH_APR, GICH_HCR, GICH_VMCR, ListRegister};
+use crate::vgicv3::Vgicv3;
+
+/// Writes the fields of a List register value.
+fn write_lr(w: &mut dyn Write, lr: &ListRegister) -> fmt::Result {
+    write!(
+        w,
+        "vINTID={} pINTID={} Priority={:#x} Group={:?} State={:?} HW={}",
+        lr.vintid, lr.pintid, lr.priority, lr.group, lr.state, lr.hw as u8,
+    )
+}
+
+/// Writes the fields of a `GICH_HCR` value.
+fn write_hcr(w: &mut dyn Write, value: u32) -> fmt::Result {
+    let hcr = LocalRegisterCopy::<u32, GICH_HCR::Register>::new(value);
+    write!(
+        w,
+        "En={} UIE={} LRENPIE={} NPIE={} VGrp0EIE={} VGrp0DIE={} VGrp1EIE={} VGrp1DIE={} EOICount={}",
+        hcr.read(GICH_HCR::En),
+        hcr.read(GICH_HCR::UIE),
+        hcr.read(GICH_HCR::LRENPIE),
+        hcr.read(GICH_HCR::NPIE),
+        hcr.read(GICH_HCR::VGrp0EIE),
+        hcr.read(GICH_HCR::VGrp0DIE),
+        hcr.read(GICH_HCR::VGrp1EIE),
+        hcr.read(GICH_HCR::VGrp1DIE),
+        hcr.read(GICH_HCR::EOICount),
+    )
+}
+
+/// Writes the fields of a `GICH_VMCR` value.
+fn write_vmcr(w: &mut dyn Write, value: u32) -> fmt::Result {
+    let vmcr = LocalRegisterCopy::<u32, GICH_VMCR::Register>::new(value);
+    write!(
+        w,
+        "VENG0={} VENG1={} VAckCtl={} VFIQEn={} VCBPR={} VEOIM={} VBPR0={} VBPR1={} VPMR={:#x}",
+        vmcr.read(GICH_VMCR::VENG0),
+        vmcr.read(GICH_VMCR::VENG1),
+        vmcr.read(GICH_VMCR::VAckCtl),
+        vmcr.read(GICH_VMCR::VFIQEn),
+        vmcr.read(GICH_VMCR::VCBPR),
+        vmcr.read(GICH_VMCR::VEOIM),
+        vmcr.read(GICH_VMCR::VBPR0),
+        vmcr.read(GICH_VMCR::VBPR1),
+        vmcr.read(GICH_VMCR::VPMR),
+    )
+}
+
+impl Vgicv3 {
+    /// Writes a human-readable dump of the whole vGIC state.
+    ///
+    /// This covers the distributor control state, the state and routing of every
+    /// implemented interrupt (one line per vCPU bank for SGIs and PPIs), and for each vCPU
+    /// its queued interrupts and the List register values last written to the hardware.
+    /// The live `GICH_HCR`, `GICH_VMCR`, `GICH_APR<n>` and List registers are only
+    /// accessible on the physical CPU running a vCPU, so they are dumped for the vCPU the
+    /// caller runs on, if any. With the software CPU interface, the emulated `GICH_VMCR`
+    /// and active priorities of every vCPU are dumped instead.
+    ///
+    /// The state is sampled one interrupt and one vCPU at a time, so it is not a consistent
+    /// snapshot while the vGIC is in use.
+    pub fn dump_state(&self, w: &mut dyn Write) -> fmt::Result {
+        writeln!(
+            w,
+            "GICD: version={:?} security={:?} CTLR={:#x} ARE_S={} ARE_NS={}",
+            self.config.version,
+            self.config.security,
+            self.ctlr.load(Ordering::Acquire),
+            self.affinity_routing(true),
+            self.affinity_routing(false),
+        )?;
+
+        writeln!(
+            w,
+            "{:>6} {:>5} {:>3} {:>4} {:>3} {:>4} {:>5} {:>5}  ROUTE",
+            "INTID", "VCPU", "EN", "PEND", "ACT", "PRIO", "GROUP", "TRIG"
+        )?;
+        for (vcpu_id, cpu) in self.cpus.iter().enumerate() {
+            for irq in cpu.private.iter().chain(&cpu.eppis) {
+                self.write_irq(w, Some(vcpu_id), &irq.lock().clone())?;
+            }
+        }
+        for irq in self.spis.iter().chain(&self.espis) {
+            self.write_irq(w, None, &irq.lock().clone())?;
+        }
+
+        let current = self.host.current_vcpu_id();
+        for (vcpu_id, cpu) in self.cpus.iter().enumerate() {
+            let (ap_list, lrs, vmcr, apr) = {
+                let lists = cpu.lists.lock();
+                (
+                    lists.ap_list.clone(),
+                    lists.lrs.clone(),
+                    lists.vmcr,
+                    lists.apr,
+                )
+            };
+            writeln!(w, "vCPU {vcpu_id}: queued {ap_list:?}")?;
+            if self.config.software_cpu_interface {
+                write!(w, "  emulated VMCR: ")?;
+                write_vmcr(w, vmcr)?;
+                writeln!(
+                    w,
+                    "\n  emulated APR: G0={:#010x} G1={:#010x}",
+                    apr[0], apr[1]
+                )?;
+                continue;
+            }
+            for (n, lr) in lrs.iter().enumerate() {
+                write!(w, "  written LR{n}: ")?;
+                write_lr(w, lr)?;
+                writeln!(w)?;
+            }
+            if vcpu_id != current {
+                continue;
+            }
+            write!(w, "  GICH_HCR: ")?;
+            write_hcr(w, self.host.read_hcr())?;
+            write!(w, "\n  GICH_VMCR: ")?;
+            write_vmcr(w, self.host.read_vmcr())?;
+            writeln!(w)?;
+            for n in 0..self.vtr.map_or(1, |vtr| vtr.apr_count()) {
+                let apr = LocalRegisterCopy::<u32, GICH_APR::Register>::new(self.host.read_apr(n));
+                writeln!(
+                    w,
+                    "  GICH_APR{n}: ACTIVE_PRIORITY_BITS={:#010x}",
+                    apr.read(GICH_APR::ACTIVE_PRIORITY_BITS)
+                )?;
+            }
+            for n in 0..self.nr_lrs {
+                let view = if self.ich_lr { "ICH_LR" } else { "GICH_LR" };
+                write!(w, "  {view}{n}: ")?;
+                write_lr(w, &self.read_list_register(n))?;
+                writeln!(w)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes one line of the interrupt table.
+    fn write_irq(&self, w: &mut dyn Write, vcpu_id: Option<usize>, irq: &VirtIrq) -> fmt::Result {
+        write!(w, "{:>6} ", irq.intid)?;
+        match vcpu_id {
+            Some(vcpu_id) => write!(w, "{vcpu_id:>5}")?,
+            None => write!(w, "{:>5}", "-")?,
+        }
+        let group = match irq.group() {
+            IrqGroup::Group0 => "G0",
+            IrqGroup::Group1Secure => "G1S",
+            IrqGroup::Group1NonSecure => "G1NS",
+        };
+        let trigger = match irq.trigger {
+            TriggerMode::Level => "level",
+            TriggerMode::Edge => "edge",
+        };
+        write!(
+            w,
+            " {:>3} {:>4} {:>3} {:>#4x} {:>5} {:>5}  ",
+            irq.enabled as u8,
+            irq.is_pending() as u8,
+            irq.active as u8,
+            irq.priority,
+            group,
+            trigger,
+        )?;
+        if irq.is_sgi() && irq.sgi_sources != 0 {
+            write!(w, "sources={:#04x} ", irq.sgi_sources)?;
+        }
+        if irq.is_private() {
+            writeln!(w, "local")
+        } else if self.affinity_routing(irq.group() != IrqGroup::Group1NonSecure) {
+            if irq.route & GICD_IROUTER_IRM != 0 {
+                writeln!(w, "any")
+            } else {
+                writeln!(w, "affinity={:#x}", irq.route & MPIDR_AFFINITY_MASK)
+            }
+        } else {
+            writeln!(w, "targets={:#04x}", irq.targets)
+        }
+    }
+}
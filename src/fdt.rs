@@ -0,0 +1,268 @@
+//! Device tree description of the emulated interrupt controller.
+//!
+//! Nodes are returned in a structured form, for the hypervisor to serialize into the guest
+//! device tree. Addresses and sizes are encoded with two cells each, so the parent node must
+//! use `#address-cells = <2>` and `#size-cells = <2>`.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use axaddrspace::GuestPhysAddr;
+
+use crate::config::GicVersion;
+use crate::consts::*;
+use crate::v2m::Gicv2mFrame;
+use crate::vgicv3::Vgicv3;
+
+/// `GIC_PPI` in the first cell of an interrupt specifier.
+const GIC_PPI: u32 = 1;
+/// `IRQ_TYPE_LEVEL_HIGH` in the third cell of an interrupt specifier.
+const IRQ_TYPE_LEVEL_HIGH: u32 = 4;
+/// Size of the frames of an ITS (control and translation).
+const GITS_SIZE: usize = 0x20000;
+
+/// Value of a device tree property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FdtValue {
+    /// A property without value, e.g. `interrupt-controller`.
+    Empty,
+    /// A list of 32-bit cells.
+    Cells(Vec<u32>),
+    /// A string.
+    Str(&'static str),
+}
+
+/// A device tree property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdtProperty {
+    /// Property name.
+    pub name: &'static str,
+    /// Property value.
+    pub value: FdtValue,
+}
+
+/// A device tree node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdtNode {
+    /// Node name, including the unit address.
+    pub name: String,
+    /// Properties, in the order they should be emitted.
+    pub properties: Vec<FdtProperty>,
+    /// Child nodes.
+    pub children: Vec<FdtNode>,
+}
+
+impl FdtNode {
+    /// Creates a node named `<name>@<unit address>`.
+    fn new(name: &str, base: GuestPhysAddr) -> Self {
+        Self {
+            name: format!("{name}@{:x}", base.as_usize()),
+            properties: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Appends a property.
+    fn prop(mut self, name: &'static str, value: FdtValue) -> Self {
+        self.properties.push(FdtProperty { name, value });
+        self
+    }
+
+    /// Appends a property made of 32-bit cells.
+    fn cells(self, name: &'static str, cells: &[u32]) -> Self {
+        self.prop(name, FdtValue::Cells(cells.to_vec()))
+    }
+
+    /// Returns the property called `name`, if any.
+    pub fn property(&self, name: &str) -> Option<&FdtValue> {
+        self.properties
+            .iter()
+            .find(|prop| prop.name == name)
+            .map(|prop| &prop.value)
+    }
+}
+
+/// Encodes a `reg` entry with two address and two size cells.
+fn reg_cells(base: GuestPhysAddr, size: usize) -> [u32; 4] {
+    let base = base.as_usize() as u64;
+    let size = size as u64;
+    [
+        (base >> 32) as u32,
+        base as u32,
+        (size >> 32) as u32,
+        size as u32,
+    ]
+}
+
+impl Vgicv3 {
+    /// Returns the device tree node of the emulated interrupt controller.
+    ///
+    /// With [`GicVersion::V3`] this is an `arm,gic-v3` node covering the distributor and the
+    /// redistributors of all vCPUs (and the CPU interface with legacy support); with
+    /// [`GicVersion::V2`] an `arm,cortex-a15-gic` node. MSI controllers can be appended to
+    /// [`FdtNode::children`], see [`Gicv2mFrame::fdt_node`] and [`its_fdt_node`].
+    ///
+    /// # Arguments
+    /// * `maintenance_ppi` - The PPI number (INTID - 16) of the virtual maintenance
+    ///   interrupt, advertised to guests that run their own hypervisor
+    pub fn fdt_node(&self, maintenance_ppi: u32) -> FdtNode {
+        let config = &self.config;
+        let mut reg = Vec::new();
+        reg.extend(reg_cells(config.gicd_base, GICD_FRAME_SIZE));
+        let node = FdtNode::new("interrupt-controller", config.gicd_base);
+        let node = match config.version {
+            GicVersion::V3 => {
+                reg.extend(reg_cells(config.gicr_base, config.vcpu_num * GICR_STRIDE));
+                if config.supports_legacy() {
+                    reg.extend(reg_cells(config.gicc_base, GICC_FRAME_SIZE));
+                }
+                node.prop("compatible", FdtValue::Str("arm,gic-v3"))
+                    .cells("#redistributor-regions", &[1])
+                    .cells("redistributor-stride", &[0, GICR_STRIDE as u32])
+                    .cells(
+                        "interrupts",
+                        &[GIC_PPI, maintenance_ppi, IRQ_TYPE_LEVEL_HIGH],
+                    )
+            }
+            GicVersion::V2 => {
+                reg.extend(reg_cells(config.gicc_base, GICC_FRAME_SIZE));
+                // GICv2 PPI specifiers carry the mask of CPUs the PPI is wired to.
+                let cpu_mask = ((1u32 << config.vcpu_num) - 1) << 8;
+                node.prop("compatible", FdtValue::Str("arm,cortex-a15-gic"))
+                    .cells(
+                        "interrupts",
+                        &[GIC_PPI, maintenance_ppi, cpu_mask | IRQ_TYPE_LEVEL_HIGH],
+                    )
+            }
+        };
+        node.cells("#interrupt-cells", &[3])
+            .prop("interrupt-controller", FdtValue::Empty)
+            .cells("#address-cells", &[2])
+            .cells("#size-cells", &[2])
+            .prop("ranges", FdtValue::Empty)
+            .prop("reg", FdtValue::Cells(reg))
+    }
+}
+
+impl Gicv2mFrame {
+    /// Returns the `arm,gic-v2m-frame` node of this MSI frame, to be appended to the
+    /// children of [`Vgicv3::fdt_node`].
+    pub fn fdt_node(&self) -> FdtNode {
+        FdtNode::new("v2m", self.base)
+            .prop("compatible", FdtValue::Str("arm,gic-v2m-frame"))
+            .prop("msi-controller", FdtValue::Empty)
+            .prop(
+                "reg",
+                FdtValue::Cells(reg_cells(self.base, V2M_FRAME_SIZE).to_vec()),
+            )
+    }
+}
+
+/// Returns the `arm,gic-v3-its` node of an ITS at `base`, to be appended to the children
+/// of [`Vgicv3::fdt_node`].
+///
+/// The ITS itself is not emulated by this crate; this describes one provided by the
+/// hypervisor.
+pub fn its_fdt_node(base: GuestPhysAddr) -> FdtNode {
+    FdtNode::new("msi-controller", base)
+        .prop("compatible", FdtValue::Str("arm,gic-v3-its"))
+        .prop("msi-controller", FdtValue::Empty)
+        .cells("#msi-cells", &[1])
+        .prop("reg", FdtValue::Cells(reg_cells(base, GITS_SIZE).to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::mock;
+
+    fn node(config: Vgicv3Config) -> FdtNode {
+        let host = mock::MockHost::with_gicv(mock::MockHost::default_vtr());
+        Vgicv3::new(config, host).unwrap().fdt_node(9)
+    }
+
+    fn cells(node: &FdtNode, name: &str) -> Vec<u32> {
+        match node.property(name) {
+            Some(FdtValue::Cells(cells)) => cells.clone(),
+            value => panic!("{name}: unexpected value {value:?}"),
+        }
+    }
+
+    #[test]
+    fn gicv3_node() {
+        let node = node(Vgicv3Config {
+            vcpu_num: 4,
+            ..Default::default()
+        });
+        assert_eq!(node.name, "interrupt-controller@8000000");
+        assert_eq!(
+            node.property("compatible"),
+            Some(&FdtValue::Str("arm,gic-v3"))
+        );
+        assert_eq!(
+            cells(&node, "reg"),
+            [0, 0x800_0000, 0, 0x1_0000, 0, 0x80a_0000, 0, 0x8_0000]
+        );
+        assert_eq!(cells(&node, "interrupts"), [1, 9, 4]);
+        assert_eq!(cells(&node, "redistributor-stride"), [0, 0x2_0000]);
+        assert_eq!(cells(&node, "#interrupt-cells"), [3]);
+    }
+
+    #[test]
+    fn gicv3_node_with_legacy_support() {
+        let node = node(Vgicv3Config {
+            vcpu_num: 2,
+            legacy_support: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            cells(&node, "reg"),
+            [
+                0, 0x800_0000, 0, 0x1_0000, 0, 0x80a_0000, 0, 0x4_0000, 0, 0x801_0000, 0, 0x2000,
+            ]
+        );
+        assert_eq!(cells(&node, "interrupts"), [1, 9, 4]);
+    }
+
+    #[test]
+    fn gicv2_node() {
+        let node = node(Vgicv3Config {
+            version: GicVersion::V2,
+            vcpu_num: 2,
+            ..Default::default()
+        });
+        assert_eq!(
+            node.property("compatible"),
+            Some(&FdtValue::Str("arm,cortex-a15-gic"))
+        );
+        assert_eq!(
+            cells(&node, "reg"),
+            [0, 0x800_0000, 0, 0x1_0000, 0, 0x801_0000, 0, 0x2000]
+        );
+        // The maintenance PPI is wired to CPUs 0 and 1.
+        assert_eq!(cells(&node, "interrupts"), [1, 9, 0x304]);
+        assert!(node.property("redistributor-stride").is_none());
+    }
+
+    #[test]
+    fn msi_controller_nodes() {
+        let config = Vgicv3Config {
+            spi_num: 64,
+            ..Default::default()
+        };
+        let vgic = Arc::new(mock::vgic(config).0);
+        let frame = Gicv2mFrame::new(vgic, 0x802_0000.into(), 64, 32).unwrap();
+        let node = frame.fdt_node();
+        assert_eq!(node.name, "v2m@8020000");
+        assert_eq!(cells(&node, "reg"), [0, 0x802_0000, 0, 0x1000]);
+
+        let node = its_fdt_node(0x1_0808_0000.into());
+        assert_eq!(node.name, "msi-controller@108080000");
+        assert_eq!(cells(&node, "reg"), [1, 0x808_0000, 0, 0x2_0000]);
+        assert_eq!(cells(&node, "#msi-cells"), [1]);
+    }
+}
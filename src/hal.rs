@@ -0,0 +1,104 @@
+use axaddrspace::HostPhysAddr;
+
+/// Access to the virtual interface control registers (the GICH frame) of the physical CPU
+/// the caller is running on.
+///
+/// On a GICv3 host these are provided by the legacy memory-mapped interface
+/// (FEAT_GICv3_LEGACY). Values use the layouts defined in [`crate::regs::gich`].
+pub trait GichOps: Send + Sync {
+    /// Reads `GICH_HCR`.
+    fn read_hcr(&self) -> u32;
+    /// Writes `GICH_HCR`.
+    fn write_hcr(&self, value: u32);
+    /// Reads `GICH_VTR`.
+    fn read_vtr(&self) -> u32;
+    /// Reads `GICH_VMCR`.
+    fn read_vmcr(&self) -> u32;
+    /// Writes `GICH_VMCR`.
+    fn write_vmcr(&self, value: u32);
+    /// Reads `GICH_MISR`.
+    fn read_misr(&self) -> u32;
+    /// Reads `GICH_EISR`.
+    fn read_eisr(&self) -> u32;
+    /// Reads `GICH_ELRSR`.
+    fn read_elrsr(&self) -> u32;
+    /// Reads `GICH_APR<n>`, the Group 1 active priorities (`ICH_AP1R<n>_EL2`).
+    fn read_apr(&self, n: usize) -> u32;
+    /// Writes `GICH_APR<n>`.
+    fn write_apr(&self, n: usize, value: u32);
+    /// Reads `ICH_AP0R<n>_EL2`, the Group 0 active priorities.
+    ///
+    /// The GICH frame has no equivalent: hosts that only access it keep the default,
+    /// RAZ/WI.
+    fn read_ap0r(&self, n: usize) -> u32 {
+        let _ = n;
+        0
+    }
+    /// Writes `ICH_AP0R<n>_EL2`.
+    fn write_ap0r(&self, n: usize, value: u32) {
+        let _ = (n, value);
+    }
+    /// Returns the number of active priority registers of each group the host gives
+    /// access to: `n` must be below it in [`GichOps::read_apr`], [`GichOps::read_ap0r`]
+    /// and their writes.
+    ///
+    /// Defaults to 1, `GICH_APR0` alone. Saving the active priorities of a host whose
+    /// `GICH_VTR.PREbits` implements more registers fails rather than losing them.
+    fn apr_count(&self) -> usize {
+        1
+    }
+    /// Reads `GICH_LR<n>`.
+    fn read_lr(&self, n: usize) -> u32;
+    /// Writes `GICH_LR<n>`.
+    fn write_lr(&self, n: usize, value: u32);
+    /// Returns whether the host gives access to the List registers as `ICH_LR<n>_EL2`,
+    /// whose 32-bit `vINTID` field can present Extended SPIs and PPIs.
+    ///
+    /// If so, a vGIC implementing extended INTIDs accesses its List registers through
+    /// [`GichOps::read_ich_lr`] and [`GichOps::write_ich_lr`] instead of
+    /// [`GichOps::read_lr`] and [`GichOps::write_lr`].
+    fn supports_ich_lr(&self) -> bool {
+        false
+    }
+    /// Reads `ICH_LR<n>_EL2`. Only called if [`GichOps::supports_ich_lr`] returns `true`.
+    fn read_ich_lr(&self, n: usize) -> u64 {
+        let _ = n;
+        0
+    }
+    /// Writes `ICH_LR<n>_EL2`. Only called if [`GichOps::supports_ich_lr`] returns `true`.
+    fn write_ich_lr(&self, n: usize, value: u64) {
+        let _ = (n, value);
+    }
+    /// Returns the host physical base address of the GICV frame, or `None` if the host
+    /// GIC does not implement legacy operation.
+    fn gicv_base(&self) -> Option<HostPhysAddr>;
+
+    /// Returns whether the host List registers implement the NMI attribute (GICv3.3).
+    fn supports_nmi(&self) -> bool {
+        false
+    }
+    /// Sets `ICH_LR<n>_EL2.NMI`, which has no equivalent in the `GICH_LR<n>` layout.
+    ///
+    /// Called after `write_lr(n, ..)` or `write_ich_lr(n, ..)`, and only if
+    /// [`GichOps::supports_nmi`] returns `true`.
+    fn set_lr_nmi(&self, n: usize, nmi: bool) {
+        let _ = (n, nmi);
+    }
+}
+
+/// Services the virtual GIC requires from the hypervisor.
+pub trait VgicHostOps: GichOps {
+    /// Returns the ID of the vCPU that performed the current register access.
+    fn current_vcpu_id(&self) -> usize;
+    /// Forces the given vCPU to exit the guest, so that newly pending interrupts are
+    /// written to its List registers before it resumes.
+    fn kick_vcpu(&self, vcpu_id: usize);
+
+    /// Returns whether the current register access is a Secure access.
+    ///
+    /// Only consulted with [`SecurityModel::TwoSecurityStates`](crate::SecurityModel), for
+    /// hypervisors that model a Secure world inside the VM. Guests run Non-secure by default.
+    fn current_access_secure(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,586 @@
+//! Driver for the physical GICv3 the hypervisor runs on.
+//!
+//! All interrupts are configured as Non-secure Group 1 and the CPU interface runs with
+//! `ICC_CTLR_EL1.EOImode == 1`: [`HostGicv3::eoi`] only drops the running priority, so a
+//! passthrough interrupt can be left active and deactivated by the guest through a
+//! hardware List register entry, while interrupts handled by the hypervisor are
+//! deactivated with [`HostGicv3::deactivate`].
+
+mod sysreg;
+
+use core::hint::spin_loop;
+use core::ptr::NonNull;
+
+use axaddrspace::HostPhysAddr;
+use axerrno::{AxResult, ax_err};
+use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
+
+use crate::consts::*;
+use crate::hal::GichOps;
+use crate::interrupt::TriggerMode;
+use crate::regs::gich::GichRegs;
+use crate::regs::{
+    GICD_CTLR, GICD_IROUTER, GICD_TYPER, GICR_CTLR, GICR_TYPER, GICR_WAKER, GicdRegs, GicrRdRegs,
+    GicrSgiRegs, ICC_SGI1R_EL1,
+};
+
+/// Priority given to every interrupt by the initialization routines.
+const DEFAULT_PRIORITY: u8 = 0xa0;
+
+/// `ICC_SRE_EL2.SRE`: system register access to the CPU interface at EL2.
+const ICC_SRE_SRE: u64 = 1 << 0;
+/// `ICC_SRE_EL2.Enable`: EL1 may use system register access through `ICC_SRE_EL1`.
+const ICC_SRE_ENABLE: u64 = 1 << 3;
+/// `ICC_CTLR_EL1.EOImode`: `ICC_EOIR1_EL1` only performs priority drop.
+const ICC_CTLR_EOIMODE: u64 = 1 << 1;
+
+/// Returns the `Aff3.Aff2.Aff1.Aff0` affinity of an `MPIDR_EL1` value, in the layout of
+/// `GICR_TYPER.Affinity_Value`.
+fn mpidr_to_affinity(mpidr: u64) -> u64 {
+    (mpidr & 0xff_ffff) | (((mpidr >> 32) & 0xff) << 24)
+}
+
+/// The physical GICv3 distributor, redistributors and CPU interface.
+pub struct HostGicv3 {
+    gicd: NonNull<GicdRegs>,
+    gicr: NonNull<u8>,
+    gich: NonNull<GichRegs>,
+    gicv_base: Option<HostPhysAddr>,
+}
+
+// SAFETY: the frames are only accessed through volatile register operations, and the
+// GICH frame is banked per physical CPU.
+unsafe impl Send for HostGicv3 {}
+// SAFETY: see above.
+unsafe impl Sync for HostGicv3 {}
+
+impl HostGicv3 {
+    /// Creates a driver from the virtual addresses the host GIC frames are mapped at.
+    ///
+    /// `gicr` is the start of the redistributor region; `gich` is the GICH frame of the
+    /// legacy interface backing [`GichOps`], and `gicv_base` the physical address of the
+    /// matching GICV frame, if any.
+    ///
+    /// # Safety
+    ///
+    /// The addresses must be mappings of the corresponding device frames, valid for the
+    /// lifetime of the driver and not accessed by anything else.
+    pub unsafe fn new(
+        gicd: NonNull<u8>,
+        gicr: NonNull<u8>,
+        gich: NonNull<u8>,
+        gicv_base: Option<HostPhysAddr>,
+    ) -> Self {
+        Self {
+            gicd: gicd.cast(),
+            gicr,
+            gich: gich.cast(),
+            gicv_base,
+        }
+    }
+
+    fn gicd(&self) -> &GicdRegs {
+        // SAFETY: guaranteed by the caller of `new`.
+        unsafe { self.gicd.as_ref() }
+    }
+
+    fn gich(&self) -> &GichRegs {
+        // SAFETY: guaranteed by the caller of `new`.
+        unsafe { self.gich.as_ref() }
+    }
+
+    /// Returns one past the largest SPI INTID implemented by the distributor.
+    pub fn spi_limit(&self) -> usize {
+        let lines = self.gicd().TYPER.read(GICD_TYPER::ITLinesNumber) as usize;
+        (32 * (lines + 1)).min(SPI_ID_MAX)
+    }
+
+    fn wait_gicd_rwp(&self) {
+        while self.gicd().CTLR.is_set(GICD_CTLR::RWP) {
+            spin_loop();
+        }
+    }
+
+    /// Returns the `RD_base` and `SGI_base` frames of the redistributor of the current CPU.
+    fn redistributor(&self) -> AxResult<(&GicrRdRegs, &GicrSgiRegs)> {
+        let affinity = mpidr_to_affinity(sysreg::read_mpidr());
+        let mut frame = self.gicr;
+        loop {
+            // SAFETY: the redistributor region is made of consecutive redistributors, the
+            // last one having `GICR_TYPER.Last` set.
+            let rd = unsafe { frame.cast::<GicrRdRegs>().as_ref() };
+            let typer = rd.TYPER.extract();
+            if typer.read(GICR_TYPER::Affinity_Value) == affinity {
+                // SAFETY: `SGI_base` follows `RD_base`.
+                let sgi = unsafe { frame.add(GICR_SGI_BASE).cast::<GicrSgiRegs>().as_ref() };
+                return Ok((rd, sgi));
+            }
+            if typer.is_set(GICR_TYPER::Last) {
+                return ax_err!(NotFound, "no redistributor for the current CPU");
+            }
+            // GICv4 redistributors have two more frames (`VLPI_base` and a reserved one).
+            let stride = if typer.is_set(GICR_TYPER::VLPIS) {
+                2 * GICR_STRIDE
+            } else {
+                GICR_STRIDE
+            };
+            // SAFETY: see above.
+            frame = unsafe { frame.add(stride) };
+        }
+    }
+
+    /// Initializes the distributor: affinity routing is enabled, and every SPI is made a
+    /// disabled, inactive, level-sensitive Non-secure Group 1 interrupt routed to the
+    /// current CPU.
+    ///
+    /// Called once, on the boot CPU, before [`HostGicv3::init_cpu`].
+    pub fn init_distributor(&self) {
+        let gicd = self.gicd();
+        gicd.CTLR.set(0);
+        self.wait_gicd_rwp();
+
+        let spi_limit = self.spi_limit();
+        // The last bank may be partially implemented.
+        for n in SPI_ID_BASE / 32..spi_limit.div_ceil(32) {
+            gicd.IGROUPR[n].set(u32::MAX);
+            gicd.IGRPMODR[n].set(0);
+            gicd.ICENABLER[n].set(u32::MAX);
+            gicd.ICPENDR[n].set(u32::MAX);
+            gicd.ICACTIVER[n].set(u32::MAX);
+        }
+        for n in SPI_ID_BASE / 16..spi_limit.div_ceil(16) {
+            gicd.ICFGR[n].set(0);
+        }
+        let affinity = sysreg::read_mpidr() & MPIDR_AFFINITY_MASK;
+        for intid in SPI_ID_BASE..spi_limit {
+            gicd.IPRIORITYR[intid].set(DEFAULT_PRIORITY);
+            gicd.IROUTER[intid].set(affinity);
+        }
+        self.wait_gicd_rwp();
+
+        // In the Non-secure view bit 4 is ARE_NS, and bits 0 and 1 enable Group 1
+        // interrupts (EnableGrp1 and EnableGrp1A).
+        gicd.CTLR.write(
+            GICD_CTLR::ARE_S::SET + GICD_CTLR::EnableGrp1NS::SET + GICD_CTLR::EnableGrp0::SET,
+        );
+        self.wait_gicd_rwp();
+    }
+
+    /// Initializes the redistributor and the CPU interface of the current CPU: the
+    /// redistributor is woken up, SGIs are enabled and PPIs disabled, all as Non-secure
+    /// Group 1, and system register access is enabled with `EOImode == 1`.
+    ///
+    /// Called on every CPU.
+    pub fn init_cpu(&self) -> AxResult {
+        let (rd, sgi) = self.redistributor()?;
+        rd.WAKER.modify(GICR_WAKER::ProcessorSleep::CLEAR);
+        while rd.WAKER.is_set(GICR_WAKER::ChildrenAsleep) {
+            spin_loop();
+        }
+
+        sgi.IGROUPR[0].set(u32::MAX);
+        sgi.IGRPMODR[0].set(0);
+        sgi.ICACTIVER[0].set(u32::MAX);
+        sgi.ICPENDR[0].set(u32::MAX);
+        sgi.ICENABLER[0].set(0xffff_0000);
+        sgi.ISENABLER[0].set(0x0000_ffff);
+        sgi.ICFGR[1].set(0);
+        for intid in 0..PRIVATE_IRQ_NUM {
+            sgi.IPRIORITYR[intid].set(DEFAULT_PRIORITY);
+        }
+        while rd.CTLR.is_set(GICR_CTLR::RWP) {
+            spin_loop();
+        }
+
+        sysreg::write_icc_sre_el2(sysreg::read_icc_sre_el2() | ICC_SRE_SRE | ICC_SRE_ENABLE);
+        sysreg::isb();
+        sysreg::write_icc_pmr_el1(0xff);
+        sysreg::write_icc_bpr1_el1(0);
+        sysreg::write_icc_ctlr_el1(sysreg::read_icc_ctlr_el1() | ICC_CTLR_EOIMODE);
+        sysreg::write_icc_igrpen1_el1(1);
+        sysreg::isb();
+        Ok(())
+    }
+
+    /// Runs `f` on the bitmap bank register holding `intid` and the bit of `intid` in it.
+    ///
+    /// Private interrupts are accessed in the redistributor of the current CPU.
+    fn with_bank<R>(
+        &self,
+        intid: u32,
+        f: impl FnOnce(&GicdRegs, Option<&GicrSgiRegs>, usize) -> R,
+    ) -> AxResult<R> {
+        let intid = intid as usize;
+        if intid < PRIVATE_IRQ_NUM {
+            let (_, sgi) = self.redistributor()?;
+            Ok(f(self.gicd(), Some(sgi), intid))
+        } else if intid < self.spi_limit() {
+            Ok(f(self.gicd(), None, intid))
+        } else {
+            ax_err!(InvalidInput, "interrupt not implemented by the host GIC")
+        }
+    }
+
+    /// Enables or disables the forwarding of an interrupt.
+    pub fn set_enable(&self, intid: u32, enable: bool) -> AxResult {
+        self.with_bank(intid, |gicd, sgi, intid| {
+            let bit = 1 << (intid % 32);
+            match (sgi, enable) {
+                (Some(sgi), true) => sgi.ISENABLER[0].set(bit),
+                (Some(sgi), false) => sgi.ICENABLER[0].set(bit),
+                (None, true) => gicd.ISENABLER[intid / 32].set(bit),
+                (None, false) => gicd.ICENABLER[intid / 32].set(bit),
+            }
+        })
+    }
+
+    /// Sets the priority of an interrupt.
+    pub fn set_priority(&self, intid: u32, priority: u8) -> AxResult {
+        self.with_bank(intid, |gicd, sgi, intid| match sgi {
+            Some(sgi) => sgi.IPRIORITYR[intid].set(priority),
+            None => gicd.IPRIORITYR[intid].set(priority),
+        })
+    }
+
+    /// Configures an interrupt as level-sensitive or edge-triggered. SGIs are always
+    /// edge-triggered.
+    pub fn set_trigger_mode(&self, intid: u32, mode: TriggerMode) -> AxResult {
+        if (intid as usize) < SGI_NUM {
+            return ax_err!(InvalidInput, "SGIs are always edge-triggered");
+        }
+        self.with_bank(intid, |gicd, sgi, intid| {
+            let reg = match sgi {
+                Some(sgi) => &sgi.ICFGR[1],
+                None => &gicd.ICFGR[intid / 16],
+            };
+            let bit = 1 << ((intid % 16) * 2 + 1);
+            match mode {
+                TriggerMode::Level => reg.set(reg.get() & !bit),
+                TriggerMode::Edge => reg.set(reg.get() | bit),
+            }
+        })
+    }
+
+    /// Routes an SPI to the CPU with the given `MPIDR_EL1` value.
+    pub fn route_spi(&self, intid: u32, mpidr: u64) -> AxResult {
+        let intid = intid as usize;
+        if !(SPI_ID_BASE..self.spi_limit()).contains(&intid) {
+            return ax_err!(InvalidInput, "not an SPI implemented by the host GIC");
+        }
+        self.gicd().IROUTER[intid].write(
+            GICD_IROUTER::Aff3.val((mpidr >> 32) & 0xff)
+                + GICD_IROUTER::Aff2.val((mpidr >> 16) & 0xff)
+                + GICD_IROUTER::Aff1.val((mpidr >> 8) & 0xff)
+                + GICD_IROUTER::Aff0.val(mpidr & 0xff),
+        );
+        Ok(())
+    }
+
+    /// Sends an SGI to the CPU with the given `MPIDR_EL1` value.
+    pub fn send_sgi(&self, intid: u32, mpidr: u64) -> AxResult {
+        if intid as usize >= SGI_NUM {
+            return ax_err!(InvalidInput, "not an SGI");
+        }
+        let aff0 = mpidr & 0xff;
+        let value = ICC_SGI1R_EL1::Aff3.val((mpidr >> 32) & 0xff)
+            + ICC_SGI1R_EL1::Aff2.val((mpidr >> 16) & 0xff)
+            + ICC_SGI1R_EL1::Aff1.val((mpidr >> 8) & 0xff)
+            + ICC_SGI1R_EL1::RS.val(aff0 / 16)
+            + ICC_SGI1R_EL1::INTID.val(intid as u64)
+            + ICC_SGI1R_EL1::TargetList.val(1 << (aff0 % 16));
+        sysreg::dsb_ishst();
+        sysreg::write_icc_sgi1r_el1(value.value);
+        sysreg::isb();
+        Ok(())
+    }
+
+    /// Acknowledges the highest priority pending interrupt of the current CPU, returning
+    /// its INTID, or `None` if no interrupt is pending.
+    pub fn ack(&self) -> Option<u32> {
+        let intid = (sysreg::read_icc_iar1_el1() & 0xff_ffff) as u32;
+        (!(SPI_ID_MAX as u32..1024).contains(&intid)).then_some(intid)
+    }
+
+    /// Drops the running priority of an acknowledged interrupt. The interrupt stays active
+    /// until it is deactivated.
+    pub fn eoi(&self, intid: u32) {
+        sysreg::write_icc_eoir1_el1(intid as u64);
+    }
+
+    /// Deactivates an interrupt whose priority has been dropped with [`HostGicv3::eoi`].
+    ///
+    /// Not needed for interrupts forwarded to a guest through a hardware List register
+    /// entry: the guest's deactivation deactivates the physical interrupt.
+    pub fn deactivate(&self, intid: u32) {
+        sysreg::write_icc_dir_el1(intid as u64);
+    }
+}
+
+impl GichOps for HostGicv3 {
+    fn read_hcr(&self) -> u32 {
+        self.gich().HCR.get()
+    }
+
+    fn write_hcr(&self, value: u32) {
+        self.gich().HCR.set(value)
+    }
+
+    fn read_vtr(&self) -> u32 {
+        self.gich().VTR.get()
+    }
+
+    fn read_vmcr(&self) -> u32 {
+        self.gich().VMCR.get()
+    }
+
+    fn write_vmcr(&self, value: u32) {
+        self.gich().VMCR.set(value)
+    }
+
+    fn read_misr(&self) -> u32 {
+        self.gich().MISR.get()
+    }
+
+    fn read_eisr(&self) -> u32 {
+        self.gich().EISR[0].get()
+    }
+
+    fn read_elrsr(&self) -> u32 {
+        self.gich().ELRSR[0].get()
+    }
+
+    fn read_apr(&self, n: usize) -> u32 {
+        self.gich().APR[n].get()
+    }
+
+    fn write_apr(&self, n: usize, value: u32) {
+        self.gich().APR[n].set(value)
+    }
+
+    fn read_ap0r(&self, n: usize) -> u32 {
+        sysreg::read_ich_ap0r_el2(n) as u32
+    }
+
+    fn write_ap0r(&self, n: usize, value: u32) {
+        sysreg::write_ich_ap0r_el2(n, value as u64)
+    }
+
+    fn apr_count(&self) -> usize {
+        GICH_APR_NUM_MAX
+    }
+
+    fn read_lr(&self, n: usize) -> u32 {
+        self.gich().LR[n].get()
+    }
+
+    fn write_lr(&self, n: usize, value: u32) {
+        self.gich().LR[n].set(value)
+    }
+
+    fn supports_ich_lr(&self) -> bool {
+        true
+    }
+
+    fn read_ich_lr(&self, n: usize) -> u64 {
+        sysreg::read_ich_lr_el2(n)
+    }
+
+    fn write_ich_lr(&self, n: usize, value: u64) {
+        sysreg::write_ich_lr_el2(n, value)
+    }
+
+    fn gicv_base(&self) -> Option<HostPhysAddr> {
+        self.gicv_base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    use super::*;
+
+    /// A zeroed device frame in memory.
+    struct FakeFrame {
+        words: NonNull<[u32]>,
+    }
+
+    impl FakeFrame {
+        fn new(size: usize) -> Self {
+            let words = NonNull::from(Box::leak(vec![0u32; size / 4].into_boxed_slice()));
+            Self { words }
+        }
+
+        fn base(&self) -> NonNull<u8> {
+            self.words.cast()
+        }
+
+        fn word(&self, offset: usize) -> u32 {
+            // SAFETY: `offset` is within the frame, which is only accessed through raw
+            // pointers.
+            unsafe { self.words.cast::<u32>().add(offset / 4).read_volatile() }
+        }
+
+        fn set_word(&self, offset: usize, value: u32) {
+            // SAFETY: see above.
+            unsafe {
+                self.words
+                    .cast::<u32>()
+                    .add(offset / 4)
+                    .write_volatile(value)
+            }
+        }
+    }
+
+    impl Drop for FakeFrame {
+        fn drop(&mut self) {
+            // SAFETY: the frame was leaked by `new`, and the driver using it is dropped
+            // first.
+            drop(unsafe { Box::from_raw(self.words.as_ptr()) });
+        }
+    }
+
+    /// A driver whose distributor and GICH frames are in memory. Redistributors are not
+    /// backed, so only SPIs and the virtual interface can be accessed.
+    struct FakeGic {
+        host: HostGicv3,
+        gicd: FakeFrame,
+        gich: FakeFrame,
+    }
+
+    impl FakeGic {
+        fn new() -> Self {
+            let gicd = FakeFrame::new(GICD_FRAME_SIZE);
+            let gich = FakeFrame::new(0x200);
+            // SAFETY: the frames outlive the driver, which is dropped first.
+            let host =
+                unsafe { HostGicv3::new(gicd.base(), NonNull::dangling(), gich.base(), None) };
+            Self { host, gicd, gich }
+        }
+
+        fn irouter(&self, intid: usize) -> u64 {
+            let offset = crate::consts::GICD_IROUTER + 8 * intid;
+            self.gicd.word(offset) as u64 | (self.gicd.word(offset + 4) as u64) << 32
+        }
+    }
+
+    #[test]
+    fn distributor_init_covers_partial_last_bank() {
+        let gic = FakeGic::new();
+        // ITLinesNumber == 31: INTIDs up to 1019 are implemented.
+        gic.gicd.set_word(crate::consts::GICD_TYPER, 31);
+        for n in 0..64 {
+            gic.gicd.set_word(GICD_ICFGR + 4 * n, u32::MAX);
+        }
+        gic.host.init_distributor();
+        assert_eq!(gic.host.spi_limit(), SPI_ID_MAX);
+
+        assert_eq!(gic.gicd.word(GICD_IGROUPR), 0);
+        for n in 1..32 {
+            assert_eq!(gic.gicd.word(GICD_IGROUPR + 4 * n), u32::MAX, "IGROUPR{n}");
+            assert_eq!(
+                gic.gicd.word(GICD_ICENABLER + 4 * n),
+                u32::MAX,
+                "ICENABLER{n}"
+            );
+            assert_eq!(gic.gicd.word(GICD_ICPENDR + 4 * n), u32::MAX, "ICPENDR{n}");
+            assert_eq!(
+                gic.gicd.word(GICD_ICACTIVER + 4 * n),
+                u32::MAX,
+                "ICACTIVER{n}"
+            );
+        }
+        assert_eq!(gic.gicd.word(GICD_ICFGR + 4), u32::MAX);
+        for n in 2..64 {
+            assert_eq!(gic.gicd.word(GICD_ICFGR + 4 * n), 0, "ICFGR{n}");
+        }
+
+        let affinity = sysreg::read_mpidr() & MPIDR_AFFINITY_MASK;
+        for intid in [SPI_ID_BASE, SPI_ID_MAX - 1] {
+            let priority = gic.gicd.word(GICD_IPRIORITYR + intid / 4 * 4) >> (8 * (intid % 4));
+            assert_eq!(priority as u8, DEFAULT_PRIORITY);
+            assert_eq!(gic.irouter(intid), affinity);
+        }
+        assert_eq!(gic.irouter(SPI_ID_MAX), 0);
+        assert_eq!(
+            gic.gicd.word(crate::consts::GICD_CTLR),
+            GICD_CTLR_ARE_S | GICD_CTLR_ENABLE_GRP1NS | GICD_CTLR_ENABLE_GRP0
+        );
+    }
+
+    #[test]
+    fn spi_routing_and_trigger_mode() {
+        let gic = FakeGic::new();
+        gic.gicd.set_word(crate::consts::GICD_TYPER, 31);
+
+        gic.host.route_spi(1019, 0x0000_0003_8002_0104).unwrap();
+        assert_eq!(gic.irouter(1019), 0x0000_0003_0002_0104);
+        assert!(gic.host.route_spi(1020, 0).is_err());
+        assert!(gic.host.route_spi(31, 0).is_err());
+
+        gic.host.set_trigger_mode(1019, TriggerMode::Edge).unwrap();
+        gic.host.set_trigger_mode(1018, TriggerMode::Edge).unwrap();
+        assert_eq!(gic.gicd.word(GICD_ICFGR + 4 * 63), 0b1010 << 20);
+        gic.host.set_trigger_mode(1018, TriggerMode::Level).unwrap();
+        assert_eq!(gic.gicd.word(GICD_ICFGR + 4 * 63), 0b1000 << 20);
+        assert!(gic.host.set_trigger_mode(1020, TriggerMode::Edge).is_err());
+        assert!(gic.host.set_trigger_mode(3, TriggerMode::Edge).is_err());
+    }
+
+    #[test]
+    fn spi_limit_follows_it_lines_number() {
+        let gic = FakeGic::new();
+        gic.gicd.set_word(crate::consts::GICD_TYPER, 2);
+        assert_eq!(gic.host.spi_limit(), 96);
+        gic.host.init_distributor();
+        assert_eq!(gic.gicd.word(GICD_IGROUPR + 4 * 2), u32::MAX);
+        assert_eq!(gic.gicd.word(GICD_IGROUPR + 4 * 3), 0);
+        assert!(gic.host.route_spi(96, 0).is_err());
+    }
+
+    #[test]
+    fn control_registers() {
+        let gic = FakeGic::new();
+        gic.host.write_hcr(0x0800_0001);
+        assert_eq!(gic.gich.word(0x00), 0x0800_0001);
+        gic.host.write_vmcr(0xf000_0203);
+        assert_eq!(gic.gich.word(0x08), 0xf000_0203);
+        assert_eq!(gic.host.read_vmcr(), 0xf000_0203);
+
+        gic.gich.set_word(0x04, 0x9000_0003);
+        gic.gich.set_word(0x10, 0x5);
+        gic.gich.set_word(0x20, 0x2);
+        gic.gich.set_word(0x30, 0xfffd);
+        assert_eq!(gic.host.read_vtr(), 0x9000_0003);
+        assert_eq!(gic.host.read_misr(), 0x5);
+        assert_eq!(gic.host.read_eisr(), 0x2);
+        assert_eq!(gic.host.read_elrsr(), 0xfffd);
+        assert_eq!(gic.host.read_hcr(), 0x0800_0001);
+    }
+
+    #[test]
+    fn list_registers() {
+        let gic = FakeGic::new();
+        for n in 0..64 {
+            gic.host.write_lr(n, 0x1000_0000 | n as u32);
+        }
+        for n in 0..64 {
+            assert_eq!(gic.gich.word(0x100 + 4 * n), 0x1000_0000 | n as u32);
+            assert_eq!(gic.host.read_lr(n), 0x1000_0000 | n as u32);
+        }
+    }
+
+    #[test]
+    fn active_priority_registers() {
+        let gic = FakeGic::new();
+        for n in 0..4 {
+            gic.host.write_apr(n, 1 << (8 * n));
+        }
+        for n in 0..4 {
+            assert_eq!(gic.gich.word(0xf0 + 4 * n), 1 << (8 * n));
+            assert_eq!(gic.host.read_apr(n), 1 << (8 * n));
+        }
+        assert_eq!(gic.gich.word(0x100), 0);
+    }
+}
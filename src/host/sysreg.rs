@@ -0,0 +1,219 @@
+//! Accessors for the GICv3 CPU interface system registers of the current CPU.
+//!
+//! Registers are named by their generic encodings so that no GIC support is required
+//! from the assembler. Accesses are not marked `nomem`: acknowledging, completing or
+//! generating an interrupt must stay ordered with the memory accesses around it.
+
+macro_rules! sysreg_read {
+    ($(#[$attr:meta])* $name:ident, $reg:literal) => {
+        $(#[$attr])*
+        #[inline]
+        pub fn $name() -> u64 {
+            let value: u64;
+            // SAFETY: reading a GIC CPU interface register does not access memory.
+            unsafe {
+                core::arch::asm!(concat!("mrs {}, ", $reg), out(reg) value, options(nostack));
+            }
+            value
+        }
+    };
+}
+
+macro_rules! sysreg_write {
+    ($(#[$attr:meta])* $name:ident, $reg:literal) => {
+        $(#[$attr])*
+        #[inline]
+        pub fn $name(value: u64) {
+            // SAFETY: writing a GIC CPU interface register does not access memory.
+            unsafe {
+                core::arch::asm!(concat!("msr ", $reg, ", {}"), in(reg) value, options(nostack));
+            }
+        }
+    };
+}
+
+sysreg_read!(
+    /// Reads `MPIDR_EL1`.
+    read_mpidr,
+    "mpidr_el1"
+);
+sysreg_read!(
+    /// Reads `ICC_SRE_EL2`.
+    read_icc_sre_el2,
+    "S3_4_C12_C9_5"
+);
+sysreg_write!(
+    /// Writes `ICC_SRE_EL2`.
+    write_icc_sre_el2,
+    "S3_4_C12_C9_5"
+);
+sysreg_read!(
+    /// Reads `ICC_CTLR_EL1`.
+    read_icc_ctlr_el1,
+    "S3_0_C12_C12_4"
+);
+sysreg_write!(
+    /// Writes `ICC_CTLR_EL1`.
+    write_icc_ctlr_el1,
+    "S3_0_C12_C12_4"
+);
+sysreg_write!(
+    /// Writes `ICC_PMR_EL1`.
+    write_icc_pmr_el1,
+    "S3_0_C4_C6_0"
+);
+sysreg_write!(
+    /// Writes `ICC_BPR1_EL1`.
+    write_icc_bpr1_el1,
+    "S3_0_C12_C12_3"
+);
+sysreg_write!(
+    /// Writes `ICC_IGRPEN1_EL1`.
+    write_icc_igrpen1_el1,
+    "S3_0_C12_C12_7"
+);
+sysreg_read!(
+    /// Reads `ICC_IAR1_EL1`, acknowledging the highest priority pending Group 1 interrupt.
+    read_icc_iar1_el1,
+    "S3_0_C12_C12_0"
+);
+sysreg_write!(
+    /// Writes `ICC_EOIR1_EL1`.
+    write_icc_eoir1_el1,
+    "S3_0_C12_C12_1"
+);
+sysreg_write!(
+    /// Writes `ICC_DIR_EL1`.
+    write_icc_dir_el1,
+    "S3_0_C12_C11_1"
+);
+sysreg_write!(
+    /// Writes `ICC_SGI1R_EL1`.
+    write_icc_sgi1r_el1,
+    "S3_0_C12_C11_5"
+);
+sysreg_read!(
+    /// Reads `ICH_AP0R0_EL2`.
+    read_ich_ap0r0_el2,
+    "S3_4_C12_C8_0"
+);
+sysreg_read!(
+    /// Reads `ICH_AP0R1_EL2`.
+    read_ich_ap0r1_el2,
+    "S3_4_C12_C8_1"
+);
+sysreg_read!(
+    /// Reads `ICH_AP0R2_EL2`.
+    read_ich_ap0r2_el2,
+    "S3_4_C12_C8_2"
+);
+sysreg_read!(
+    /// Reads `ICH_AP0R3_EL2`.
+    read_ich_ap0r3_el2,
+    "S3_4_C12_C8_3"
+);
+sysreg_write!(
+    /// Writes `ICH_AP0R0_EL2`.
+    write_ich_ap0r0_el2,
+    "S3_4_C12_C8_0"
+);
+sysreg_write!(
+    /// Writes `ICH_AP0R1_EL2`.
+    write_ich_ap0r1_el2,
+    "S3_4_C12_C8_1"
+);
+sysreg_write!(
+    /// Writes `ICH_AP0R2_EL2`.
+    write_ich_ap0r2_el2,
+    "S3_4_C12_C8_2"
+);
+sysreg_write!(
+    /// Writes `ICH_AP0R3_EL2`.
+    write_ich_ap0r3_el2,
+    "S3_4_C12_C8_3"
+);
+
+/// Reads `ICH_AP0R<n>_EL2`, n = 0 - 3.
+pub fn read_ich_ap0r_el2(n: usize) -> u64 {
+    match n {
+        0 => read_ich_ap0r0_el2(),
+        1 => read_ich_ap0r1_el2(),
+        2 => read_ich_ap0r2_el2(),
+        3 => read_ich_ap0r3_el2(),
+        _ => panic!("ICH_AP0R{n}_EL2 does not exist"),
+    }
+}
+
+/// Writes `ICH_AP0R<n>_EL2`, n = 0 - 3.
+pub fn write_ich_ap0r_el2(n: usize, value: u64) {
+    match n {
+        0 => write_ich_ap0r0_el2(value),
+        1 => write_ich_ap0r1_el2(value),
+        2 => write_ich_ap0r2_el2(value),
+        3 => write_ich_ap0r3_el2(value),
+        _ => panic!("ICH_AP0R{n}_EL2 does not exist"),
+    }
+}
+
+macro_rules! ich_lr_accessors {
+    ($($n:literal: $read:ident, $write:ident, $reg:literal;)*) => {
+        $(
+            sysreg_read!(#[doc = concat!("Reads `ICH_LR", $n, "_EL2`.")] $read, $reg);
+            sysreg_write!(#[doc = concat!("Writes `ICH_LR", $n, "_EL2`.")] $write, $reg);
+        )*
+
+        /// Reads `ICH_LR<n>_EL2`, n = 0 - 15.
+        pub fn read_ich_lr_el2(n: usize) -> u64 {
+            match n {
+                $($n => $read(),)*
+                _ => panic!("ICH_LR{n}_EL2 does not exist"),
+            }
+        }
+
+        /// Writes `ICH_LR<n>_EL2`, n = 0 - 15.
+        pub fn write_ich_lr_el2(n: usize, value: u64) {
+            match n {
+                $($n => $write(value),)*
+                _ => panic!("ICH_LR{n}_EL2 does not exist"),
+            }
+        }
+    };
+}
+
+ich_lr_accessors! {
+    0: read_ich_lr0_el2, write_ich_lr0_el2, "S3_4_C12_C12_0";
+    1: read_ich_lr1_el2, write_ich_lr1_el2, "S3_4_C12_C12_1";
+    2: read_ich_lr2_el2, write_ich_lr2_el2, "S3_4_C12_C12_2";
+    3: read_ich_lr3_el2, write_ich_lr3_el2, "S3_4_C12_C12_3";
+    4: read_ich_lr4_el2, write_ich_lr4_el2, "S3_4_C12_C12_4";
+    5: read_ich_lr5_el2, write_ich_lr5_el2, "S3_4_C12_C12_5";
+    6: read_ich_lr6_el2, write_ich_lr6_el2, "S3_4_C12_C12_6";
+    7: read_ich_lr7_el2, write_ich_lr7_el2, "S3_4_C12_C12_7";
+    8: read_ich_lr8_el2, write_ich_lr8_el2, "S3_4_C12_C13_0";
+    9: read_ich_lr9_el2, write_ich_lr9_el2, "S3_4_C12_C13_1";
+    10: read_ich_lr10_el2, write_ich_lr10_el2, "S3_4_C12_C13_2";
+    11: read_ich_lr11_el2, write_ich_lr11_el2, "S3_4_C12_C13_3";
+    12: read_ich_lr12_el2, write_ich_lr12_el2, "S3_4_C12_C13_4";
+    13: read_ich_lr13_el2, write_ich_lr13_el2, "S3_4_C12_C13_5";
+    14: read_ich_lr14_el2, write_ich_lr14_el2, "S3_4_C12_C13_6";
+    15: read_ich_lr15_el2, write_ich_lr15_el2, "S3_4_C12_C13_7";
+}
+
+/// Instruction synchronization barrier, required after `ICC_SRE_EL2` writes.
+#[inline]
+pub fn isb() {
+    // SAFETY: a barrier does not access memory.
+    unsafe {
+        core::arch::asm!("isb", options(nostack));
+    }
+}
+
+/// Data synchronization barrier for stores to the inner shareable domain, required before
+/// an `ICC_SGI1R_EL1` write so that the target observes the data the SGI announces.
+#[inline]
+pub fn dsb_ishst() {
+    // SAFETY: a barrier does not access memory.
+    unsafe {
+        core::arch::asm!("dsb ishst", options(nostack));
+    }
+}
@@ -0,0 +1,172 @@
+//! Asynchronous injection of wired interrupts from device backends.
+//!
+//! Each vCPU owns a bounded lock-free queue of line level events. Producers post events
+//! without taking any vGIC lock and kick the vCPU only if no kick is already pending, so
+//! a burst of injections costs a single exit. The vCPU applies the queued events in
+//! [`Vgicv3::flush_lrs`], before taking its ap_list lock.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use axerrno::{AxResult, ax_err};
+use log::warn;
+use spin::Mutex;
+
+use crate::consts::INJECT_QUEUE_LEN;
+use crate::vgicv3::Vgicv3;
+
+/// A slot of an [`InjectQueue`].
+struct Slot {
+    /// Sequence number: the position the slot can be written at when equal to it, the
+    /// position it can be read at when one more.
+    seq: AtomicUsize,
+    /// The event, as `intid | level << 32`.
+    event: AtomicU64,
+}
+
+/// Bounded multi-producer multi-consumer queue of line level events, after Dmitry
+/// Vyukov's bounded MPMC queue.
+pub(crate) struct InjectQueue {
+    slots: Vec<Slot>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    /// Set by the producer that kicks the vCPU, cleared when the vCPU starts draining.
+    kick_pending: AtomicBool,
+    /// Serializes consumers, so that events are applied in the order they were posted.
+    consumer: Mutex<()>,
+}
+
+impl InjectQueue {
+    /// Creates an empty queue of `INJECT_QUEUE_LEN` events.
+    pub fn new() -> Self {
+        let slots = (0..INJECT_QUEUE_LEN)
+            .map(|pos| Slot {
+                seq: AtomicUsize::new(pos),
+                event: AtomicU64::new(0),
+            })
+            .collect();
+        Self {
+            slots,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            kick_pending: AtomicBool::new(false),
+            consumer: Mutex::new(()),
+        }
+    }
+
+    /// Appends an event, returning `false` if the queue is full.
+    fn push(&self, intid: u32, level: bool) -> bool {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % INJECT_QUEUE_LEN];
+            let seq = slot.seq.load(Ordering::Acquire);
+            if seq == pos {
+                match self.tail.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        slot.event
+                            .store(intid as u64 | (level as u64) << 32, Ordering::Relaxed);
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return true;
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if (seq.wrapping_sub(pos) as isize) < 0 {
+                return false;
+            } else {
+                pos = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Removes the oldest event.
+    fn pop(&self) -> Option<(u32, bool)> {
+        let mut pos = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos % INJECT_QUEUE_LEN];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let readable = pos.wrapping_add(1);
+            if seq == readable {
+                match self.head.compare_exchange_weak(
+                    pos,
+                    readable,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let event = slot.event.load(Ordering::Relaxed);
+                        slot.seq
+                            .store(pos.wrapping_add(INJECT_QUEUE_LEN), Ordering::Release);
+                        return Some((event as u32, event >> 32 != 0));
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if (seq.wrapping_sub(readable) as isize) < 0 {
+                return None;
+            } else {
+                pos = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Vgicv3 {
+    /// Posts a line level change of a PPI or SPI without taking any vGIC lock.
+    ///
+    /// The event is applied as by [`Vgicv3::set_irq_level`] when `vcpu_id` next loads its
+    /// List registers. `vcpu_id` is kicked unless a kick posted earlier has not been
+    /// handled yet. If the queue of `vcpu_id` is full, the queued events and this one are
+    /// applied immediately instead.
+    ///
+    /// # Arguments
+    /// * `vcpu_id` - The vCPU whose banked interrupt is targeted. For SPIs, the vCPU that
+    ///   applies the event, preferably the one the SPI is routed to
+    /// * `intid` - The interrupt ID, 16 or above
+    /// * `level` - The new line level
+    pub fn post_irq_level(&self, vcpu_id: usize, intid: u32, level: bool) -> AxResult {
+        let Some(queue) = self.inject_queues.get(vcpu_id) else {
+            return ax_err!(InvalidInput, "vCPU not implemented");
+        };
+        if !queue.push(intid, level) {
+            // Apply the queued events first, so that they cannot override this one.
+            let mut kicks = Vec::new();
+            let result = {
+                let _consumer = queue.consumer.lock();
+                self.apply_injections(queue, vcpu_id, &mut kicks);
+                self.apply_irq_level(vcpu_id, intid, level, &mut kicks)
+            };
+            self.kick_vcpus(&kicks);
+            return result;
+        }
+        if !queue.kick_pending.swap(true, Ordering::AcqRel) {
+            self.host.kick_vcpu(vcpu_id);
+        }
+        Ok(())
+    }
+
+    /// Applies the events posted to `vcpu_id` with [`Vgicv3::post_irq_level`].
+    ///
+    /// Must be called without any vGIC lock held. vCPUs that need to be kicked to observe
+    /// the changes are appended to `kicks`.
+    pub(crate) fn drain_injections(&self, vcpu_id: usize, kicks: &mut Vec<usize>) {
+        let queue = &self.inject_queues[vcpu_id];
+        let _consumer = queue.consumer.lock();
+        self.apply_injections(queue, vcpu_id, kicks);
+    }
+
+    /// Applies the events of `queue`, with its consumer lock held.
+    fn apply_injections(&self, queue: &InjectQueue, vcpu_id: usize, kicks: &mut Vec<usize>) {
+        // Events posted from now on must kick the vCPU again. Reading the flag set by the
+        // last kicking producer makes all the events it posted visible.
+        queue.kick_pending.swap(false, Ordering::AcqRel);
+        while let Some((intid, level)) = queue.pop() {
+            if let Err(err) = self.apply_irq_level(vcpu_id, intid, level, kicks) {
+                warn!("vgicv3: dropping posted event for INTID {intid}: {err:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::consts::{EPPI_ID_BASE, EPPI_NUM_MAX, SGI_NUM, SPI_ID_BASE};
+
+/// Trigger mode of an interrupt, as configured through `GICD_ICFGR<n>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// The interrupt is pending while its input line is asserted.
+    Level,
+    /// The interrupt becomes pending on a rising edge and stays pending until acknowledged.
+    Edge,
+}
+
+/// Interrupt group, as configured through `GICD_IGROUPR<n>` and `GICD_IGRPMODR<n>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqGroup {
+    /// Group 0, signalled as a (virtual) FIQ.
+    Group0,
+    /// Secure Group 1, only with two security states.
+    Group1Secure,
+    /// Non-secure Group 1, or Group 1 with a single security state.
+    Group1NonSecure,
+}
+
+/// Returns whether `intid` is banked per vCPU (an SGI, a PPI or an Extended PPI).
+pub(crate) fn is_private_intid(intid: u32) -> bool {
+    let intid = intid as usize;
+    intid < SPI_ID_BASE || (EPPI_ID_BASE..EPPI_ID_BASE + EPPI_NUM_MAX).contains(&intid)
+}
+
+/// Emulated state of a single virtual interrupt.
+#[derive(Debug, Clone)]
+pub(crate) struct VirtIrq {
+    /// Interrupt ID.
+    pub intid: u32,
+    /// Forwarding enabled (`GICD_ISENABLER<n>`).
+    pub enabled: bool,
+    /// Pending state latched by an edge or a write to `GICD_ISPENDR<n>`.
+    pub pending_latch: bool,
+    /// Current level of the input line, only meaningful for level-sensitive interrupts.
+    pub line_level: bool,
+    /// Source CPUs of a pending GICv2 SGI, one bit per requesting vCPU.
+    pub sgi_sources: u8,
+    /// Active state.
+    pub active: bool,
+    /// vCPU a shared interrupt is active on, only meaningful while `active` is set.
+    pub active_vcpu: usize,
+    /// Priority (`GICD_IPRIORITYR<n>`), lower values are higher priority.
+    pub priority: u8,
+    /// Group 1 if set, Group 0 otherwise (`GICD_IGROUPR<n>`).
+    pub group1: bool,
+    /// Group modifier (`GICD_IGRPMODR<n>`), selects Secure Group 1 for Group 0 interrupts.
+    pub grpmod: bool,
+    /// Non-maskable interrupt attribute (`GICD_INMIR<n>`).
+    pub nmi: bool,
+    /// Trigger mode (`GICD_ICFGR<n>`).
+    pub trigger: TriggerMode,
+    /// GICv2 CPU target list (`GICD_ITARGETSR<n>`).
+    pub targets: u8,
+    /// GICv3 routing information (`GICD_IROUTER<n>`).
+    pub route: u64,
+    /// Event counters.
+    #[cfg(feature = "stats")]
+    pub stats: crate::stats::IrqStats,
+}
+
+impl VirtIrq {
+    /// Creates an interrupt in its reset state.
+    pub fn new(intid: u32) -> Self {
+        let is_sgi = (intid as usize) < SGI_NUM;
+        Self {
+            intid,
+            // SGIs are permanently enabled, as in most GICv2 implementations.
+            enabled: is_sgi,
+            pending_latch: false,
+            line_level: false,
+            sgi_sources: 0,
+            active: false,
+            active_vcpu: 0,
+            priority: 0,
+            group1: false,
+            grpmod: false,
+            nmi: false,
+            trigger: if is_sgi {
+                TriggerMode::Edge
+            } else {
+                TriggerMode::Level
+            },
+            targets: 0,
+            route: 0,
+            #[cfg(feature = "stats")]
+            stats: Default::default(),
+        }
+    }
+
+    /// Returns whether this interrupt is banked per vCPU (an SGI, a PPI or an Extended PPI).
+    pub fn is_private(&self) -> bool {
+        is_private_intid(self.intid)
+    }
+
+    /// Returns whether this interrupt is an SGI.
+    pub fn is_sgi(&self) -> bool {
+        (self.intid as usize) < SGI_NUM
+    }
+
+    /// Returns the group of the interrupt.
+    pub fn group(&self) -> IrqGroup {
+        match (self.group1, self.grpmod) {
+            (true, _) => IrqGroup::Group1NonSecure,
+            (false, false) => IrqGroup::Group0,
+            (false, true) => IrqGroup::Group1Secure,
+        }
+    }
+
+    /// Returns whether the interrupt is pending.
+    pub fn is_pending(&self) -> bool {
+        self.pending_latch
+            || self.sgi_sources != 0
+            || (self.trigger == TriggerMode::Level && self.line_level)
+    }
+
+    /// Returns whether the interrupt needs a List register.
+    pub fn is_queued(&self) -> bool {
+        self.active || (self.enabled && self.is_pending())
+    }
+}
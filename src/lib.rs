@@ -1 +1,91 @@
-pub use Vgicv3::Vgicv3;
+#![no_std]
+#![recursion_limit = "256"]
+
+extern crate alloc;
+
+/// Logs a vGIC event at trace level if the `trace` feature is enabled.
+#[cfg(feature = "hv")]
+macro_rules! vgic_trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        log::trace!($($arg)*);
+    };
+}
+
+pub mod regs;
+
+#[cfg(feature = "hv")]
+mod acpi;
+#[cfg(feature = "hv")]
+mod apr;
+#[cfg(feature = "hv")]
+mod caps;
+#[cfg(feature = "hv")]
+mod config;
+#[cfg(feature = "hv")]
+mod consts;
+#[cfg(feature = "hv")]
+mod cpuif;
+#[cfg(feature = "hv")]
+mod devops_impl;
+#[cfg(feature = "hv")]
+mod dump;
+#[cfg(feature = "hv")]
+mod fdt;
+#[cfg(feature = "hv")]
+mod hal;
+#[cfg(all(feature = "hv", target_arch = "aarch64"))]
+mod host;
+#[cfg(feature = "hv")]
+mod inject;
+#[cfg(feature = "hv")]
+mod interrupt;
+#[cfg(feature = "hv")]
+mod maintenance;
+#[cfg(all(test, feature = "hv"))]
+mod mock;
+#[cfg(feature = "hv")]
+mod resample;
+#[cfg(feature = "hv")]
+mod stats;
+#[cfg(feature = "hv")]
+mod v2m;
+#[cfg(feature = "hv")]
+mod vcpu;
+#[cfg(feature = "hv")]
+mod vgicd;
+#[cfg(feature = "hv")]
+mod vgicr;
+#[cfg(feature = "hv")]
+mod vgicv3;
+
+#[cfg(feature = "hv")]
+pub use acpi::{acpi_checksum, its_madt_entry};
+#[cfg(feature = "hv")]
+pub use apr::AprState;
+#[cfg(feature = "hv")]
+pub use caps::HostGicCaps;
+#[cfg(feature = "hv")]
+pub use config::{GicVersion, SecurityModel, Vgicv3Config};
+#[cfg(feature = "hv")]
+pub use cpuif::IccReg;
+#[cfg(feature = "hv")]
+pub use fdt::{FdtNode, FdtProperty, FdtValue, its_fdt_node};
+#[cfg(feature = "hv")]
+pub use hal::{GichOps, VgicHostOps};
+#[cfg(all(feature = "hv", target_arch = "aarch64"))]
+pub use host::HostGicv3;
+#[cfg(feature = "hv")]
+pub use interrupt::{IrqGroup, TriggerMode};
+#[cfg(feature = "hv")]
+pub use maintenance::MaintenanceActions;
+#[cfg(feature = "hv")]
+pub use resample::IrqResampler;
+#[cfg(feature = "stats")]
+pub use stats::IrqStats;
+#[cfg(feature = "hv")]
+pub use v2m::Gicv2mFrame;
+#[cfg(feature = "hv")]
+pub use vgicr::Vgicr;
+#[cfg(feature = "hv")]
+pub use vgicv3::{GiccMapping, Vgicv3};
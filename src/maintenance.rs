@@ -0,0 +1,326 @@
+//! Maintenance interrupt handling.
+
+use alloc::vec::Vec;
+
+use tock_registers::LocalRegisterCopy;
+
+use crate::regs::gich::{GICH_HCR, ListRegister, MisrValue};
+use crate::resample::ResampleNotification;
+use crate::vcpu::CpuLists;
+use crate::vgicv3::Vgicv3;
+
+/// Actions the hypervisor must take after [`Vgicv3::handle_maintenance`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceActions {
+    /// Interrupts deactivated by the guest whose List register requested an EOI
+    /// maintenance interrupt, in List register order.
+    pub eoi: Vec<u32>,
+    /// The List registers no longer reflect what should be presented to the vCPU:
+    /// [`Vgicv3::sync_lrs`] and [`Vgicv3::flush_lrs`] must run before it resumes.
+    pub refill_lrs: bool,
+}
+
+impl Vgicv3 {
+    /// Handles the maintenance interrupt of the virtual CPU interface.
+    ///
+    /// Must be called on the physical CPU running `vcpu_id`, from the handler of the
+    /// maintenance PPI (usually INTID 25), before [`Vgicv3::sync_lrs`]. The conditions
+    /// reported in `GICH_MISR` are handled and their enables cleared in `GICH_HCR`:
+    ///
+    /// - EOI: the `pINTID` EOI bit of the List registers flagged in `GICH_EISR` is
+    ///   cleared and their interrupts are reported in [`MaintenanceActions::eoi`]. Their
+    ///   resamplers are notified once the vGIC locks are released.
+    /// - U and NP: List registers have been freed for interrupts still queued.
+    /// - LRENP: the guest has deactivated `GICH_HCR.EOICount` interrupts that were not in
+    ///   a List register; the highest priority active ones not loaded are deactivated.
+    /// - VGrp0E/VGrp0D/VGrp1E/VGrp1D: the guest has changed the group enables that
+    ///   [`Vgicv3::flush_lrs`] armed a trap for. They are recorded from `GICH_VMCR` and
+    ///   1-of-N SPIs are re-evaluated.
+    pub fn handle_maintenance(&self, vcpu_id: usize) -> MaintenanceActions {
+        let misr = MisrValue::from(self.host.read_misr());
+        let eisr = self.host.read_eisr();
+        let elrsr = self.host.read_elrsr();
+        vgic_trace!("vgicv3: vCPU {vcpu_id} maintenance {misr:?} EISR {eisr:#x} ELRSR {elrsr:#x}");
+
+        let mut notify = Vec::new();
+        let mut deactivated = Vec::new();
+        let mut group_enables = None;
+        let lists = self.cpus[vcpu_id].lists.lock();
+
+        let mut actions = MaintenanceActions::default();
+        let loaded = lists.lrs.len();
+        if misr.eoi {
+            for n in (0..loaded).filter(|n| eisr & (1 << n) != 0) {
+                let mut lr = self.read_list_register(n);
+                if lr.hw {
+                    continue;
+                }
+                actions.eoi.push(lr.vintid);
+                if let Some(irq) = self.irq(vcpu_id, lr.vintid) {
+                    irq.lock().count_maintenance();
+                }
+                self.resample_eoi(vcpu_id, lr.vintid, &mut notify);
+                // Deassert the condition. The value kept in `lrs` still carries the state
+                // presented to the guest, for `sync_lrs` to account for the deactivation.
+                lr.pintid &= !ListRegister::PINTID_EOI;
+                self.write_list_register(n, lr);
+            }
+        }
+
+        let mut hcr = LocalRegisterCopy::<u32, GICH_HCR::Register>::new(self.host.read_hcr());
+        if misr.lr_entry_not_present {
+            let count = hcr.read(GICH_HCR::EOICount) as usize;
+            deactivated = self.deactivate_unlisted(&lists, vcpu_id, count, &mut notify);
+            hcr.modify(GICH_HCR::EOICount.val(0) + GICH_HCR::LRENPIE::Disabled);
+            actions.refill_lrs = true;
+        }
+        if misr.underflow || misr.no_pending {
+            hcr.modify(GICH_HCR::UIE::Disabled + GICH_HCR::NPIE::Disabled);
+            let free_lrs = (0..loaded).any(|n| elrsr & (1 << n) != 0);
+            actions.refill_lrs |= free_lrs && lists.ap_list.len() > loaded;
+        }
+        if misr.vgrp0_enabled || misr.vgrp0_disabled || misr.vgrp1_enabled || misr.vgrp1_disabled {
+            hcr.modify(
+                GICH_HCR::VGrp0EIE::Disabled
+                    + GICH_HCR::VGrp0DIE::Disabled
+                    + GICH_HCR::VGrp1EIE::Disabled
+                    + GICH_HCR::VGrp1DIE::Disabled,
+            );
+            group_enables = Some(LocalRegisterCopy::new(self.host.read_vmcr()));
+            actions.refill_lrs = true;
+        }
+        self.host.write_hcr(hcr.get());
+        drop(lists);
+
+        let mut kicks = Vec::new();
+        for intid in deactivated {
+            self.update_irq(vcpu_id, intid, &mut kicks);
+        }
+        if let Some(vmcr) = group_enables {
+            self.set_group_enables(vcpu_id, vmcr, &mut kicks);
+        }
+        Self::notify_resamplers(notify);
+        self.kick_vcpus(&kicks);
+        actions
+    }
+
+    /// Deactivates the `count` highest priority active interrupts of a vCPU that are not
+    /// held in a List register, returning their INTIDs.
+    fn deactivate_unlisted(
+        &self,
+        lists: &CpuLists,
+        vcpu_id: usize,
+        count: usize,
+        notify: &mut Vec<ResampleNotification>,
+    ) -> Vec<u32> {
+        let unlisted = &lists.ap_list[lists.lrs.len().min(lists.ap_list.len())..];
+        let mut active: Vec<(u8, u32)> = unlisted
+            .iter()
+            .filter_map(|&intid| {
+                let irq = self.irq(vcpu_id, intid)?.lock();
+                irq.active.then_some((irq.priority, intid))
+            })
+            .collect();
+        active.sort_unstable();
+        active.truncate(count);
+        for &(_, intid) in &active {
+            vgic_trace!("vgicv3: vCPU {vcpu_id} INTID {intid} deactivated without List register");
+            let mut irq = self.irq(vcpu_id, intid).unwrap().lock();
+            irq.active = false;
+            irq.count_maintenance();
+            drop(irq);
+            self.resample_eoi(vcpu_id, intid, notify);
+        }
+        active.into_iter().map(|(_, intid)| intid).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::consts::*;
+    use crate::hal::GichOps;
+    use crate::mock::{self, MockHost};
+    use crate::regs::gich::{GICH_VMCR, LrState};
+    use crate::vgicv3::Access;
+
+    fn config(vcpu_num: usize) -> Vgicv3Config {
+        Vgicv3Config {
+            vcpu_num,
+            ..Default::default()
+        }
+    }
+
+    fn hcr(host: &MockHost) -> LocalRegisterCopy<u32, GICH_HCR::Register> {
+        LocalRegisterCopy::new(host.read_hcr())
+    }
+
+    fn set_misr(host: &MockHost, misr: MisrValue) {
+        host.regs.lock().misr = misr.into();
+    }
+
+    #[test]
+    fn eoi_clears_the_request_and_reports_the_interrupt() {
+        let (vgic, host) = mock::vgic(config(1));
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        mock::enable_spi(&vgic, 41, 0xb0, 0);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        vgic.set_irq_level(0, 41, true).unwrap();
+        vgic.flush_lrs(0);
+
+        // The guest deactivates the interrupt in LR1, which requested an EOI.
+        let lr = host.lr(1);
+        host.set_lr(
+            1,
+            ListRegister {
+                state: LrState::Inactive,
+                pintid: ListRegister::PINTID_EOI,
+                ..lr
+            },
+        );
+        set_misr(
+            &host,
+            MisrValue {
+                eoi: true,
+                ..Default::default()
+            },
+        );
+        host.regs.lock().eisr = 1 << 1;
+        let actions = vgic.handle_maintenance(0);
+        assert_eq!(
+            actions,
+            MaintenanceActions {
+                eoi: alloc::vec![41],
+                refill_lrs: false,
+            }
+        );
+        assert_eq!(host.lr(1).pintid & ListRegister::PINTID_EOI, 0);
+        assert_eq!(host.lr(0).vintid, 40);
+    }
+
+    #[test]
+    fn underflow_refills_only_with_queued_interrupts() {
+        let (vgic, host) = mock::vgic(config(1));
+        for intid in 40..45 {
+            mock::enable_spi(&vgic, intid, 0xa0, 0);
+            vgic.set_irq_level(0, intid, true).unwrap();
+        }
+        vgic.flush_lrs(0);
+        assert!(hcr(&host).is_set(GICH_HCR::UIE));
+
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Inactive,
+                ..host.lr(0)
+            },
+        );
+        host.regs.lock().elrsr = 1 << 0;
+        set_misr(
+            &host,
+            MisrValue {
+                underflow: true,
+                ..Default::default()
+            },
+        );
+        let actions = vgic.handle_maintenance(0);
+        assert!(actions.refill_lrs);
+        assert!(actions.eoi.is_empty());
+        assert!(!hcr(&host).is_set(GICH_HCR::UIE));
+
+        // Once everything fits, a free List register is not worth an exit.
+        vgic.sync_lrs(0);
+        vgic.set_irq_level(0, 40, false).unwrap();
+        vgic.flush_lrs(0);
+        assert!(!hcr(&host).is_set(GICH_HCR::UIE));
+        set_misr(
+            &host,
+            MisrValue {
+                no_pending: true,
+                ..Default::default()
+            },
+        );
+        host.regs.lock().hcr |= GICH_HCR::NPIE::Enabled.value;
+        assert!(!vgic.handle_maintenance(0).refill_lrs);
+        assert!(!hcr(&host).is_set(GICH_HCR::NPIE));
+    }
+
+    #[test]
+    fn entry_not_present_deactivates_unlisted_interrupts() {
+        let (vgic, host) = mock::vgic(config(1));
+        for intid in 40..46 {
+            mock::enable_spi(&vgic, intid, 0x10 * (intid as u8 - 39), 0);
+        }
+        vgic.handle_write32(GICD_ISACTIVER + 4, 0x3f << 8);
+        vgic.flush_lrs(0);
+        assert!(hcr(&host).is_set(GICH_HCR::LRENPIE));
+        let loaded: Vec<u32> = (0..4).map(|n| host.lr(n).vintid).collect();
+        assert_eq!(loaded, [40, 41, 42, 43]);
+
+        // The guest deactivates one interrupt it has no List register for.
+        host.regs.lock().hcr |= GICH_HCR::EOICount.val(1).value;
+        set_misr(
+            &host,
+            MisrValue {
+                lr_entry_not_present: true,
+                ..Default::default()
+            },
+        );
+        let actions = vgic.handle_maintenance(0);
+        assert!(actions.refill_lrs);
+        assert!(actions.eoi.is_empty());
+        assert_eq!(hcr(&host).read(GICH_HCR::EOICount), 0);
+        assert!(!hcr(&host).is_set(GICH_HCR::LRENPIE));
+        assert!(!vgic.irq(0, 44).unwrap().lock().active);
+        assert!(vgic.irq(0, 45).unwrap().lock().active);
+        assert!(vgic.irq(0, 43).unwrap().lock().active);
+    }
+
+    #[test]
+    fn group_enable_changes_are_trapped() {
+        let (vgic, host) = mock::vgic(config(2));
+        let mut kicks = Vec::new();
+        let access = Access {
+            vcpu_id: 1,
+            secure: true,
+            redistributor: false,
+        };
+        vgic.gicr_write(
+            access,
+            GICR_WAKER,
+            0,
+            GICR_WAKER_PROCESSOR_SLEEP,
+            &mut kicks,
+        );
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.handle_write64(GICD_IROUTER + 8 * 40, GICD_IROUTER_IRM as usize);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        host.take_kicks();
+
+        vgic.flush_lrs(1);
+        let armed = hcr(&host);
+        assert!(armed.is_set(GICH_HCR::VGrp0EIE) && armed.is_set(GICH_HCR::VGrp1EIE));
+        assert!(!armed.is_set(GICH_HCR::VGrp0DIE) && !armed.is_set(GICH_HCR::VGrp1DIE));
+
+        // vCPU 1 enables Group 1: the 1-of-N SPI moves to it right away.
+        host.write_vmcr(GICH_VMCR::VENG1::SET.value);
+        set_misr(
+            &host,
+            MisrValue {
+                vgrp1_enabled: true,
+                ..Default::default()
+            },
+        );
+        assert!(vgic.handle_maintenance(1).refill_lrs);
+        assert!(!hcr(&host).is_set(GICH_HCR::VGrp1EIE));
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+
+        vgic.sync_lrs(1);
+        vgic.flush_lrs(1);
+        let armed = hcr(&host);
+        assert!(armed.is_set(GICH_HCR::VGrp0EIE) && armed.is_set(GICH_HCR::VGrp1DIE));
+        assert!(!armed.is_set(GICH_HCR::VGrp0DIE) && !armed.is_set(GICH_HCR::VGrp1EIE));
+        assert_eq!(host.lr(0).vintid, 40);
+    }
+}
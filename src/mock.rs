@@ -0,0 +1,246 @@
+//! In-memory host virtual CPU interface for unit tests.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axaddrspace::HostPhysAddr;
+use spin::Mutex;
+
+use crate::config::Vgicv3Config;
+use crate::consts::{
+    GICD_CTLR, GICD_CTLR_ENABLE_GRP0, GICD_CTLR_ENABLE_GRP1NS, GICD_IGROUPR, GICD_IPRIORITYR,
+    GICD_IROUTER, GICD_ISENABLER, GICH_APR_NUM_MAX, GICH_LR_NUM_MAX,
+};
+use crate::hal::{GichOps, VgicHostOps};
+use crate::regs::gich::{ListRegister, VtrInfo};
+use crate::vgicv3::Vgicv3;
+
+/// Registers of the mock virtual CPU interface.
+#[derive(Default)]
+pub(crate) struct MockRegs {
+    pub hcr: u32,
+    pub vtr: u32,
+    pub vmcr: u32,
+    pub misr: u32,
+    pub eisr: u32,
+    pub elrsr: u32,
+    pub ap0r: [u32; GICH_APR_NUM_MAX],
+    pub apr: [u32; GICH_APR_NUM_MAX],
+    /// Number of active priority registers of each group accessible.
+    pub apr_count: usize,
+    pub lrs: [u32; GICH_LR_NUM_MAX],
+    /// Whether the List registers are accessible as `ICH_LR<n>_EL2`.
+    pub ich_lr: bool,
+    pub ich_lrs: [u64; GICH_LR_NUM_MAX],
+    /// Physical address of the GICV frame, if the host implements legacy operation.
+    pub gicv_base: Option<HostPhysAddr>,
+}
+
+/// A host whose GICH registers live in memory and which records the vCPUs it kicks.
+#[derive(Default)]
+pub(crate) struct MockHost {
+    pub regs: Mutex<MockRegs>,
+    /// vCPU performing the emulated register accesses.
+    pub current_vcpu: AtomicUsize,
+    pub kicks: Mutex<Vec<usize>>,
+}
+
+impl MockHost {
+    /// Returns the `GICH_VTR` of the mock: 4 List registers with 5 priority and
+    /// preemption bits.
+    pub fn default_vtr() -> VtrInfo {
+        VtrInfo {
+            list_regs: 4,
+            pre_bits: 5,
+            pri_bits: 5,
+            id_bits: 0,
+            seis: false,
+            a3v: false,
+        }
+    }
+
+    /// Creates a host reporting `vtr`.
+    pub fn new(vtr: VtrInfo) -> Arc<MockHost> {
+        let host = MockHost::default();
+        let mut regs = host.regs.lock();
+        regs.vtr = vtr.into();
+        regs.apr_count = GICH_APR_NUM_MAX;
+        drop(regs);
+        Arc::new(host)
+    }
+
+    /// Creates a host reporting `vtr` that gives access to `ICH_LR<n>_EL2`.
+    pub fn with_ich_lr(vtr: VtrInfo) -> Arc<MockHost> {
+        let host = Self::new(vtr);
+        host.regs.lock().ich_lr = true;
+        host
+    }
+
+    /// Creates a host reporting `vtr` that implements legacy operation.
+    pub fn with_gicv(vtr: VtrInfo) -> Arc<MockHost> {
+        let host = Self::new(vtr);
+        host.regs.lock().gicv_base = Some(HostPhysAddr::from(0x2c02_0000));
+        host
+    }
+
+    /// Returns the decoded List register `n`, from `ICH_LR<n>_EL2` if the host gives
+    /// access to it and `GICH_LR<n>` otherwise.
+    pub fn lr(&self, n: usize) -> ListRegister {
+        let regs = self.regs.lock();
+        if regs.ich_lr {
+            regs.ich_lrs[n].into()
+        } else {
+            regs.lrs[n].into()
+        }
+    }
+
+    /// Sets List register `n`, as the guest acknowledging or deactivating an interrupt
+    /// would.
+    pub fn set_lr(&self, n: usize, lr: ListRegister) {
+        let mut regs = self.regs.lock();
+        if regs.ich_lr {
+            regs.ich_lrs[n] = lr.into();
+        } else {
+            regs.lrs[n] = lr.into();
+        }
+    }
+
+    /// Makes `vcpu_id` the vCPU performing the next register accesses.
+    pub fn set_current_vcpu(&self, vcpu_id: usize) {
+        self.current_vcpu.store(vcpu_id, Ordering::Relaxed);
+    }
+
+    /// Returns and clears the vCPUs kicked so far.
+    pub fn take_kicks(&self) -> Vec<usize> {
+        core::mem::take(&mut *self.kicks.lock())
+    }
+}
+
+impl GichOps for MockHost {
+    fn read_hcr(&self) -> u32 {
+        self.regs.lock().hcr
+    }
+
+    fn write_hcr(&self, value: u32) {
+        self.regs.lock().hcr = value;
+    }
+
+    fn read_vtr(&self) -> u32 {
+        self.regs.lock().vtr
+    }
+
+    fn read_vmcr(&self) -> u32 {
+        self.regs.lock().vmcr
+    }
+
+    fn write_vmcr(&self, value: u32) {
+        self.regs.lock().vmcr = value;
+    }
+
+    fn read_misr(&self) -> u32 {
+        self.regs.lock().misr
+    }
+
+    fn read_eisr(&self) -> u32 {
+        self.regs.lock().eisr
+    }
+
+    fn read_elrsr(&self) -> u32 {
+        self.regs.lock().elrsr
+    }
+
+    fn read_apr(&self, n: usize) -> u32 {
+        self.regs.lock().apr[n]
+    }
+
+    fn write_apr(&self, n: usize, value: u32) {
+        self.regs.lock().apr[n] = value;
+    }
+
+    fn read_ap0r(&self, n: usize) -> u32 {
+        self.regs.lock().ap0r[n]
+    }
+
+    fn write_ap0r(&self, n: usize, value: u32) {
+        self.regs.lock().ap0r[n] = value;
+    }
+
+    fn apr_count(&self) -> usize {
+        self.regs.lock().apr_count
+    }
+
+    fn read_lr(&self, n: usize) -> u32 {
+        self.regs.lock().lrs[n]
+    }
+
+    fn write_lr(&self, n: usize, value: u32) {
+        self.regs.lock().lrs[n] = value;
+    }
+
+    fn supports_ich_lr(&self) -> bool {
+        self.regs.lock().ich_lr
+    }
+
+    fn read_ich_lr(&self, n: usize) -> u64 {
+        self.regs.lock().ich_lrs[n]
+    }
+
+    fn write_ich_lr(&self, n: usize, value: u64) {
+        self.regs.lock().ich_lrs[n] = value;
+    }
+
+    fn gicv_base(&self) -> Option<HostPhysAddr> {
+        self.regs.lock().gicv_base
+    }
+}
+
+impl VgicHostOps for MockHost {
+    fn current_vcpu_id(&self) -> usize {
+        self.current_vcpu.load(Ordering::Relaxed)
+    }
+
+    fn kick_vcpu(&self, vcpu_id: usize) {
+        self.kicks.lock().push(vcpu_id);
+    }
+}
+
+/// Creates a vGIC with the given configuration on a mock host reporting `vtr`.
+pub(crate) fn vgic_with_vtr(config: Vgicv3Config, vtr: VtrInfo) -> (Vgicv3, Arc<MockHost>) {
+    let host = MockHost::new(vtr);
+    let vgic = Vgicv3::new(config, host.clone()).unwrap();
+    (vgic, host)
+}
+
+/// Creates a vGIC with the given configuration on a mock host with the default `GICH_VTR`.
+pub(crate) fn vgic(config: Vgicv3Config) -> (Vgicv3, Arc<MockHost>) {
+    vgic_with_vtr(config, MockHost::default_vtr())
+}
+
+/// Enables both groups in the distributor, and makes `intid` an enabled Group 1 SPI with
+/// the given priority, routed to `vcpu_id`.
+///
+/// The registers are written by the vCPU set with [`MockHost::set_current_vcpu`].
+pub(crate) fn enable_spi(vgic: &Vgicv3, intid: u32, priority: u8, vcpu_id: usize) {
+    let (n, bit) = (intid as usize / 32, 1 << (intid % 32));
+    vgic.handle_write32(
+        GICD_CTLR,
+        (GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS) as usize,
+    );
+    let group = vgic.handle_read32(GICD_IGROUPR + 4 * n).unwrap();
+    vgic.handle_write32(GICD_IGROUPR + 4 * n, group | bit);
+    vgic.handle_write32(GICD_ISENABLER + 4 * n, bit);
+    vgic.handle_write8(GICD_IPRIORITYR + intid as usize, priority as usize);
+    route_spi(vgic, intid, vcpu_id);
+}
+
+/// Routes an SPI to `vcpu_id` through `GICD_IROUTER<n>`.
+pub(crate) fn route_spi(vgic: &Vgicv3, intid: u32, vcpu_id: usize) {
+    let affinity = vgic.config().vcpu_affinity(vcpu_id);
+    vgic.handle_write64(GICD_IROUTER + 8 * intid as usize, affinity as usize);
+}
+
+/// Returns the ap_list of `vcpu_id`.
+pub(crate) fn ap_list(vgic: &Vgicv3, vcpu_id: usize) -> Vec<u32> {
+    vgic.cpus[vcpu_id].lists.lock().ap_list.clone()
+}
@@ -0,0 +1,268 @@
+//! Distributor register map, GICD
+//!
+//! ## Purpose
+//!
+//! Describes the complete 64KB GICv3 distributor frame, including the GICv3.1
+//! Extended SPI banks and the GICv3.3 NMI banks, together with the bitfields of
+//! its control and identification registers.
+//!
+//! ## Usage
+//!
+//! [`GicdRegs`] can be laid over a real distributor frame by a host GIC driver.
+//! The emulator derives its decode offsets from the same block, see `consts.rs`.
+//!
+//! With `GICD_CTLR.ARE == 0` the frame keeps the GICv2 layout of its first 4KB:
+//! `ITARGETSR`, `SGIR`, `CPENDSGIR` and `SPENDSGIR` are only meaningful then,
+//! while `IROUTER` is only meaningful with `ARE == 1`.
+
+use tock_registers::register_bitfields;
+use tock_registers::register_structs;
+use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
+
+use super::GICD_SGIR;
+
+register_bitfields! {u32,
+    pub GICD_CTLR [
+        /// [31] RWP
+        /// Register Write Pending.
+        RWP OFFSET(31) NUMBITS(1) [],
+        /// [8] nASSGIreq
+        /// Whether SGIs are delivered with an active state (GICv4.1).
+        nASSGIreq OFFSET(8) NUMBITS(1) [],
+        /// [7] E1NWF
+        /// Enable 1 of N Wakeup Functionality.
+        E1NWF OFFSET(7) NUMBITS(1) [],
+        /// [6] DS
+        /// Disable Security. Secure view only, RAO/WI with a single security state.
+        DS OFFSET(6) NUMBITS(1) [],
+        /// [5] ARE_NS
+        /// Affinity Routing Enable, Non-secure state. Secure view only.
+        ARE_NS OFFSET(5) NUMBITS(1) [],
+        /// [4] ARE_S
+        /// Affinity Routing Enable, Secure state. This is `ARE` with a single
+        /// security state and `ARE_NS` in the Non-secure view.
+        ARE_S OFFSET(4) NUMBITS(1) [],
+        /// [2] EnableGrp1S
+        /// Enable Secure Group 1 interrupts. Secure view only.
+        EnableGrp1S OFFSET(2) NUMBITS(1) [],
+        /// [1] EnableGrp1NS
+        /// Enable Non-secure Group 1 interrupts. This is `EnableGrp1A` in the
+        /// Non-secure view.
+        EnableGrp1NS OFFSET(1) NUMBITS(1) [],
+        /// [0] EnableGrp0
+        /// Enable Group 0 interrupts. This is `EnableGrp1` in the Non-secure view.
+        EnableGrp0 OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GICD_TYPER [
+        /// [31:27] ESPI_range
+        /// Maximum Extended SPI INTID is `(32 * (ESPI_range + 1) + 4095)`.
+        ESPI_range OFFSET(27) NUMBITS(5) [],
+        /// [26] RSS
+        /// Range Selector Support for the `RS` field of `ICC_SGI1R_EL1`.
+        RSS OFFSET(26) NUMBITS(1) [],
+        /// [25] No1N
+        /// 1 of N SPI interrupts are not supported.
+        No1N OFFSET(25) NUMBITS(1) [],
+        /// [24] A3V
+        /// Affinity 3 valid.
+        A3V OFFSET(24) NUMBITS(1) [],
+        /// [23:19] IDbits
+        /// Number of interrupt identifier bits supported, minus one.
+        IDbits OFFSET(19) NUMBITS(5) [],
+        /// [18] DVIS
+        /// Direct injection of virtual LPIs supported.
+        DVIS OFFSET(18) NUMBITS(1) [],
+        /// [17] LPIS
+        /// LPIs are supported.
+        LPIS OFFSET(17) NUMBITS(1) [],
+        /// [16] MBIS
+        /// Message-based SPIs are supported.
+        MBIS OFFSET(16) NUMBITS(1) [],
+        /// [15:11] num_LPIs
+        /// Number of LPIs supported when `GICR_PROPBASER` limits them.
+        num_LPIs OFFSET(11) NUMBITS(5) [],
+        /// [10] SecurityExtn
+        /// Two security states are implemented.
+        SecurityExtn OFFSET(10) NUMBITS(1) [],
+        /// [9] NMI
+        /// Non-maskable interrupts are supported.
+        NMI OFFSET(9) NUMBITS(1) [],
+        /// [8] ESPI
+        /// The Extended SPI range is implemented.
+        ESPI OFFSET(8) NUMBITS(1) [],
+        /// [7:5] CPUNumber
+        /// Number of PEs that can be used when affinity routing is disabled, minus one.
+        CPUNumber OFFSET(5) NUMBITS(3) [],
+        /// [4:0] ITLinesNumber
+        /// Maximum SPI INTID is `(32 * (ITLinesNumber + 1) - 1)`.
+        ITLinesNumber OFFSET(0) NUMBITS(5) []
+    ],
+
+    pub GICD_IIDR [
+        /// [31:24] ProductID
+        ProductID OFFSET(24) NUMBITS(8) [],
+        /// [19:16] Variant
+        Variant OFFSET(16) NUMBITS(4) [],
+        /// [15:12] Revision
+        Revision OFFSET(12) NUMBITS(4) [],
+        /// [11:0] Implementer
+        /// JEP106 code of the implementer, 0x43b for ARM.
+        Implementer OFFSET(0) NUMBITS(12) []
+    ],
+
+    pub GICD_TYPER2 [
+        /// [8] nASSGIcap
+        /// `GICD_CTLR.nASSGIreq` is implemented.
+        nASSGIcap OFFSET(8) NUMBITS(1) [],
+        /// [7] VIL
+        /// `VID` is valid.
+        VIL OFFSET(7) NUMBITS(1) [],
+        /// [4:0] VID
+        /// Number of vPE ID bits supported, minus one.
+        VID OFFSET(0) NUMBITS(5) []
+    ],
+
+    pub GICD_STATUSR [
+        /// [3] WROD
+        /// Write to a read-only register was detected.
+        WROD OFFSET(3) NUMBITS(1) [],
+        /// [2] RWOD
+        /// Read of a write-only register was detected.
+        RWOD OFFSET(2) NUMBITS(1) [],
+        /// [1] WRD
+        /// Write to a reserved location was detected.
+        WRD OFFSET(1) NUMBITS(1) [],
+        /// [0] RRD
+        /// Read of a reserved location was detected.
+        RRD OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GICD_SETSPI [
+        /// [12:0] INTID
+        /// The INTID of the SPI (or Extended SPI) to set or clear pending.
+        INTID OFFSET(0) NUMBITS(13) []
+    ],
+
+    pub GICD_PIDR2 [
+        /// [7:4] ArchRev
+        /// Architecture revision of the GIC.
+        ArchRev OFFSET(4) NUMBITS(4) [
+            GICv1 = 0x1,
+            GICv2 = 0x2,
+            GICv3 = 0x3,
+            GICv4 = 0x4
+        ],
+        /// [3] JEDEC
+        /// A JEDEC-assigned identity code is used.
+        JEDEC OFFSET(3) NUMBITS(1) [],
+        /// [2:0] DES_1
+        /// Bits [6:4] of the JEP106 identity code.
+        DES_1 OFFSET(0) NUMBITS(3) []
+    ]
+}
+
+register_bitfields! {u64,
+    pub GICD_IROUTER [
+        /// [39:32] Aff3
+        Aff3 OFFSET(32) NUMBITS(8) [],
+        /// [31] Interrupt_Routing_Mode
+        Interrupt_Routing_Mode OFFSET(31) NUMBITS(1) [
+            /// Route to the PE specified by the affinity fields.
+            Aff = 0,
+            /// Route to any PE participating in 1 of N distribution.
+            Any = 1
+        ],
+        /// [23:16] Aff2
+        Aff2 OFFSET(16) NUMBITS(8) [],
+        /// [15:8] Aff1
+        Aff1 OFFSET(8) NUMBITS(8) [],
+        /// [7:0] Aff0
+        Aff0 OFFSET(0) NUMBITS(8) []
+    ]
+}
+
+register_structs! {
+    /// The GICv3 distributor frame.
+    ///
+    /// Banks indexed by INTID (`IPRIORITYR`, `ITARGETSR`, `IROUTER` and their
+    /// Extended SPI variants) are sized so that entry `n` describes INTID `n`
+    /// (or INTID `4096 + n` for the E variants); entries of SGIs and PPIs are
+    /// reserved once affinity routing is enabled.
+    #[allow(non_snake_case)]
+    pub GicdRegs {
+        (0x0000 => pub CTLR: ReadWrite<u32, GICD_CTLR::Register>),
+        (0x0004 => pub TYPER: ReadOnly<u32, GICD_TYPER::Register>),
+        (0x0008 => pub IIDR: ReadOnly<u32, GICD_IIDR::Register>),
+        (0x000c => pub TYPER2: ReadOnly<u32, GICD_TYPER2::Register>),
+        (0x0010 => pub STATUSR: ReadWrite<u32, GICD_STATUSR::Register>),
+        (0x0014 => _reserved0),
+        (0x0040 => pub SETSPI_NSR: WriteOnly<u32, GICD_SETSPI::Register>),
+        (0x0044 => _reserved1),
+        (0x0048 => pub CLRSPI_NSR: WriteOnly<u32, GICD_SETSPI::Register>),
+        (0x004c => _reserved2),
+        (0x0050 => pub SETSPI_SR: WriteOnly<u32, GICD_SETSPI::Register>),
+        (0x0054 => _reserved3),
+        (0x0058 => pub CLRSPI_SR: WriteOnly<u32, GICD_SETSPI::Register>),
+        (0x005c => _reserved4),
+        (0x0080 => pub IGROUPR: [ReadWrite<u32>; 32]),
+        (0x0100 => pub ISENABLER: [ReadWrite<u32>; 32]),
+        (0x0180 => pub ICENABLER: [ReadWrite<u32>; 32]),
+        (0x0200 => pub ISPENDR: [ReadWrite<u32>; 32]),
+        (0x0280 => pub ICPENDR: [ReadWrite<u32>; 32]),
+        (0x0300 => pub ISACTIVER: [ReadWrite<u32>; 32]),
+        (0x0380 => pub ICACTIVER: [ReadWrite<u32>; 32]),
+        (0x0400 => pub IPRIORITYR: [ReadWrite<u8>; 1024]),
+        (0x0800 => pub ITARGETSR: [ReadWrite<u8>; 1024]),
+        (0x0c00 => pub ICFGR: [ReadWrite<u32>; 64]),
+        (0x0d00 => pub IGRPMODR: [ReadWrite<u32>; 32]),
+        (0x0d80 => _reserved5),
+        (0x0e00 => pub NSACR: [ReadWrite<u32>; 64]),
+        (0x0f00 => pub SGIR: WriteOnly<u32, GICD_SGIR::Register>),
+        (0x0f04 => _reserved6),
+        (0x0f10 => pub CPENDSGIR: [ReadWrite<u8>; 16]),
+        (0x0f20 => pub SPENDSGIR: [ReadWrite<u8>; 16]),
+        (0x0f30 => _reserved7),
+        (0x0f80 => pub INMIR: [ReadWrite<u32>; 32]),
+        (0x1000 => pub IGROUPRE: [ReadWrite<u32>; 32]),
+        (0x1080 => _reserved8),
+        (0x1200 => pub ISENABLERE: [ReadWrite<u32>; 32]),
+        (0x1280 => _reserved9),
+        (0x1400 => pub ICENABLERE: [ReadWrite<u32>; 32]),
+        (0x1480 => _reserved10),
+        (0x1600 => pub ISPENDRE: [ReadWrite<u32>; 32]),
+        (0x1680 => _reserved11),
+        (0x1800 => pub ICPENDRE: [ReadWrite<u32>; 32]),
+        (0x1880 => _reserved12),
+        (0x1a00 => pub ISACTIVERE: [ReadWrite<u32>; 32]),
+        (0x1a80 => _reserved13),
+        (0x1c00 => pub ICACTIVERE: [ReadWrite<u32>; 32]),
+        (0x1c80 => _reserved14),
+        (0x2000 => pub IPRIORITYRE: [ReadWrite<u8>; 1024]),
+        (0x2400 => _reserved15),
+        (0x3000 => pub ICFGRE: [ReadWrite<u32>; 64]),
+        (0x3100 => _reserved16),
+        (0x3400 => pub IGRPMODRE: [ReadWrite<u32>; 32]),
+        (0x3480 => _reserved17),
+        (0x3600 => pub NSACRE: [ReadWrite<u32>; 64]),
+        (0x3700 => _reserved18),
+        (0x3b00 => pub INMIRE: [ReadWrite<u32>; 32]),
+        (0x3b80 => _reserved19),
+        (0x6000 => pub IROUTER: [ReadWrite<u64, GICD_IROUTER::Register>; 1024]),
+        (0x8000 => pub IROUTERE: [ReadWrite<u64, GICD_IROUTER::Register>; 1024]),
+        (0xa000 => _reserved20),
+        (0xffd0 => pub PIDR4: ReadOnly<u32>),
+        (0xffd4 => pub PIDR5: ReadOnly<u32>),
+        (0xffd8 => pub PIDR6: ReadOnly<u32>),
+        (0xffdc => pub PIDR7: ReadOnly<u32>),
+        (0xffe0 => pub PIDR0: ReadOnly<u32>),
+        (0xffe4 => pub PIDR1: ReadOnly<u32>),
+        (0xffe8 => pub PIDR2: ReadOnly<u32, GICD_PIDR2::Register>),
+        (0xffec => pub PIDR3: ReadOnly<u32>),
+        (0xfff0 => pub CIDR0: ReadOnly<u32>),
+        (0xfff4 => pub CIDR1: ReadOnly<u32>),
+        (0xfff8 => pub CIDR2: ReadOnly<u32>),
+        (0xfffc => pub CIDR3: ReadOnly<u32>),
+        (0x10000 => @END),
+    }
+}
@@ -24,7 +24,7 @@ register_bitfields! {u32,
         Reserved31_16 OFFSET(16) NUMBITS(16) [],
         /// [15:0] Status<n>
         /// Status bit for List register <n>.
-        Status OFFSET(15) NUMBITS(16) []
+        Status OFFSET(0) NUMBITS(16) [],
     ]
 }
 
@@ -0,0 +1,33 @@
+//! Virtual interface control frame, GICH
+//!
+//! ## Purpose
+//!
+//! Describes the memory-mapped virtual interface control registers of a GICv2, or of a
+//! GICv3 implementing FEAT_GICv3_LEGACY. The frame is banked per physical CPU.
+
+use tock_registers::register_structs;
+
+use super::{
+    GichAprReg, GichEisrReg, GichElrsrReg, GichHcrReg, GichLrReg, GichMisrReg, GichVmcrReg,
+    GichVtrReg,
+};
+
+register_structs! {
+    /// The GICH frame.
+    #[allow(non_snake_case)]
+    pub GichRegs {
+        (0x0000 => pub HCR: GichHcrReg),
+        (0x0004 => pub VTR: GichVtrReg),
+        (0x0008 => pub VMCR: GichVmcrReg),
+        (0x000c => _reserved0),
+        (0x0010 => pub MISR: GichMisrReg),
+        (0x0014 => _reserved1),
+        (0x0020 => pub EISR: [GichEisrReg; 2]),
+        (0x0028 => _reserved2),
+        (0x0030 => pub ELRSR: [GichElrsrReg; 2]),
+        (0x0038 => _reserved3),
+        (0x00f0 => pub APR: [GichAprReg; 4]),
+        (0x0100 => pub LR: [GichLrReg; 64]),
+        (0x0200 => @END),
+    }
+}
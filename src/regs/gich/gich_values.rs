@@ -0,0 +1,575 @@
+//! Decoded views of GICH register values.
+//!
+//! These plain types convert to and from the raw register values without going through
+//! tock-registers `FieldValue`s. Reserved fields are not represented: they read as zero
+//! after a round trip.
+
+use axerrno::{AxResult, ax_err};
+use tock_registers::LocalRegisterCopy;
+
+use super::{GICH_LR, GICH_MISR, GICH_VMCR, GICH_VTR, ICH_LR_EL2};
+
+/// State of the interrupt held in a List register (`GICH_LR<n>.State`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LrState {
+    /// The List register is free.
+    Inactive,
+    /// The interrupt is pending.
+    Pending,
+    /// The interrupt is active.
+    Active,
+    /// The interrupt is active and pending.
+    ActiveAndPending,
+}
+
+impl LrState {
+    /// Returns whether the interrupt is pending.
+    pub fn is_pending(self) -> bool {
+        matches!(self, Self::Pending | Self::ActiveAndPending)
+    }
+
+    /// Returns whether the interrupt is active.
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Active | Self::ActiveAndPending)
+    }
+}
+
+/// Group of the interrupt held in a List register (`GICH_LR<n>.Group`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LrGroup {
+    /// Group 0, signalled as a virtual FIQ if `GICH_VMCR.VFIQEn` is set.
+    Group0,
+    /// Group 1, signalled as a virtual IRQ.
+    Group1,
+}
+
+/// Decoded `GICH_LR<n>` or `ICH_LR<n>_EL2` value.
+///
+/// The NMI attribute of `ICH_LR<n>_EL2` is not represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListRegister {
+    /// Virtual INTID presented to the guest, below 1024 in the `GICH_LR<n>` layout.
+    pub vintid: u32,
+    /// Physical INTID if `hw` is set. Otherwise bit 9 requests an EOI maintenance interrupt
+    /// and bits [2:0] hold the source CPU of an SGI.
+    pub pintid: u16,
+    /// Priority, with the unimplemented low-order bits as zero. Only bits [7:3] are held.
+    pub priority: u8,
+    /// Interrupt state.
+    pub state: LrState,
+    /// Interrupt group.
+    pub group: LrGroup,
+    /// Whether the virtual interrupt is linked to the physical interrupt `pintid`.
+    pub hw: bool,
+}
+
+impl ListRegister {
+    /// `pINTID` bit requesting an EOI maintenance interrupt for a software interrupt.
+    pub const PINTID_EOI: u16 = 1 << 9;
+    /// `pINTID` bits holding the source CPU of a software SGI.
+    pub const PINTID_CPUID_MASK: u16 = 0x7;
+
+    /// Checks that the value can be held by a `GICH_LR<n>` List register of a host
+    /// described by `vtr`.
+    ///
+    /// # Returns
+    /// - `Ok(())` if every field fits, `priority` only uses implemented bits and a hardware
+    ///   interrupt is linked to a physical INTID below the special range 1020-1023
+    /// - `Err(AxError::InvalidInput)` otherwise
+    pub fn validate(&self, vtr: &VtrInfo) -> AxResult {
+        if self.vintid > 0x3ff || self.pintid > 0x3ff {
+            return ax_err!(InvalidInput, "INTID does not fit in a List register");
+        }
+        if self.hw && self.pintid >= 1020 {
+            return ax_err!(
+                InvalidInput,
+                "hardware interrupt linked to a special physical INTID"
+            );
+        }
+        if self.priority & !vtr.priority_mask() != 0 {
+            return ax_err!(InvalidInput, "priority uses unimplemented bits");
+        }
+        if !self.hw && self.pintid & !(Self::PINTID_EOI | Self::PINTID_CPUID_MASK) != 0 {
+            return ax_err!(
+                InvalidInput,
+                "reserved pINTID bits set for a software interrupt"
+            );
+        }
+        Ok(())
+    }
+}
+
+impl From<LocalRegisterCopy<u32, GICH_LR::Register>> for ListRegister {
+    fn from(lr: LocalRegisterCopy<u32, GICH_LR::Register>) -> Self {
+        let state = match lr.read_as_enum(GICH_LR::State) {
+            Some(GICH_LR::State::Value::Pending) => LrState::Pending,
+            Some(GICH_LR::State::Value::Active) => LrState::Active,
+            Some(GICH_LR::State::Value::ActiveAndPending) => LrState::ActiveAndPending,
+            _ => LrState::Inactive,
+        };
+        let group = match lr.read_as_enum(GICH_LR::Group) {
+            Some(GICH_LR::Group::Value::Group1) => LrGroup::Group1,
+            _ => LrGroup::Group0,
+        };
+        Self {
+            vintid: lr.read(GICH_LR::vINTID),
+            pintid: lr.read(GICH_LR::pINTID) as u16,
+            priority: (lr.read(GICH_LR::Priority) << 3) as u8,
+            state,
+            group,
+            hw: lr.read(GICH_LR::HW) != 0,
+        }
+    }
+}
+
+impl From<ListRegister> for LocalRegisterCopy<u32, GICH_LR::Register> {
+    fn from(lr: ListRegister) -> Self {
+        let state = match lr.state {
+            LrState::Inactive => GICH_LR::State::Inactive,
+            LrState::Pending => GICH_LR::State::Pending,
+            LrState::Active => GICH_LR::State::Active,
+            LrState::ActiveAndPending => GICH_LR::State::ActiveAndPending,
+        };
+        let group = match lr.group {
+            LrGroup::Group0 => GICH_LR::Group::Group0,
+            LrGroup::Group1 => GICH_LR::Group::Group1,
+        };
+        LocalRegisterCopy::new(
+            (GICH_LR::vINTID.val(lr.vintid)
+                + GICH_LR::pINTID.val(lr.pintid as u32)
+                + GICH_LR::Priority.val((lr.priority >> 3) as u32)
+                + GICH_LR::HW.val(lr.hw as u32)
+                + group
+                + state)
+                .value,
+        )
+    }
+}
+
+impl From<u32> for ListRegister {
+    fn from(value: u32) -> Self {
+        LocalRegisterCopy::<u32, GICH_LR::Register>::new(value).into()
+    }
+}
+
+impl From<ListRegister> for u32 {
+    fn from(lr: ListRegister) -> Self {
+        LocalRegisterCopy::<u32, GICH_LR::Register>::from(lr).get()
+    }
+}
+
+impl From<LocalRegisterCopy<u64, ICH_LR_EL2::Register>> for ListRegister {
+    fn from(lr: LocalRegisterCopy<u64, ICH_LR_EL2::Register>) -> Self {
+        let state = match lr.read_as_enum(ICH_LR_EL2::State) {
+            Some(ICH_LR_EL2::State::Value::Pending) => LrState::Pending,
+            Some(ICH_LR_EL2::State::Value::Active) => LrState::Active,
+            Some(ICH_LR_EL2::State::Value::ActiveAndPending) => LrState::ActiveAndPending,
+            _ => LrState::Inactive,
+        };
+        let group = match lr.read_as_enum(ICH_LR_EL2::Group) {
+            Some(ICH_LR_EL2::Group::Value::Group1) => LrGroup::Group1,
+            _ => LrGroup::Group0,
+        };
+        Self {
+            vintid: lr.read(ICH_LR_EL2::vINTID) as u32,
+            pintid: lr.read(ICH_LR_EL2::pINTID) as u16,
+            priority: lr.read(ICH_LR_EL2::Priority) as u8,
+            state,
+            group,
+            hw: lr.read(ICH_LR_EL2::HW) != 0,
+        }
+    }
+}
+
+impl From<ListRegister> for LocalRegisterCopy<u64, ICH_LR_EL2::Register> {
+    fn from(lr: ListRegister) -> Self {
+        let state = match lr.state {
+            LrState::Inactive => ICH_LR_EL2::State::Inactive,
+            LrState::Pending => ICH_LR_EL2::State::Pending,
+            LrState::Active => ICH_LR_EL2::State::Active,
+            LrState::ActiveAndPending => ICH_LR_EL2::State::ActiveAndPending,
+        };
+        let group = match lr.group {
+            LrGroup::Group0 => ICH_LR_EL2::Group::Group0,
+            LrGroup::Group1 => ICH_LR_EL2::Group::Group1,
+        };
+        LocalRegisterCopy::new(
+            (ICH_LR_EL2::vINTID.val(lr.vintid as u64)
+                + ICH_LR_EL2::pINTID.val(lr.pintid as u64)
+                + ICH_LR_EL2::Priority.val(lr.priority as u64)
+                + ICH_LR_EL2::HW.val(lr.hw as u64)
+                + group
+                + state)
+                .value,
+        )
+    }
+}
+
+impl From<u64> for ListRegister {
+    fn from(value: u64) -> Self {
+        LocalRegisterCopy::<u64, ICH_LR_EL2::Register>::new(value).into()
+    }
+}
+
+impl From<ListRegister> for u64 {
+    fn from(lr: ListRegister) -> Self {
+        LocalRegisterCopy::<u64, ICH_LR_EL2::Register>::from(lr).get()
+    }
+}
+
+/// Decoded `GICH_VMCR` value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VmcrValue {
+    /// Virtual Group 0 interrupts enabled.
+    pub veng0: bool,
+    /// Virtual Group 1 interrupts enabled.
+    pub veng1: bool,
+    /// Acknowledge of a Group 1 interrupt through `GICV_IAR` returns its INTID.
+    pub vack_ctl: bool,
+    /// Virtual Group 0 interrupts are signalled as FIQs.
+    pub vfiq_en: bool,
+    /// `GICV_BPR` controls the preemption of Group 1 interrupts too.
+    pub vcbpr: bool,
+    /// EOI mode: priority drop and deactivation are separate.
+    pub veoim: bool,
+    /// Group 0 binary point.
+    pub vbpr0: u8,
+    /// Group 1 binary point.
+    pub vbpr1: u8,
+    /// Virtual priority mask.
+    pub vpmr: u8,
+}
+
+impl From<u32> for VmcrValue {
+    fn from(value: u32) -> Self {
+        let vmcr = LocalRegisterCopy::<u32, GICH_VMCR::Register>::new(value);
+        Self {
+            veng0: vmcr.read(GICH_VMCR::VENG0) != 0,
+            veng1: vmcr.read(GICH_VMCR::VENG1) != 0,
+            vack_ctl: vmcr.read(GICH_VMCR::VAckCtl) != 0,
+            vfiq_en: vmcr.read(GICH_VMCR::VFIQEn) != 0,
+            vcbpr: vmcr.read(GICH_VMCR::VCBPR) != 0,
+            veoim: vmcr.read(GICH_VMCR::VEOIM) != 0,
+            vbpr0: vmcr.read(GICH_VMCR::VBPR0) as u8,
+            vbpr1: vmcr.read(GICH_VMCR::VBPR1) as u8,
+            vpmr: vmcr.read(GICH_VMCR::VPMR) as u8,
+        }
+    }
+}
+
+impl From<VmcrValue> for u32 {
+    fn from(vmcr: VmcrValue) -> Self {
+        (GICH_VMCR::VENG0.val(vmcr.veng0 as u32)
+            + GICH_VMCR::VENG1.val(vmcr.veng1 as u32)
+            + GICH_VMCR::VAckCtl.val(vmcr.vack_ctl as u32)
+            + GICH_VMCR::VFIQEn.val(vmcr.vfiq_en as u32)
+            + GICH_VMCR::VCBPR.val(vmcr.vcbpr as u32)
+            + GICH_VMCR::VEOIM.val(vmcr.veoim as u32)
+            + GICH_VMCR::VBPR0.val(vmcr.vbpr0 as u32)
+            + GICH_VMCR::VBPR1.val(vmcr.vbpr1 as u32)
+            + GICH_VMCR::VPMR.val(vmcr.vpmr as u32))
+        .value
+    }
+}
+
+/// Decoded `GICH_MISR` value: the maintenance interrupt conditions that are asserted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MisrValue {
+    /// End Of Interrupt of a List register requesting an EOI maintenance interrupt.
+    pub eoi: bool,
+    /// Underflow: at most one List register holds a valid interrupt.
+    pub underflow: bool,
+    /// List Register Entry Not Present: `GICH_HCR.EOICount` is non-zero.
+    pub lr_entry_not_present: bool,
+    /// No Pending: no List register holds a pending interrupt.
+    pub no_pending: bool,
+    /// Virtual Group 0 interrupts are enabled.
+    pub vgrp0_enabled: bool,
+    /// Virtual Group 0 interrupts are disabled.
+    pub vgrp0_disabled: bool,
+    /// Virtual Group 1 interrupts are enabled.
+    pub vgrp1_enabled: bool,
+    /// Virtual Group 1 interrupts are disabled.
+    pub vgrp1_disabled: bool,
+}
+
+impl From<u32> for MisrValue {
+    fn from(value: u32) -> Self {
+        let misr = LocalRegisterCopy::<u32, GICH_MISR::Register>::new(value);
+        Self {
+            eoi: misr.read(GICH_MISR::EOI) != 0,
+            underflow: misr.read(GICH_MISR::U) != 0,
+            lr_entry_not_present: misr.read(GICH_MISR::LRENP) != 0,
+            no_pending: misr.read(GICH_MISR::NP) != 0,
+            vgrp0_enabled: misr.read(GICH_MISR::VGrp0E) != 0,
+            vgrp0_disabled: misr.read(GICH_MISR::VGrp0D) != 0,
+            vgrp1_enabled: misr.read(GICH_MISR::VGrp1E) != 0,
+            vgrp1_disabled: misr.read(GICH_MISR::VGrp1D) != 0,
+        }
+    }
+}
+
+impl From<MisrValue> for u32 {
+    fn from(misr: MisrValue) -> Self {
+        (GICH_MISR::EOI.val(misr.eoi as u32)
+            + GICH_MISR::U.val(misr.underflow as u32)
+            + GICH_MISR::LRENP.val(misr.lr_entry_not_present as u32)
+            + GICH_MISR::NP.val(misr.no_pending as u32)
+            + GICH_MISR::VGrp0E.val(misr.vgrp0_enabled as u32)
+            + GICH_MISR::VGrp0D.val(misr.vgrp0_disabled as u32)
+            + GICH_MISR::VGrp1E.val(misr.vgrp1_enabled as u32)
+            + GICH_MISR::VGrp1D.val(misr.vgrp1_disabled as u32))
+        .value
+    }
+}
+
+/// Decoded `GICH_VTR` value: the features of the host virtual CPU interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtrInfo {
+    /// Number of implemented List registers.
+    pub list_regs: usize,
+    /// Number of virtual preemption bits.
+    pub pre_bits: u8,
+    /// Number of virtual priority bits.
+    pub pri_bits: u8,
+    /// Raw `IDbits` field: 0 for 16 physical INTID bits, 1 for 24.
+    pub id_bits: u8,
+    /// The CPU interface supports local generation of SEIs.
+    pub seis: bool,
+    /// The virtual CPU interface supports non-zero Aff3 values.
+    pub a3v: bool,
+}
+
+impl VtrInfo {
+    /// Returns the mask of the priority bits implemented by the List registers.
+    ///
+    /// `GICH_LR<n>.Priority` holds at most 5 bits, whatever `pri_bits` reports.
+    pub fn priority_mask(&self) -> u8 {
+        let bits = self.pri_bits.clamp(1, 5);
+        !(0xffu8 >> bits)
+    }
+
+    /// Returns the number of implemented active priority registers of each group
+    /// (`ICH_AP0R<n>_EL2` and `ICH_AP1R<n>_EL2`, or `GICH_APR<n>`): 1 with 5 preemption
+    /// bits, 2 with 6 and 4 with 7.
+    pub fn apr_count(&self) -> usize {
+        1 << (self.pre_bits.clamp(5, 7) - 5)
+    }
+}
+
+impl From<u32> for VtrInfo {
+    fn from(value: u32) -> Self {
+        let vtr = LocalRegisterCopy::<u32, GICH_VTR::Register>::new(value);
+        Self {
+            list_regs: vtr.read(GICH_VTR::ListRegs) as usize + 1,
+            pre_bits: vtr.read(GICH_VTR::PREbits) as u8 + 1,
+            pri_bits: vtr.read(GICH_VTR::PRIbits) as u8 + 1,
+            id_bits: vtr.read(GICH_VTR::IDbits) as u8,
+            seis: vtr.read(GICH_VTR::SEIS) != 0,
+            a3v: vtr.read(GICH_VTR::A3V) != 0,
+        }
+    }
+}
+
+impl From<VtrInfo> for u32 {
+    fn from(vtr: VtrInfo) -> Self {
+        (GICH_VTR::ListRegs.val(vtr.list_regs.saturating_sub(1) as u32)
+            + GICH_VTR::PREbits.val(vtr.pre_bits.saturating_sub(1) as u32)
+            + GICH_VTR::PRIbits.val(vtr.pri_bits.saturating_sub(1) as u32)
+            + GICH_VTR::IDbits.val(vtr.id_bits as u32)
+            + GICH_VTR::SEIS.val(vtr.seis as u32)
+            + GICH_VTR::A3V.val(vtr.a3v as u32))
+        .value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vtr() -> VtrInfo {
+        VtrInfo {
+            list_regs: 4,
+            pre_bits: 5,
+            pri_bits: 5,
+            id_bits: 0,
+            seis: false,
+            a3v: false,
+        }
+    }
+
+    fn software_lr() -> ListRegister {
+        ListRegister {
+            vintid: 42,
+            pintid: ListRegister::PINTID_EOI | 3,
+            priority: 0xa8,
+            state: LrState::ActiveAndPending,
+            group: LrGroup::Group1,
+            hw: false,
+        }
+    }
+
+    #[test]
+    fn list_register_round_trip() {
+        let hardware = ListRegister {
+            vintid: 0x3ff,
+            pintid: 1019,
+            priority: 0xf8,
+            state: LrState::Active,
+            group: LrGroup::Group0,
+            hw: true,
+        };
+        for lr in [software_lr(), hardware] {
+            assert_eq!(ListRegister::from(u32::from(lr)), lr);
+        }
+        for state in [
+            LrState::Inactive,
+            LrState::Pending,
+            LrState::Active,
+            LrState::ActiveAndPending,
+        ] {
+            let lr = ListRegister {
+                state,
+                ..software_lr()
+            };
+            assert_eq!(ListRegister::from(u32::from(lr)), lr);
+        }
+        // Reserved bits [22:20] are dropped.
+        let raw = u32::from(software_lr());
+        assert_eq!(u32::from(ListRegister::from(raw | 0x0070_0000)), raw);
+    }
+
+    #[test]
+    fn ich_list_register_round_trip() {
+        let extended = ListRegister {
+            vintid: 4096 + 31,
+            pintid: ListRegister::PINTID_EOI,
+            priority: 0x47,
+            ..software_lr()
+        };
+        let raw = u64::from(extended);
+        assert_eq!(raw, 0xd047_0200_0000_101f);
+        assert_eq!(ListRegister::from(raw), extended);
+        let hardware = ListRegister {
+            pintid: 0x1fff,
+            hw: true,
+            ..extended
+        };
+        assert_eq!(ListRegister::from(u64::from(hardware)), hardware);
+        // The NMI attribute and reserved bits are dropped.
+        let nmi = 1 << 59;
+        assert_eq!(
+            u64::from(ListRegister::from(raw | nmi | 0x0700_e000 << 32)),
+            raw
+        );
+    }
+
+    #[test]
+    fn vmcr_round_trip() {
+        let vmcr = VmcrValue {
+            veng0: true,
+            veng1: false,
+            vack_ctl: true,
+            vfiq_en: false,
+            vcbpr: true,
+            veoim: true,
+            vbpr0: 2,
+            vbpr1: 7,
+            vpmr: 0x1f,
+        };
+        assert_eq!(VmcrValue::from(u32::from(vmcr)), vmcr);
+        let inverted = VmcrValue {
+            veng0: false,
+            veng1: true,
+            vack_ctl: false,
+            vfiq_en: true,
+            vcbpr: false,
+            veoim: false,
+            vbpr0: 7,
+            vbpr1: 0,
+            vpmr: 0,
+        };
+        assert_eq!(VmcrValue::from(u32::from(inverted)), inverted);
+    }
+
+    #[test]
+    fn misr_round_trip() {
+        for bits in 0..0x100u32 {
+            let misr = MisrValue {
+                eoi: bits & 1 != 0,
+                underflow: bits & 2 != 0,
+                lr_entry_not_present: bits & 4 != 0,
+                no_pending: bits & 8 != 0,
+                vgrp0_enabled: bits & 0x10 != 0,
+                vgrp0_disabled: bits & 0x20 != 0,
+                vgrp1_enabled: bits & 0x40 != 0,
+                vgrp1_disabled: bits & 0x80 != 0,
+            };
+            assert_eq!(MisrValue::from(u32::from(misr)), misr);
+        }
+    }
+
+    #[test]
+    fn vtr_round_trip() {
+        let vtr = VtrInfo {
+            list_regs: 16,
+            pre_bits: 7,
+            pri_bits: 8,
+            id_bits: 1,
+            seis: true,
+            a3v: true,
+        };
+        assert_eq!(VtrInfo::from(u32::from(vtr)), vtr);
+        assert_eq!(VtrInfo::from(u32::from(self::vtr())), self::vtr());
+        assert_eq!(vtr.apr_count(), 4);
+        assert_eq!(self::vtr().apr_count(), 1);
+    }
+
+    #[test]
+    fn validate_accepts_representable_values() {
+        assert_eq!(software_lr().validate(&vtr()), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_unimplemented_priority_bits() {
+        let lr = ListRegister {
+            priority: 0xa4,
+            ..software_lr()
+        };
+        assert!(lr.validate(&vtr()).is_err());
+
+        let narrow = VtrInfo {
+            pri_bits: 4,
+            ..vtr()
+        };
+        assert!(software_lr().validate(&narrow).is_err());
+        let lr = ListRegister {
+            priority: 0xf0,
+            ..software_lr()
+        };
+        assert_eq!(lr.validate(&narrow), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_special_physical_intids() {
+        let lr = ListRegister {
+            pintid: 1019,
+            hw: true,
+            ..software_lr()
+        };
+        assert_eq!(lr.validate(&vtr()), Ok(()));
+        for pintid in 1020..=1023 {
+            let lr = ListRegister { pintid, ..lr };
+            assert!(lr.validate(&vtr()).is_err());
+        }
+    }
+
+    #[test]
+    fn validate_rejects_reserved_software_pintid_bits() {
+        let lr = ListRegister {
+            pintid: 0x10,
+            ..software_lr()
+        };
+        assert!(lr.validate(&vtr()).is_err());
+    }
+}
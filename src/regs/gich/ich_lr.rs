@@ -0,0 +1,61 @@
+//! Interrupt Controller List Registers, `ICH_LR<n>_EL2`, n = 0 - 15
+//! The `ICH_LR<n>_EL2` characteristics are:
+//!
+//! ## Purpose
+//!
+//! The system register view of the List registers, providing the same context information
+//! as `GICH_LR<n>` with a 32-bit virtual INTID, a 13-bit physical INTID and the NMI
+//! attribute.
+//!
+//! ## Configuration
+//!
+//! This register is present only when EL2 is implemented. Unimplemented List registers
+//! are UNDEFINED.
+//!
+//! ## Attributes
+//!
+//! `ICH_LR<n>_EL2` is a 64-bit register.
+
+use tock_registers::register_bitfields;
+
+register_bitfields! {u64,
+    pub ICH_LR_EL2 [
+        /// [63:62] State
+        /// The state of the interrupt.
+        State OFFSET(62) NUMBITS(2) [
+            Inactive = 0b00,
+            Pending = 0b01,
+            Active = 0b10,
+            ActiveAndPending = 0b11
+        ],
+        /// [61] HW
+        /// Indicates whether this virtual interrupt is a hardware interrupt.
+        HW OFFSET(61) NUMBITS(1) [
+            Software = 0,
+            Hardware = 1
+        ],
+        /// [60] Group
+        /// Indicates whether the interrupt is Group 0 or Group 1.
+        Group OFFSET(60) NUMBITS(1) [
+            Group0 = 0,
+            Group1 = 1
+        ],
+        /// [59] NMI
+        /// Indicates whether the virtual interrupt has superpriority (FEAT_GICv3_NMI).
+        NMI OFFSET(59) NUMBITS(1) [],
+        /// [58:56] Reserved, RES0.
+        Reserved58_56 OFFSET(56) NUMBITS(3) [],
+        /// [55:48] Priority
+        /// The priority of this interrupt.
+        Priority OFFSET(48) NUMBITS(8) [],
+        /// [47:45] Reserved, RES0.
+        Reserved47_45 OFFSET(45) NUMBITS(3) [],
+        /// [44:32] pINTID
+        /// The physical interrupt ID.
+        /// The function of this field depends on the value of ICH_LR<n>_EL2.HW.
+        pINTID OFFSET(32) NUMBITS(13) [],
+        /// [31:0] vINTID
+        /// The virtual interrupt ID.
+        vINTID OFFSET(0) NUMBITS(32) []
+    ]
+}
@@ -0,0 +1,366 @@
+//! Redistributor register map, GICR
+//!
+//! ## Purpose
+//!
+//! Describes the 64KB frames of a GICv3/GICv4 redistributor:
+//!
+//! - `RD_base`: control, identification and physical LPI registers.
+//! - `SGI_base`: the SGI, PPI and Extended PPI banks.
+//! - `VLPI_base`: the GICv4 virtual LPI registers.
+//!
+//! ## Usage
+//!
+//! A redistributor occupies two frames (`RD_base` and `SGI_base`) in GICv3 and
+//! four in GICv4 (`VLPI_base` and a reserved frame follow). The emulator derives
+//! its decode offsets from these blocks, see `consts.rs`.
+
+use tock_registers::register_bitfields;
+use tock_registers::register_structs;
+use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
+
+use super::{GICD_IIDR, GICD_PIDR2, GICD_STATUSR};
+
+register_bitfields! {u32,
+    pub GICR_CTLR [
+        /// [31] UWP
+        /// Upstream Write Pending.
+        UWP OFFSET(31) NUMBITS(1) [],
+        /// [26] DPG1S
+        /// Disable Processor selection for Secure Group 1 interrupts.
+        DPG1S OFFSET(26) NUMBITS(1) [],
+        /// [25] DPG1NS
+        /// Disable Processor selection for Non-secure Group 1 interrupts.
+        DPG1NS OFFSET(25) NUMBITS(1) [],
+        /// [24] DPG0
+        /// Disable Processor selection for Group 0 interrupts.
+        DPG0 OFFSET(24) NUMBITS(1) [],
+        /// [3] RWP
+        /// Register Write Pending.
+        RWP OFFSET(3) NUMBITS(1) [],
+        /// [2] IR
+        /// LPI invalidate registers supported (GICv4.1).
+        IR OFFSET(2) NUMBITS(1) [],
+        /// [1] CES
+        /// Clear Enable Supported: `EnableLPIs` can be cleared once set.
+        CES OFFSET(1) NUMBITS(1) [],
+        /// [0] EnableLPIs
+        EnableLPIs OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GICR_WAKER [
+        /// [2] ChildrenAsleep
+        /// The interface to the connected PE is quiescent.
+        ChildrenAsleep OFFSET(2) NUMBITS(1) [],
+        /// [1] ProcessorSleep
+        /// The connected PE is asleep and must not be signalled interrupts.
+        ProcessorSleep OFFSET(1) NUMBITS(1) []
+    ],
+
+    pub GICR_MPAMIDR [
+        /// [23:16] PMGMax
+        PMGMax OFFSET(16) NUMBITS(8) [],
+        /// [15:0] PARTIDMax
+        PARTIDMax OFFSET(0) NUMBITS(16) []
+    ],
+
+    pub GICR_PARTIDR [
+        /// [23:16] PMG
+        PMG OFFSET(16) NUMBITS(8) [],
+        /// [15:0] PARTID
+        PARTID OFFSET(0) NUMBITS(16) []
+    ],
+
+    pub GICR_SYNCR [
+        /// [0] Busy
+        /// A write to `GICR_INVLPIR`, `GICR_INVALLR` or `GICR_CLRLPIR` is in progress.
+        Busy OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GICR_VSGIR [
+        /// [15:0] vPEID
+        /// vPE whose vSGI pending state is queried through `GICR_VSGIPENDR`.
+        vPEID OFFSET(0) NUMBITS(16) []
+    ],
+
+    pub GICR_VSGIPENDR [
+        /// [31] Busy
+        /// The query started by the last `GICR_VSGIR` write is in progress.
+        Busy OFFSET(31) NUMBITS(1) [],
+        /// [15:0] Pending
+        /// Pending state of vSGIs 0-15 of the queried vPE.
+        Pending OFFSET(0) NUMBITS(16) []
+    ]
+}
+
+register_bitfields! {u64,
+    pub GICR_TYPER [
+        /// [63:32] Affinity_Value
+        /// Aff3.Aff2.Aff1.Aff0 of the connected PE.
+        Affinity_Value OFFSET(32) NUMBITS(32) [],
+        /// [31:27] PPInum
+        /// Maximum Extended PPI INTID: 0 none, 1 up to 1087, 2 up to 1119.
+        PPInum OFFSET(27) NUMBITS(5) [],
+        /// [26] VSGI
+        /// vSGIs can be queried through `GICR_VSGIR` (GICv4.1).
+        VSGI OFFSET(26) NUMBITS(1) [],
+        /// [25:24] CommonLPIAff
+        /// Affinity level at which redistributors share an LPI configuration table.
+        CommonLPIAff OFFSET(24) NUMBITS(2) [],
+        /// [23:8] Processor_Number
+        /// Identifies the redistributor to an ITS when `GITS_TYPER.PTA == 0`.
+        Processor_Number OFFSET(8) NUMBITS(16) [],
+        /// [7] RVPEID
+        /// `GICR_VPENDBASER` records a vPE ID (GICv4.1).
+        RVPEID OFFSET(7) NUMBITS(1) [],
+        /// [6] MPAM
+        MPAM OFFSET(6) NUMBITS(1) [],
+        /// [5] DPGS
+        /// `GICR_CTLR.DPG*` are supported.
+        DPGS OFFSET(5) NUMBITS(1) [],
+        /// [4] Last
+        /// Last redistributor of a contiguous region.
+        Last OFFSET(4) NUMBITS(1) [],
+        /// [3] DirectLPI
+        /// Direct LPI injection through `GICR_SETLPIR` and friends is supported.
+        DirectLPI OFFSET(3) NUMBITS(1) [],
+        /// [2] Dirty
+        /// `GICR_VPENDBASER.Dirty` is supported.
+        Dirty OFFSET(2) NUMBITS(1) [],
+        /// [1] VLPIS
+        /// Virtual LPIs and direct injection of virtual LPIs are supported.
+        VLPIS OFFSET(1) NUMBITS(1) [],
+        /// [0] PLPIS
+        /// Physical LPIs are supported.
+        PLPIS OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GICR_SETLPIR [
+        /// [31:0] pINTID
+        /// The physical LPI to set (or clear through `GICR_CLRLPIR`) pending.
+        pINTID OFFSET(0) NUMBITS(32) []
+    ],
+
+    pub GICR_INVLPIR [
+        /// [63] V
+        /// `vPEID` is valid and `INTID` is a virtual LPI (GICv4.1).
+        V OFFSET(63) NUMBITS(1) [],
+        /// [47:32] vPEID
+        vPEID OFFSET(32) NUMBITS(16) [],
+        /// [31:0] INTID
+        INTID OFFSET(0) NUMBITS(32) []
+    ],
+
+    pub GICR_INVALLR [
+        /// [63] V
+        /// `vPEID` is valid (GICv4.1).
+        V OFFSET(63) NUMBITS(1) [],
+        /// [47:32] vPEID
+        vPEID OFFSET(32) NUMBITS(16) []
+    ],
+
+    pub GICR_PROPBASER [
+        /// [58:56] OuterCache
+        OuterCache OFFSET(56) NUMBITS(3) [
+            SameAsInner = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [51:12] Physical_Address
+        /// Bits [51:12] of the LPI configuration table address.
+        Physical_Address OFFSET(12) NUMBITS(40) [],
+        /// [11:10] Shareability
+        Shareability OFFSET(10) NUMBITS(2) [
+            NonShareable = 0,
+            InnerShareable = 1,
+            OuterShareable = 2
+        ],
+        /// [9:7] InnerCache
+        InnerCache OFFSET(7) NUMBITS(3) [
+            DeviceNGnRnE = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [4:0] IDbits
+        /// Number of LPI INTID bits supported, minus one.
+        IDbits OFFSET(0) NUMBITS(5) []
+    ],
+
+    pub GICR_PENDBASER [
+        /// [62] PTZ
+        /// The LPI pending table is known to be zeroed.
+        PTZ OFFSET(62) NUMBITS(1) [],
+        /// [58:56] OuterCache
+        OuterCache OFFSET(56) NUMBITS(3) [
+            SameAsInner = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [51:16] Physical_Address
+        /// Bits [51:16] of the LPI pending table address.
+        Physical_Address OFFSET(16) NUMBITS(36) [],
+        /// [11:10] Shareability
+        Shareability OFFSET(10) NUMBITS(2) [
+            NonShareable = 0,
+            InnerShareable = 1,
+            OuterShareable = 2
+        ],
+        /// [9:7] InnerCache
+        InnerCache OFFSET(7) NUMBITS(3) [
+            DeviceNGnRnE = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ]
+    ],
+
+    /// GICv4.0 layout; GICv4.1 replaces it with a vPE configuration table descriptor.
+    pub GICR_VPROPBASER [
+        /// [58:56] OuterCache
+        OuterCache OFFSET(56) NUMBITS(3) [],
+        /// [51:12] Physical_Address
+        /// Bits [51:12] of the virtual LPI configuration table address.
+        Physical_Address OFFSET(12) NUMBITS(40) [],
+        /// [11:10] Shareability
+        Shareability OFFSET(10) NUMBITS(2) [],
+        /// [9:7] InnerCache
+        InnerCache OFFSET(7) NUMBITS(3) [],
+        /// [4:0] IDbits
+        /// Number of virtual LPI INTID bits supported, minus one.
+        IDbits OFFSET(0) NUMBITS(5) []
+    ],
+
+    /// GICv4.0 layout.
+    pub GICR_VPENDBASER [
+        /// [63] Valid
+        /// The vPE described by this register is scheduled on the redistributor.
+        Valid OFFSET(63) NUMBITS(1) [],
+        /// [62] IDAI
+        /// Implementation Defined Area Invalid.
+        IDAI OFFSET(62) NUMBITS(1) [],
+        /// [61] PendingLast
+        /// A virtual interrupt was pending when the vPE was descheduled.
+        PendingLast OFFSET(61) NUMBITS(1) [],
+        /// [60] Dirty
+        /// The redistributor is still processing the last `Valid` change.
+        Dirty OFFSET(60) NUMBITS(1) [],
+        /// [58:56] OuterCache
+        OuterCache OFFSET(56) NUMBITS(3) [],
+        /// [51:16] Physical_Address
+        /// Bits [51:16] of the virtual LPI pending table address.
+        Physical_Address OFFSET(16) NUMBITS(36) [],
+        /// [11:10] Shareability
+        Shareability OFFSET(10) NUMBITS(2) [],
+        /// [9:7] InnerCache
+        InnerCache OFFSET(7) NUMBITS(3) []
+    ]
+}
+
+register_structs! {
+    /// The `RD_base` frame of a redistributor.
+    #[allow(non_snake_case)]
+    pub GicrRdRegs {
+        (0x0000 => pub CTLR: ReadWrite<u32, GICR_CTLR::Register>),
+        (0x0004 => pub IIDR: ReadOnly<u32, GICD_IIDR::Register>),
+        (0x0008 => pub TYPER: ReadOnly<u64, GICR_TYPER::Register>),
+        (0x0010 => pub STATUSR: ReadWrite<u32, GICD_STATUSR::Register>),
+        (0x0014 => pub WAKER: ReadWrite<u32, GICR_WAKER::Register>),
+        (0x0018 => pub MPAMIDR: ReadOnly<u32, GICR_MPAMIDR::Register>),
+        (0x001c => pub PARTIDR: ReadWrite<u32, GICR_PARTIDR::Register>),
+        (0x0020 => _reserved0),
+        (0x0040 => pub SETLPIR: WriteOnly<u64, GICR_SETLPIR::Register>),
+        (0x0048 => pub CLRLPIR: WriteOnly<u64, GICR_SETLPIR::Register>),
+        (0x0050 => _reserved1),
+        (0x0070 => pub PROPBASER: ReadWrite<u64, GICR_PROPBASER::Register>),
+        (0x0078 => pub PENDBASER: ReadWrite<u64, GICR_PENDBASER::Register>),
+        (0x0080 => _reserved2),
+        (0x00a0 => pub INVLPIR: WriteOnly<u64, GICR_INVLPIR::Register>),
+        (0x00a8 => _reserved3),
+        (0x00b0 => pub INVALLR: WriteOnly<u64, GICR_INVALLR::Register>),
+        (0x00b8 => _reserved4),
+        (0x00c0 => pub SYNCR: ReadOnly<u32, GICR_SYNCR::Register>),
+        (0x00c4 => _reserved5),
+        (0xffd0 => pub PIDR4: ReadOnly<u32>),
+        (0xffd4 => pub PIDR5: ReadOnly<u32>),
+        (0xffd8 => pub PIDR6: ReadOnly<u32>),
+        (0xffdc => pub PIDR7: ReadOnly<u32>),
+        (0xffe0 => pub PIDR0: ReadOnly<u32>),
+        (0xffe4 => pub PIDR1: ReadOnly<u32>),
+        (0xffe8 => pub PIDR2: ReadOnly<u32, GICD_PIDR2::Register>),
+        (0xffec => pub PIDR3: ReadOnly<u32>),
+        (0xfff0 => pub CIDR0: ReadOnly<u32>),
+        (0xfff4 => pub CIDR1: ReadOnly<u32>),
+        (0xfff8 => pub CIDR2: ReadOnly<u32>),
+        (0xfffc => pub CIDR3: ReadOnly<u32>),
+        (0x10000 => @END),
+    }
+}
+
+register_structs! {
+    /// The `SGI_base` frame of a redistributor.
+    ///
+    /// Entry 0 of each bitmap bank (1 and 0 for `ICFGR`) covers the SGIs and PPIs,
+    /// the following entries the Extended PPIs (`GICR_ISENABLER<n>E`, ...).
+    #[allow(non_snake_case)]
+    pub GicrSgiRegs {
+        (0x0000 => _reserved0),
+        (0x0080 => pub IGROUPR: [ReadWrite<u32>; 3]),
+        (0x008c => _reserved1),
+        (0x0100 => pub ISENABLER: [ReadWrite<u32>; 3]),
+        (0x010c => _reserved2),
+        (0x0180 => pub ICENABLER: [ReadWrite<u32>; 3]),
+        (0x018c => _reserved3),
+        (0x0200 => pub ISPENDR: [ReadWrite<u32>; 3]),
+        (0x020c => _reserved4),
+        (0x0280 => pub ICPENDR: [ReadWrite<u32>; 3]),
+        (0x028c => _reserved5),
+        (0x0300 => pub ISACTIVER: [ReadWrite<u32>; 3]),
+        (0x030c => _reserved6),
+        (0x0380 => pub ICACTIVER: [ReadWrite<u32>; 3]),
+        (0x038c => _reserved7),
+        (0x0400 => pub IPRIORITYR: [ReadWrite<u8>; 96]),
+        (0x0460 => _reserved8),
+        (0x0c00 => pub ICFGR: [ReadWrite<u32>; 6]),
+        (0x0c18 => _reserved9),
+        (0x0d00 => pub IGRPMODR: [ReadWrite<u32>; 3]),
+        (0x0d0c => _reserved10),
+        (0x0e00 => pub NSACR: ReadWrite<u32>),
+        (0x0e04 => _reserved11),
+        (0x0f80 => pub INMIR: [ReadWrite<u32>; 3]),
+        (0x0f8c => _reserved12),
+        (0x10000 => @END),
+    }
+}
+
+register_structs! {
+    /// The GICv4 `VLPI_base` frame of a redistributor.
+    #[allow(non_snake_case)]
+    pub GicrVlpiRegs {
+        (0x0000 => _reserved0),
+        (0x0070 => pub VPROPBASER: ReadWrite<u64, GICR_VPROPBASER::Register>),
+        (0x0078 => pub VPENDBASER: ReadWrite<u64, GICR_VPENDBASER::Register>),
+        (0x0080 => pub VSGIR: WriteOnly<u32, GICR_VSGIR::Register>),
+        (0x0084 => _reserved1),
+        (0x0088 => pub VSGIPENDR: ReadOnly<u32, GICR_VSGIPENDR::Register>),
+        (0x008c => _reserved2),
+        (0x10000 => @END),
+    }
+}
@@ -0,0 +1,287 @@
+//! Interrupt Translation Service register map, GITS
+//!
+//! ## Purpose
+//!
+//! Describes the two 64KB frames of a GICv3/GICv4 ITS: the control frame
+//! (`ITS_base`) and the translation frame (`ITS_base + 0x10000`) holding
+//! `GITS_TRANSLATER`.
+//!
+//! ## Usage
+//!
+//! Commands written to the queue described by `GITS_CBASER` are encoded and decoded
+//! by [`ItsCommand`](super::ItsCommand).
+
+use tock_registers::register_bitfields;
+use tock_registers::register_structs;
+use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
+
+use super::{GICD_IIDR, GICD_PIDR2, GICD_STATUSR, GICR_MPAMIDR, GICR_PARTIDR};
+
+register_bitfields! {u32,
+    pub GITS_CTLR [
+        /// [31] Quiescent
+        /// The ITS is disabled and has completed all operations.
+        Quiescent OFFSET(31) NUMBITS(1) [],
+        /// [8] UMSIirq
+        /// Generate an interrupt when `GITS_UMSIR` records an unmapped MSI.
+        UMSIirq OFFSET(8) NUMBITS(1) [],
+        /// [7:4] ITS_Number
+        /// ITS number used by `VMOVP` when `GITS_TYPER.VMOVP == 0`.
+        ITS_Number OFFSET(4) NUMBITS(4) [],
+        /// [1] ImDe
+        /// Implementation Defined event reporting is enabled.
+        ImDe OFFSET(1) NUMBITS(1) [],
+        /// [0] Enabled
+        Enabled OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GITS_MPIDR [
+        /// [31:24] Aff3
+        Aff3 OFFSET(24) NUMBITS(8) [],
+        /// [23:16] Aff2
+        Aff2 OFFSET(16) NUMBITS(8) [],
+        /// [15:8] Aff1
+        Aff1 OFFSET(8) NUMBITS(8) []
+    ],
+
+    pub GITS_TRANSLATER [
+        /// [31:0] EventID
+        /// The EventID of the MSI; the DeviceID is provided by the bus.
+        EventID OFFSET(0) NUMBITS(32) []
+    ]
+}
+
+register_bitfields! {u64,
+    pub GITS_TYPER [
+        /// [46] INV
+        /// `INV` and `INVALL` are not required after a `DISCARD` (GICv4.1).
+        INV OFFSET(46) NUMBITS(1) [],
+        /// [45] UMSIirq
+        UMSIirq OFFSET(45) NUMBITS(1) [],
+        /// [44] UMSI
+        /// Unmapped MSIs are reported through `GITS_UMSIR`.
+        UMSI OFFSET(44) NUMBITS(1) [],
+        /// [43] nID
+        /// Individual interrupt deactivation is not supported by `INV` and friends.
+        nID OFFSET(43) NUMBITS(1) [],
+        /// [42:41] SVPET
+        /// Level at which the vPE configuration table is shared.
+        SVPET OFFSET(41) NUMBITS(2) [],
+        /// [40] VMAPP
+        /// The GICv4.1 `VMAPP` format is used.
+        VMAPP OFFSET(40) NUMBITS(1) [],
+        /// [39] VSGI
+        /// The `VSGI` command is supported.
+        VSGI OFFSET(39) NUMBITS(1) [],
+        /// [38] MPAM
+        MPAM OFFSET(38) NUMBITS(1) [],
+        /// [37] VMOVP
+        /// `VMOVP` needs to be issued to a single ITS only.
+        VMOVP OFFSET(37) NUMBITS(1) [],
+        /// [36] CIL
+        /// `CIDbits` is valid.
+        CIL OFFSET(36) NUMBITS(1) [],
+        /// [35:32] CIDbits
+        /// Number of Collection ID bits supported, minus one.
+        CIDbits OFFSET(32) NUMBITS(4) [],
+        /// [31:24] HCC
+        /// Number of collections held in the ITS itself.
+        HCC OFFSET(24) NUMBITS(8) [],
+        /// [19] PTA
+        /// `RDbase` fields hold physical addresses rather than processor numbers.
+        PTA OFFSET(19) NUMBITS(1) [],
+        /// [18] SEIS
+        /// Locally generated System Errors are supported.
+        SEIS OFFSET(18) NUMBITS(1) [],
+        /// [17:13] Devbits
+        /// Number of DeviceID bits supported, minus one.
+        Devbits OFFSET(13) NUMBITS(5) [],
+        /// [12:8] ID_bits
+        /// Number of EventID bits supported, minus one.
+        ID_bits OFFSET(8) NUMBITS(5) [],
+        /// [7:4] ITT_entry_size
+        /// Size of an ITT entry in bytes, minus one.
+        ITT_entry_size OFFSET(4) NUMBITS(4) [],
+        /// [2] CCT
+        /// Cumulative Collection Tables.
+        CCT OFFSET(2) NUMBITS(1) [],
+        /// [1] Virtual
+        /// Virtual LPIs and the GICv4 commands are supported.
+        Virtual OFFSET(1) NUMBITS(1) [],
+        /// [0] Physical
+        /// Physical LPIs are supported.
+        Physical OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GITS_UMSIR [
+        /// [63:32] DeviceID
+        DeviceID OFFSET(32) NUMBITS(32) [],
+        /// [31:0] EventID
+        EventID OFFSET(0) NUMBITS(32) []
+    ],
+
+    pub GITS_CBASER [
+        /// [63] Valid
+        Valid OFFSET(63) NUMBITS(1) [],
+        /// [61:59] InnerCache
+        InnerCache OFFSET(59) NUMBITS(3) [
+            DeviceNGnRnE = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [55:53] OuterCache
+        OuterCache OFFSET(53) NUMBITS(3) [
+            SameAsInner = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [51:12] Physical_Address
+        /// Bits [51:12] of the command queue address.
+        Physical_Address OFFSET(12) NUMBITS(40) [],
+        /// [11:10] Shareability
+        Shareability OFFSET(10) NUMBITS(2) [
+            NonShareable = 0,
+            InnerShareable = 1,
+            OuterShareable = 2
+        ],
+        /// [7:0] Size
+        /// Number of 4KB pages of the command queue, minus one.
+        Size OFFSET(0) NUMBITS(8) []
+    ],
+
+    pub GITS_CWRITER [
+        /// [19:5] Offset
+        /// Offset of the next command to be written, in 32-byte units.
+        Offset OFFSET(5) NUMBITS(15) [],
+        /// [0] Retry
+        /// Restart processing of a stalled command queue.
+        Retry OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GITS_CREADR [
+        /// [19:5] Offset
+        /// Offset of the next command to be read, in 32-byte units.
+        Offset OFFSET(5) NUMBITS(15) [],
+        /// [0] Stalled
+        /// Command processing stalled on an error.
+        Stalled OFFSET(0) NUMBITS(1) []
+    ],
+
+    pub GITS_BASER [
+        /// [63] Valid
+        Valid OFFSET(63) NUMBITS(1) [],
+        /// [62] Indirect
+        /// Two-level table.
+        Indirect OFFSET(62) NUMBITS(1) [],
+        /// [61:59] InnerCache
+        InnerCache OFFSET(59) NUMBITS(3) [
+            DeviceNGnRnE = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [58:56] Type
+        /// Kind of table described by this register. Read-only.
+        Type OFFSET(56) NUMBITS(3) [
+            Unimplemented = 0,
+            Devices = 1,
+            VPEs = 2,
+            Collections = 4
+        ],
+        /// [55:53] OuterCache
+        OuterCache OFFSET(53) NUMBITS(3) [
+            SameAsInner = 0,
+            NonCacheable = 1,
+            RaWt = 2,
+            RaWb = 3,
+            WaWt = 4,
+            WaWb = 5,
+            RaWaWt = 6,
+            RaWaWb = 7
+        ],
+        /// [52:48] Entry_Size
+        /// Size of a table entry in bytes, minus one. Read-only.
+        Entry_Size OFFSET(48) NUMBITS(5) [],
+        /// [47:12] Physical_Address
+        /// Bits [47:12] of the table address with 4KB or 16KB pages. With 64KB pages
+        /// bits [15:12] hold bits [51:48] of the address.
+        Physical_Address OFFSET(12) NUMBITS(36) [],
+        /// [11:10] Shareability
+        Shareability OFFSET(10) NUMBITS(2) [
+            NonShareable = 0,
+            InnerShareable = 1,
+            OuterShareable = 2
+        ],
+        /// [9:8] Page_Size
+        Page_Size OFFSET(8) NUMBITS(2) [
+            Size4K = 0,
+            Size16K = 1,
+            Size64K = 2
+        ],
+        /// [7:0] Size
+        /// Number of pages of the table, minus one.
+        Size OFFSET(0) NUMBITS(8) []
+    ]
+}
+
+register_structs! {
+    /// The control frame of an ITS.
+    #[allow(non_snake_case)]
+    pub GitsRegs {
+        (0x0000 => pub CTLR: ReadWrite<u32, GITS_CTLR::Register>),
+        (0x0004 => pub IIDR: ReadOnly<u32, GICD_IIDR::Register>),
+        (0x0008 => pub TYPER: ReadOnly<u64, GITS_TYPER::Register>),
+        (0x0010 => pub MPAMIDR: ReadOnly<u32, GICR_MPAMIDR::Register>),
+        (0x0014 => pub PARTIDR: ReadWrite<u32, GICR_PARTIDR::Register>),
+        (0x0018 => pub MPIDR: ReadOnly<u32, GITS_MPIDR::Register>),
+        (0x001c => _reserved0),
+        (0x0040 => pub STATUSR: ReadWrite<u32, GICD_STATUSR::Register>),
+        (0x0044 => _reserved1),
+        (0x0048 => pub UMSIR: ReadOnly<u64, GITS_UMSIR::Register>),
+        (0x0050 => _reserved2),
+        (0x0080 => pub CBASER: ReadWrite<u64, GITS_CBASER::Register>),
+        (0x0088 => pub CWRITER: ReadWrite<u64, GITS_CWRITER::Register>),
+        (0x0090 => pub CREADR: ReadOnly<u64, GITS_CREADR::Register>),
+        (0x0098 => _reserved3),
+        (0x0100 => pub BASER: [ReadWrite<u64, GITS_BASER::Register>; 8]),
+        (0x0140 => _reserved4),
+        (0xffd0 => pub PIDR4: ReadOnly<u32>),
+        (0xffd4 => pub PIDR5: ReadOnly<u32>),
+        (0xffd8 => pub PIDR6: ReadOnly<u32>),
+        (0xffdc => pub PIDR7: ReadOnly<u32>),
+        (0xffe0 => pub PIDR0: ReadOnly<u32>),
+        (0xffe4 => pub PIDR1: ReadOnly<u32>),
+        (0xffe8 => pub PIDR2: ReadOnly<u32, GICD_PIDR2::Register>),
+        (0xffec => pub PIDR3: ReadOnly<u32>),
+        (0xfff0 => pub CIDR0: ReadOnly<u32>),
+        (0xfff4 => pub CIDR1: ReadOnly<u32>),
+        (0xfff8 => pub CIDR2: ReadOnly<u32>),
+        (0xfffc => pub CIDR3: ReadOnly<u32>),
+        (0x10000 => @END),
+    }
+}
+
+register_structs! {
+    /// The translation frame of an ITS, at `ITS_base + 0x10000`.
+    #[allow(non_snake_case)]
+    pub GitsTranslationRegs {
+        (0x0000 => _reserved0),
+        (0x0040 => pub TRANSLATER: WriteOnly<u32, GITS_TRANSLATER::Register>),
+        (0x0044 => _reserved1),
+        (0x10000 => @END),
+    }
+}
@@ -0,0 +1,45 @@
+//! Interrupt Controller Software Generated Interrupt Group 1 Register, ICC_SGI1R_EL1
+//! The ICC_SGI1R_EL1 characteristics are:
+//!
+//! ## Purpose
+//!
+//! Generates Group 1 SGIs for the current Security state when affinity routing is enabled.
+//!
+//! ## Configurations
+//!
+//! This register is only used when `GICD_CTLR.ARE` is set for the current Security state.
+//! Otherwise SGIs are generated through the memory-mapped `GICD_SGIR`.
+
+use tock_registers::register_bitfields;
+
+register_bitfields! {u64,
+    pub ICC_SGI1R_EL1 [
+        /// [55:48] Aff3
+        /// The affinity 3 value of the affinity path of the cluster for which SGI interrupts will be generated.
+        Aff3 OFFSET(48) NUMBITS(8) [],
+        /// [47:44] RS
+        /// RangeSelector. Controls which group of 16 values is represented by the TargetList field.
+        RS OFFSET(44) NUMBITS(4) [],
+        /// [40] IRM
+        /// Interrupt Routing Mode:
+        /// - 0 Interrupts routed to the PEs specified by Aff3.Aff2.Aff1.<target list>.
+        /// - 1 Interrupts routed to all PEs in the system, excluding "self".
+        IRM OFFSET(40) NUMBITS(1) [
+            TargetList = 0,
+            AllExceptSelf = 1
+        ],
+        /// [39:32] Aff2
+        /// The affinity 2 value of the affinity path of the cluster for which SGI interrupts will be generated.
+        Aff2 OFFSET(32) NUMBITS(8) [],
+        /// [27:24] INTID
+        /// The INTID of the SGI.
+        INTID OFFSET(24) NUMBITS(4) [],
+        /// [23:16] Aff1
+        /// The affinity 1 value of the affinity path of the cluster for which SGI interrupts will be generated.
+        Aff1 OFFSET(16) NUMBITS(8) [],
+        /// [15:0] TargetList
+        /// The set of PEs for which SGI interrupts will be generated. Each bit corresponds to the
+        /// PE within a cluster with an Aff0 value equal to the bit number, offset by 16 * RS.
+        TargetList OFFSET(0) NUMBITS(16) []
+    ]
+}
@@ -0,0 +1,782 @@
+//! ITS commands
+//!
+//! ## Purpose
+//!
+//! Encodes and decodes the 32-byte commands of the ITS command queue, as four
+//! little-endian doublewords `DW0`-`DW3`.
+//!
+//! ## Usage
+//!
+//! A host ITS driver builds commands with [`ItsCommand::encode`], an emulated ITS
+//! parses the guest queue with [`ItsCommand::decode`]. Fields of a command that are
+//! not listed by the architecture are ignored on decode and written as zero.
+
+use axerrno::{AxResult, ax_err};
+use tock_registers::LocalRegisterCopy;
+use tock_registers::fields::FieldValue;
+use tock_registers::register_bitfields;
+
+/// Size in bytes of an ITS command.
+pub const ITS_CMD_SIZE: usize = 32;
+
+register_bitfields! {u64,
+    /// Fields of the first doubleword of a command.
+    pub ITS_CMD_DW0 [
+        /// [63:32] DeviceID
+        DeviceID OFFSET(32) NUMBITS(32) [],
+        /// [51:16] VCONF_addr
+        /// Bits [51:16] of the vPE configuration table address (`VMAPP`, GICv4.1).
+        VCONF_addr OFFSET(16) NUMBITS(36) [],
+        /// [47:32] SequenceNumber (`VMOVP`)
+        SequenceNumber OFFSET(32) NUMBITS(16) [],
+        /// [35:32] SGI INTID (`VSGI`)
+        SgiIntid OFFSET(32) NUMBITS(4) [],
+        /// [23:20] Priority (`VSGI`)
+        Priority OFFSET(20) NUMBITS(4) [],
+        /// [10] G (`VSGI`)
+        Group OFFSET(10) NUMBITS(1) [],
+        /// [9] PTZ (`VMAPP`) / C (`VSGI`)
+        PtzClear OFFSET(9) NUMBITS(1) [],
+        /// [8] Alloc (`VMAPP`) / En (`VSGI`)
+        AllocEnable OFFSET(8) NUMBITS(1) [],
+        /// [7:0] Command number
+        CommandType OFFSET(0) NUMBITS(8) []
+    ],
+
+    /// Fields of the second doubleword of a command.
+    pub ITS_CMD_DW1 [
+        /// [63:32] pINTID (`MAPTI`)
+        PhysicalId OFFSET(32) NUMBITS(32) [],
+        /// [47:32] vPEID
+        VpeId OFFSET(32) NUMBITS(16) [],
+        /// [31:0] EventID / Default doorbell (`VMAPP`, GICv4.1)
+        EventID OFFSET(0) NUMBITS(32) [],
+        /// [15:0] ITSList (`VMOVP`)
+        ItsList OFFSET(0) NUMBITS(16) [],
+        /// [4:0] Size (`MAPD`)
+        /// Number of EventID bits of the device, minus one.
+        Size OFFSET(0) NUMBITS(5) []
+    ],
+
+    /// Fields of the third doubleword of a command.
+    pub ITS_CMD_DW2 [
+        /// [63] V (`MAPD`, `MAPC`, `VMAPP`) / DB (`VMOVP`, GICv4.1)
+        Valid OFFSET(63) NUMBITS(1) [],
+        /// [63:32] Doorbell pINTID
+        Doorbell OFFSET(32) NUMBITS(32) [],
+        /// [51:16] RDbase
+        RDbase OFFSET(16) NUMBITS(36) [],
+        /// [51:8] ITT_addr (`MAPD`)
+        IttAddr OFFSET(8) NUMBITS(44) [],
+        /// [31:0] vINTID
+        VirtualId OFFSET(0) NUMBITS(32) [],
+        /// [15:0] ICID
+        Icid OFFSET(0) NUMBITS(16) [],
+        /// [0] D (`VMOVI`)
+        DoorbellValid OFFSET(0) NUMBITS(1) []
+    ],
+
+    /// Fields of the fourth doubleword of a command.
+    pub ITS_CMD_DW3 [
+        /// [51:16] RDbase2 (`MOVALL`) / VPT_addr (`VMAPP`)
+        Address OFFSET(16) NUMBITS(36) [],
+        /// [31:0] Default doorbell (`VMOVP`, GICv4.1)
+        DefaultDoorbell OFFSET(0) NUMBITS(32) [],
+        /// [4:0] VPT_size (`VMAPP`)
+        VptSize OFFSET(0) NUMBITS(5) []
+    ]
+}
+
+/// A GICv3/GICv4 ITS command.
+///
+/// `rd_base` fields hold the raw `RDbase` field: bits [51:16] of the redistributor
+/// address if `GITS_TYPER.PTA` is set, its processor number (`GICR_TYPER.Processor_Number`)
+/// otherwise. Table addresses are full physical addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItsCommand {
+    /// Moves the LPI of an event to another collection.
+    Movi {
+        device_id: u32,
+        event_id: u32,
+        icid: u16,
+    },
+    /// Makes the LPI of an event pending.
+    Int { device_id: u32, event_id: u32 },
+    /// Clears the pending state of the LPI of an event.
+    Clear { device_id: u32, event_id: u32 },
+    /// Waits for the effects of previous commands on a redistributor.
+    Sync { rd_base: u64 },
+    /// Maps (or unmaps, if `!valid`) a device to its Interrupt Translation Table.
+    Mapd {
+        device_id: u32,
+        /// Number of EventID bits, minus one.
+        size: u8,
+        itt_addr: u64,
+        valid: bool,
+    },
+    /// Maps (or unmaps, if `!valid`) a collection to a redistributor.
+    Mapc {
+        icid: u16,
+        rd_base: u64,
+        valid: bool,
+    },
+    /// Maps an event to a physical LPI and a collection.
+    Mapti {
+        device_id: u32,
+        event_id: u32,
+        pintid: u32,
+        icid: u16,
+    },
+    /// Maps an event to the LPI `event_id` and a collection.
+    Mapi {
+        device_id: u32,
+        event_id: u32,
+        icid: u16,
+    },
+    /// Reloads the configuration of the LPI of an event.
+    Inv { device_id: u32, event_id: u32 },
+    /// Reloads the configuration of all LPIs of a collection.
+    Invall { icid: u16 },
+    /// Moves all LPIs pending on a redistributor to another.
+    Movall { rd_base1: u64, rd_base2: u64 },
+    /// Removes the mapping of an event and clears its pending state.
+    Discard { device_id: u32, event_id: u32 },
+    /// Moves the virtual LPI of an event to another vPE.
+    Vmovi {
+        device_id: u32,
+        event_id: u32,
+        vpe_id: u16,
+        /// Physical doorbell LPI, if any.
+        doorbell: Option<u32>,
+    },
+    /// Moves a vPE to another redistributor.
+    Vmovp {
+        sequence_number: u16,
+        its_list: u16,
+        vpe_id: u16,
+        rd_base: u64,
+        /// Default doorbell LPI of the vPE (GICv4.1), if any.
+        default_doorbell: Option<u32>,
+    },
+    /// Waits for the effects of previous commands on a vPE.
+    Vsync { vpe_id: u16 },
+    /// Maps (or unmaps, if `!valid`) a vPE to a redistributor.
+    Vmapp {
+        vpe_id: u16,
+        rd_base: u64,
+        valid: bool,
+        vpt_addr: u64,
+        /// Number of virtual LPI INTID bits, minus one.
+        vpt_size: u8,
+        /// vPE configuration table address (GICv4.1).
+        vconf_addr: u64,
+        /// Whether the vPE configuration table entry is to be allocated (GICv4.1).
+        alloc: bool,
+        /// Whether the virtual pending table is known to be zeroed (GICv4.1).
+        ptz: bool,
+        /// Default doorbell LPI (GICv4.1).
+        default_doorbell: u32,
+    },
+    /// Maps an event to a virtual LPI of a vPE.
+    Vmapti {
+        device_id: u32,
+        event_id: u32,
+        vpe_id: u16,
+        vintid: u32,
+        /// Physical doorbell LPI, 1023 if none.
+        doorbell: u32,
+    },
+    /// Maps an event to the virtual LPI `event_id` of a vPE.
+    Vmapi {
+        device_id: u32,
+        event_id: u32,
+        vpe_id: u16,
+        /// Physical doorbell LPI, 1023 if none.
+        doorbell: u32,
+    },
+    /// Reloads the configuration of all virtual LPIs of a vPE.
+    Vinvall { vpe_id: u16 },
+    /// Reloads the doorbell configuration of a vPE (GICv4.1).
+    Invdb { vpe_id: u16 },
+    /// Updates the state of a virtual SGI (GICv4.1).
+    Vsgi {
+        vpe_id: u16,
+        intid: u8,
+        /// Priority, with bits [3:0] as zero.
+        priority: u8,
+        /// Group 1 if set, Group 0 otherwise.
+        group: bool,
+        /// Clears the pending state.
+        clear: bool,
+        enable: bool,
+    },
+}
+
+impl ItsCommand {
+    pub const MOVI: u8 = 0x01;
+    pub const INT: u8 = 0x03;
+    pub const CLEAR: u8 = 0x04;
+    pub const SYNC: u8 = 0x05;
+    pub const MAPD: u8 = 0x08;
+    pub const MAPC: u8 = 0x09;
+    pub const MAPTI: u8 = 0x0a;
+    pub const MAPI: u8 = 0x0b;
+    pub const INV: u8 = 0x0c;
+    pub const INVALL: u8 = 0x0d;
+    pub const MOVALL: u8 = 0x0e;
+    pub const DISCARD: u8 = 0x0f;
+    pub const VMOVI: u8 = 0x21;
+    pub const VMOVP: u8 = 0x22;
+    pub const VSGI: u8 = 0x23;
+    pub const VSYNC: u8 = 0x25;
+    pub const VMAPP: u8 = 0x29;
+    pub const VMAPTI: u8 = 0x2a;
+    pub const VMAPI: u8 = 0x2b;
+    pub const VINVALL: u8 = 0x2d;
+    pub const INVDB: u8 = 0x2e;
+
+    /// Returns the command number, as held in `DW0[7:0]`.
+    pub fn command_type(&self) -> u8 {
+        match self {
+            Self::Movi { .. } => Self::MOVI,
+            Self::Int { .. } => Self::INT,
+            Self::Clear { .. } => Self::CLEAR,
+            Self::Sync { .. } => Self::SYNC,
+            Self::Mapd { .. } => Self::MAPD,
+            Self::Mapc { .. } => Self::MAPC,
+            Self::Mapti { .. } => Self::MAPTI,
+            Self::Mapi { .. } => Self::MAPI,
+            Self::Inv { .. } => Self::INV,
+            Self::Invall { .. } => Self::INVALL,
+            Self::Movall { .. } => Self::MOVALL,
+            Self::Discard { .. } => Self::DISCARD,
+            Self::Vmovi { .. } => Self::VMOVI,
+            Self::Vmovp { .. } => Self::VMOVP,
+            Self::Vsgi { .. } => Self::VSGI,
+            Self::Vsync { .. } => Self::VSYNC,
+            Self::Vmapp { .. } => Self::VMAPP,
+            Self::Vmapti { .. } => Self::VMAPTI,
+            Self::Vmapi { .. } => Self::VMAPI,
+            Self::Vinvall { .. } => Self::VINVALL,
+            Self::Invdb { .. } => Self::INVDB,
+        }
+    }
+
+    /// Encodes the command as its four doublewords.
+    pub fn encode(&self) -> [u64; 4] {
+        use ITS_CMD_DW0 as D0;
+        use ITS_CMD_DW1 as D1;
+        use ITS_CMD_DW2 as D2;
+        use ITS_CMD_DW3 as D3;
+
+        let mut dw0 = D0::CommandType.val(self.command_type() as u64);
+        let mut dw1 = FieldValue::<u64, D1::Register>::new(0, 0, 0);
+        let mut dw2 = FieldValue::<u64, D2::Register>::new(0, 0, 0);
+        let mut dw3 = FieldValue::<u64, D3::Register>::new(0, 0, 0);
+
+        match *self {
+            Self::Int {
+                device_id,
+                event_id,
+            }
+            | Self::Clear {
+                device_id,
+                event_id,
+            }
+            | Self::Inv {
+                device_id,
+                event_id,
+            }
+            | Self::Discard {
+                device_id,
+                event_id,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::EventID.val(event_id as u64);
+            }
+            Self::Movi {
+                device_id,
+                event_id,
+                icid,
+            }
+            | Self::Mapi {
+                device_id,
+                event_id,
+                icid,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::EventID.val(event_id as u64);
+                dw2 += D2::Icid.val(icid as u64);
+            }
+            Self::Sync { rd_base } => dw2 += D2::RDbase.val(rd_base),
+            Self::Mapd {
+                device_id,
+                size,
+                itt_addr,
+                valid,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::Size.val(size as u64);
+                dw2 += D2::IttAddr.val(itt_addr >> 8) + D2::Valid.val(valid as u64);
+            }
+            Self::Mapc {
+                icid,
+                rd_base,
+                valid,
+            } => {
+                dw2 += D2::Icid.val(icid as u64)
+                    + D2::RDbase.val(rd_base)
+                    + D2::Valid.val(valid as u64);
+            }
+            Self::Mapti {
+                device_id,
+                event_id,
+                pintid,
+                icid,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::EventID.val(event_id as u64) + D1::PhysicalId.val(pintid as u64);
+                dw2 += D2::Icid.val(icid as u64);
+            }
+            Self::Invall { icid } => dw2 += D2::Icid.val(icid as u64),
+            Self::Movall { rd_base1, rd_base2 } => {
+                dw2 += D2::RDbase.val(rd_base1);
+                dw3 += D3::Address.val(rd_base2);
+            }
+            Self::Vmovi {
+                device_id,
+                event_id,
+                vpe_id,
+                doorbell,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::EventID.val(event_id as u64) + D1::VpeId.val(vpe_id as u64);
+                if let Some(doorbell) = doorbell {
+                    dw2 += D2::DoorbellValid::SET + D2::Doorbell.val(doorbell as u64);
+                }
+            }
+            Self::Vmovp {
+                sequence_number,
+                its_list,
+                vpe_id,
+                rd_base,
+                default_doorbell,
+            } => {
+                dw0 += D0::SequenceNumber.val(sequence_number as u64);
+                dw1 += D1::ItsList.val(its_list as u64) + D1::VpeId.val(vpe_id as u64);
+                dw2 += D2::RDbase.val(rd_base);
+                if let Some(doorbell) = default_doorbell {
+                    dw2 += D2::Valid::SET;
+                    dw3 += D3::DefaultDoorbell.val(doorbell as u64);
+                }
+            }
+            Self::Vsync { vpe_id } | Self::Vinvall { vpe_id } | Self::Invdb { vpe_id } => {
+                dw1 += D1::VpeId.val(vpe_id as u64);
+            }
+            Self::Vmapp {
+                vpe_id,
+                rd_base,
+                valid,
+                vpt_addr,
+                vpt_size,
+                vconf_addr,
+                alloc,
+                ptz,
+                default_doorbell,
+            } => {
+                dw0 += D0::VCONF_addr.val(vconf_addr >> 16)
+                    + D0::AllocEnable.val(alloc as u64)
+                    + D0::PtzClear.val(ptz as u64);
+                dw1 += D1::VpeId.val(vpe_id as u64) + D1::EventID.val(default_doorbell as u64);
+                dw2 += D2::RDbase.val(rd_base) + D2::Valid.val(valid as u64);
+                dw3 += D3::Address.val(vpt_addr >> 16) + D3::VptSize.val(vpt_size as u64);
+            }
+            Self::Vmapti {
+                device_id,
+                event_id,
+                vpe_id,
+                vintid,
+                doorbell,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::EventID.val(event_id as u64) + D1::VpeId.val(vpe_id as u64);
+                dw2 += D2::VirtualId.val(vintid as u64) + D2::Doorbell.val(doorbell as u64);
+            }
+            Self::Vmapi {
+                device_id,
+                event_id,
+                vpe_id,
+                doorbell,
+            } => {
+                dw0 += D0::DeviceID.val(device_id as u64);
+                dw1 += D1::EventID.val(event_id as u64) + D1::VpeId.val(vpe_id as u64);
+                dw2 += D2::Doorbell.val(doorbell as u64);
+            }
+            Self::Vsgi {
+                vpe_id,
+                intid,
+                priority,
+                group,
+                clear,
+                enable,
+            } => {
+                dw0 += D0::SgiIntid.val(intid as u64)
+                    + D0::Priority.val((priority >> 4) as u64)
+                    + D0::Group.val(group as u64)
+                    + D0::PtzClear.val(clear as u64)
+                    + D0::AllocEnable.val(enable as u64);
+                dw1 += D1::VpeId.val(vpe_id as u64);
+            }
+        }
+
+        [dw0.value, dw1.value, dw2.value, dw3.value]
+    }
+
+    /// Decodes a command from its four doublewords.
+    ///
+    /// Returns [`InvalidData`](axerrno::AxError::InvalidData) for an unknown command number.
+    pub fn decode(raw: &[u64; 4]) -> AxResult<Self> {
+        use ITS_CMD_DW0 as D0;
+        use ITS_CMD_DW1 as D1;
+        use ITS_CMD_DW2 as D2;
+        use ITS_CMD_DW3 as D3;
+
+        let dw0 = LocalRegisterCopy::<u64, D0::Register>::new(raw[0]);
+        let dw1 = LocalRegisterCopy::<u64, D1::Register>::new(raw[1]);
+        let dw2 = LocalRegisterCopy::<u64, D2::Register>::new(raw[2]);
+        let dw3 = LocalRegisterCopy::<u64, D3::Register>::new(raw[3]);
+
+        let device_id = dw0.read(D0::DeviceID) as u32;
+        let event_id = dw1.read(D1::EventID) as u32;
+        let vpe_id = dw1.read(D1::VpeId) as u16;
+        let icid = dw2.read(D2::Icid) as u16;
+        let rd_base = dw2.read(D2::RDbase);
+        let valid = dw2.is_set(D2::Valid);
+
+        let command = match dw0.read(D0::CommandType) as u8 {
+            Self::MOVI => Self::Movi {
+                device_id,
+                event_id,
+                icid,
+            },
+            Self::INT => Self::Int {
+                device_id,
+                event_id,
+            },
+            Self::CLEAR => Self::Clear {
+                device_id,
+                event_id,
+            },
+            Self::SYNC => Self::Sync { rd_base },
+            Self::MAPD => Self::Mapd {
+                device_id,
+                size: dw1.read(D1::Size) as u8,
+                itt_addr: dw2.read(D2::IttAddr) << 8,
+                valid,
+            },
+            Self::MAPC => Self::Mapc {
+                icid,
+                rd_base,
+                valid,
+            },
+            Self::MAPTI => Self::Mapti {
+                device_id,
+                event_id,
+                pintid: dw1.read(D1::PhysicalId) as u32,
+                icid,
+            },
+            Self::MAPI => Self::Mapi {
+                device_id,
+                event_id,
+                icid,
+            },
+            Self::INV => Self::Inv {
+                device_id,
+                event_id,
+            },
+            Self::INVALL => Self::Invall { icid },
+            Self::MOVALL => Self::Movall {
+                rd_base1: rd_base,
+                rd_base2: dw3.read(D3::Address),
+            },
+            Self::DISCARD => Self::Discard {
+                device_id,
+                event_id,
+            },
+            Self::VMOVI => Self::Vmovi {
+                device_id,
+                event_id,
+                vpe_id,
+                doorbell: dw2
+                    .is_set(D2::DoorbellValid)
+                    .then(|| dw2.read(D2::Doorbell) as u32),
+            },
+            Self::VMOVP => Self::Vmovp {
+                sequence_number: dw0.read(D0::SequenceNumber) as u16,
+                its_list: dw1.read(D1::ItsList) as u16,
+                vpe_id,
+                rd_base,
+                default_doorbell: valid.then(|| dw3.read(D3::DefaultDoorbell) as u32),
+            },
+            Self::VSGI => Self::Vsgi {
+                vpe_id,
+                intid: dw0.read(D0::SgiIntid) as u8,
+                priority: (dw0.read(D0::Priority) as u8) << 4,
+                group: dw0.is_set(D0::Group),
+                clear: dw0.is_set(D0::PtzClear),
+                enable: dw0.is_set(D0::AllocEnable),
+            },
+            Self::VSYNC => Self::Vsync { vpe_id },
+            Self::VMAPP => Self::Vmapp {
+                vpe_id,
+                rd_base,
+                valid,
+                vpt_addr: dw3.read(D3::Address) << 16,
+                vpt_size: dw3.read(D3::VptSize) as u8,
+                vconf_addr: dw0.read(D0::VCONF_addr) << 16,
+                alloc: dw0.is_set(D0::AllocEnable),
+                ptz: dw0.is_set(D0::PtzClear),
+                default_doorbell: event_id,
+            },
+            Self::VMAPTI => Self::Vmapti {
+                device_id,
+                event_id,
+                vpe_id,
+                vintid: dw2.read(D2::VirtualId) as u32,
+                doorbell: dw2.read(D2::Doorbell) as u32,
+            },
+            Self::VMAPI => Self::Vmapi {
+                device_id,
+                event_id,
+                vpe_id,
+                doorbell: dw2.read(D2::Doorbell) as u32,
+            },
+            Self::VINVALL => Self::Vinvall { vpe_id },
+            Self::INVDB => Self::Invdb { vpe_id },
+            _ => return ax_err!(InvalidData, "unknown ITS command"),
+        };
+        Ok(command)
+    }
+
+    /// Encodes the command in its in-memory format.
+    pub fn to_bytes(&self) -> [u8; ITS_CMD_SIZE] {
+        let mut bytes = [0; ITS_CMD_SIZE];
+        for (chunk, dw) in bytes.chunks_exact_mut(8).zip(self.encode()) {
+            chunk.copy_from_slice(&dw.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a command from its in-memory format.
+    pub fn from_bytes(bytes: &[u8; ITS_CMD_SIZE]) -> AxResult<Self> {
+        let mut raw = [0; 4];
+        for (dw, chunk) in raw.iter_mut().zip(bytes.chunks_exact(8)) {
+            *dw = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Self::decode(&raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_commands() -> [ItsCommand; 21] {
+        [
+            ItsCommand::Movi {
+                device_id: 0xdead_beef,
+                event_id: 0x1234_5678,
+                icid: 0xabcd,
+            },
+            ItsCommand::Int {
+                device_id: 7,
+                event_id: 0xffff_ffff,
+            },
+            ItsCommand::Clear {
+                device_id: 0xffff_ffff,
+                event_id: 3,
+            },
+            ItsCommand::Sync {
+                rd_base: 0xf_ffff_ffff,
+            },
+            ItsCommand::Mapd {
+                device_id: 0x42,
+                size: 0x1f,
+                itt_addr: 0xf_ffff_ffff_ff00,
+                valid: true,
+            },
+            ItsCommand::Mapc {
+                icid: 0xffff,
+                rd_base: 0x80a,
+                valid: true,
+            },
+            ItsCommand::Mapti {
+                device_id: 0x10,
+                event_id: 0x20,
+                pintid: 8192,
+                icid: 3,
+            },
+            ItsCommand::Mapi {
+                device_id: 0x11,
+                event_id: 8193,
+                icid: 4,
+            },
+            ItsCommand::Inv {
+                device_id: 1,
+                event_id: 2,
+            },
+            ItsCommand::Invall { icid: 0x5a5a },
+            ItsCommand::Movall {
+                rd_base1: 1,
+                rd_base2: 0xf_ffff_ffff,
+            },
+            ItsCommand::Discard {
+                device_id: 9,
+                event_id: 10,
+            },
+            ItsCommand::Vmovi {
+                device_id: 5,
+                event_id: 6,
+                vpe_id: 0xffff,
+                doorbell: Some(8200),
+            },
+            ItsCommand::Vmovp {
+                sequence_number: 0xfedc,
+                its_list: 0x8001,
+                vpe_id: 12,
+                rd_base: 0x123_4567,
+                default_doorbell: Some(0xffff_ffff),
+            },
+            ItsCommand::Vsync { vpe_id: 0x7777 },
+            ItsCommand::Vmapp {
+                vpe_id: 33,
+                rd_base: 2,
+                valid: true,
+                vpt_addr: 0xf_ffff_ffff_0000,
+                vpt_size: 15,
+                vconf_addr: 0x8_0000_0000,
+                alloc: true,
+                ptz: true,
+                default_doorbell: 8300,
+            },
+            ItsCommand::Vmapti {
+                device_id: 0xffff_ffff,
+                event_id: 0xffff_ffff,
+                vpe_id: 0xffff,
+                vintid: 0xffff_ffff,
+                doorbell: 1023,
+            },
+            ItsCommand::Vmapi {
+                device_id: 13,
+                event_id: 14,
+                vpe_id: 15,
+                doorbell: 8400,
+            },
+            ItsCommand::Vinvall { vpe_id: 1 },
+            ItsCommand::Invdb { vpe_id: 2 },
+            ItsCommand::Vsgi {
+                vpe_id: 3,
+                intid: 15,
+                priority: 0xa0,
+                group: true,
+                clear: false,
+                enable: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for command in all_commands() {
+            let raw = command.encode();
+            assert_eq!(raw[0] as u8, command.command_type());
+            assert_eq!(ItsCommand::decode(&raw).unwrap(), command);
+            assert_eq!(
+                ItsCommand::from_bytes(&command.to_bytes()).unwrap(),
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_without_optional_fields() {
+        for command in [
+            ItsCommand::Vmovi {
+                device_id: 5,
+                event_id: 6,
+                vpe_id: 7,
+                doorbell: None,
+            },
+            ItsCommand::Vmovp {
+                sequence_number: 1,
+                its_list: 2,
+                vpe_id: 3,
+                rd_base: 4,
+                default_doorbell: None,
+            },
+            ItsCommand::Mapd {
+                device_id: 1,
+                size: 0,
+                itt_addr: 0,
+                valid: false,
+            },
+        ] {
+            assert_eq!(ItsCommand::decode(&command.encode()).unwrap(), command);
+        }
+    }
+
+    #[test]
+    fn known_encodings() {
+        // MAPD DeviceID 0x42, 16 EventIDs, ITT at 0x4000_0100, valid.
+        let mapd = ItsCommand::Mapd {
+            device_id: 0x42,
+            size: 3,
+            itt_addr: 0x4000_0100,
+            valid: true,
+        };
+        assert_eq!(
+            mapd.encode(),
+            [0x0000_0042_0000_0008, 3, 0x8000_0000_4000_0100, 0]
+        );
+
+        // MAPC ICID 1 to RDbase 0x80c0000 (PTA == 1).
+        let mapc = ItsCommand::Mapc {
+            icid: 1,
+            rd_base: 0x80c,
+            valid: true,
+        };
+        assert_eq!(mapc.encode(), [0x09, 0, 0x8000_0000_080c_0001, 0]);
+
+        let bytes = ItsCommand::Int {
+            device_id: 0x42,
+            event_id: 5,
+        }
+        .to_bytes();
+        assert_eq!(bytes[0], 0x03);
+        assert_eq!(bytes[4], 0x42);
+        assert_eq!(bytes[8], 5);
+    }
+
+    #[test]
+    fn decode_ignores_unused_fields() {
+        let raw = [
+            0xffff_ffff_0000_ff00 | ItsCommand::SYNC as u64,
+            u64::MAX,
+            0x0000_0123_4567_0000,
+            u64::MAX,
+        ];
+        assert_eq!(
+            ItsCommand::decode(&raw).unwrap(),
+            ItsCommand::Sync {
+                rd_base: 0x123_4567
+            }
+        );
+    }
+
+    #[test]
+    fn decode_rejects_unknown_commands() {
+        for command_type in [0x00, 0x02, 0x06, 0x20, 0x24, 0x2c, 0x2f, 0xff] {
+            assert!(ItsCommand::decode(&[command_type, 0, 0, 0]).is_err());
+        }
+    }
+}
@@ -1,6 +1,19 @@
+mod gicd;
 mod gicd_sgir;
+mod gicr;
+mod gits;
+mod icc_sgi1r;
+mod its_cmd;
 
+pub use gicd::*;
 pub use gicd_sgir::*;
+pub use gicr::*;
+pub use gits::*;
+pub use icc_sgi1r::*;
+pub use its_cmd::*;
 
 #[cfg(feature = "hv")]
 pub mod gich;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,609 @@
+//! Conformance of the register definitions with the architectural layouts.
+//!
+//! Each register is described by the bit range `[hi:lo]` the architecture gives to every
+//! field declared for it, and by the ranges the architecture leaves without a field (RES0,
+//! reserved or IMPLEMENTATION DEFINED). New registers and fields must be added to these
+//! tables, which are checked against the `register_bitfields!` declarations of the module.
+
+use alloc::vec::Vec;
+
+use super::*;
+
+/// A declared field and its architectural bit range.
+struct FieldLayout {
+    name: &'static str,
+    shift: usize,
+    mask: u64,
+    hi: u32,
+    lo: u32,
+}
+
+/// A register definition and its architectural layout.
+struct RegisterLayout {
+    name: &'static str,
+    width: u32,
+    fields: Vec<FieldLayout>,
+    /// Ranges without an architectural field, which no field may be declared over unless
+    /// it is named `Reserved*`.
+    reserved: &'static [(u32, u32)],
+    /// Whether fields overlap by design, as in the ITS command doublewords where each
+    /// command interprets the bits differently.
+    overlapping: bool,
+}
+
+/// Returns the mask of the bit range `[hi:lo]`.
+fn range_mask(hi: u32, lo: u32) -> u64 {
+    (u64::MAX >> (63 - hi)) & (u64::MAX << lo)
+}
+
+macro_rules! layout {
+    ($reg:ident: $ty:ty { $($field:ident [$hi:literal : $lo:literal]),* $(,)? }
+        reserved [$([$rhi:literal : $rlo:literal]),* $(,)?]) => {
+        layout!(@build $reg, $ty, false, [$($field [$hi : $lo]),*], [$([$rhi : $rlo]),*])
+    };
+    ($reg:ident: $ty:ty { $($field:ident [$hi:literal : $lo:literal]),* $(,)? } overlapping) => {
+        layout!(@build $reg, $ty, true, [$($field [$hi : $lo]),*], [])
+    };
+    (@build $reg:ident, $ty:ty, $overlapping:expr, [$($field:ident [$hi:literal : $lo:literal]),*],
+        [$([$rhi:literal : $rlo:literal]),*]) => {
+        RegisterLayout {
+            name: stringify!($reg),
+            width: <$ty>::BITS,
+            fields: alloc::vec![$(FieldLayout {
+                name: stringify!($field),
+                shift: $reg::$field.shift,
+                mask: $reg::$field.mask as u64,
+                hi: $hi,
+                lo: $lo,
+            }),*],
+            reserved: &[$(($rhi, $rlo)),*],
+            overlapping: $overlapping,
+        }
+    };
+}
+
+fn registers() -> Vec<RegisterLayout> {
+    let registers = alloc::vec![
+        layout!(GICD_CTLR: u32 {
+            RWP [31:31],
+            nASSGIreq [8:8],
+            E1NWF [7:7],
+            DS [6:6],
+            ARE_NS [5:5],
+            ARE_S [4:4],
+            EnableGrp1S [2:2],
+            EnableGrp1NS [1:1],
+            EnableGrp0 [0:0],
+        } reserved [[30:9], [3:3]]),
+        layout!(GICD_TYPER: u32 {
+            ESPI_range [31:27],
+            RSS [26:26],
+            No1N [25:25],
+            A3V [24:24],
+            IDbits [23:19],
+            DVIS [18:18],
+            LPIS [17:17],
+            MBIS [16:16],
+            num_LPIs [15:11],
+            SecurityExtn [10:10],
+            NMI [9:9],
+            ESPI [8:8],
+            CPUNumber [7:5],
+            ITLinesNumber [4:0],
+        } reserved []),
+        layout!(GICD_IIDR: u32 {
+            ProductID [31:24],
+            Variant [19:16],
+            Revision [15:12],
+            Implementer [11:0],
+        } reserved [[23:20]]),
+        layout!(GICD_TYPER2: u32 {
+            nASSGIcap [8:8],
+            VIL [7:7],
+            VID [4:0],
+        } reserved [[31:9], [6:5]]),
+        layout!(GICD_STATUSR: u32 {
+            WROD [3:3],
+            RWOD [2:2],
+            WRD [1:1],
+            RRD [0:0],
+        } reserved [[31:4]]),
+        layout!(GICD_SETSPI: u32 {
+            INTID [12:0],
+        } reserved [[31:13]]),
+        layout!(GICD_PIDR2: u32 {
+            ArchRev [7:4],
+            JEDEC [3:3],
+            DES_1 [2:0],
+        } reserved [[31:8]]),
+        layout!(GICD_IROUTER: u64 {
+            Aff3 [39:32],
+            Interrupt_Routing_Mode [31:31],
+            Aff2 [23:16],
+            Aff1 [15:8],
+            Aff0 [7:0],
+        } reserved [[63:40], [30:24]]),
+        layout!(GICD_SGIR: u32 {
+            Reserved31 [31:26],
+            TargetListFilter [25:24],
+            CPUTargetList [23:16],
+            NSATT [15:15],
+            Reserved14_4 [14:4],
+            SGIINTID [3:0],
+        } reserved []),
+        layout!(ICC_SGI1R_EL1: u64 {
+            Aff3 [55:48],
+            RS [47:44],
+            IRM [40:40],
+            Aff2 [39:32],
+            INTID [27:24],
+            Aff1 [23:16],
+            TargetList [15:0],
+        } reserved [[63:56], [43:41], [31:28]]),
+        layout!(GICR_CTLR: u32 {
+            UWP [31:31],
+            DPG1S [26:26],
+            DPG1NS [25:25],
+            DPG0 [24:24],
+            RWP [3:3],
+            IR [2:2],
+            CES [1:1],
+            EnableLPIs [0:0],
+        } reserved [[30:27], [23:4]]),
+        layout!(GICR_WAKER: u32 {
+            ChildrenAsleep [2:2],
+            ProcessorSleep [1:1],
+        } reserved [[31:3], [0:0]]),
+        layout!(GICR_MPAMIDR: u32 {
+            PMGMax [23:16],
+            PARTIDMax [15:0],
+        } reserved [[31:24]]),
+        layout!(GICR_PARTIDR: u32 {
+            PMG [23:16],
+            PARTID [15:0],
+        } reserved [[31:24]]),
+        layout!(GICR_SYNCR: u32 {
+            Busy [0:0],
+        } reserved [[31:1]]),
+        layout!(GICR_VSGIR: u32 {
+            vPEID [15:0],
+        } reserved [[31:16]]),
+        layout!(GICR_VSGIPENDR: u32 {
+            Busy [31:31],
+            Pending [15:0],
+        } reserved [[30:16]]),
+        layout!(GICR_TYPER: u64 {
+            Affinity_Value [63:32],
+            PPInum [31:27],
+            VSGI [26:26],
+            CommonLPIAff [25:24],
+            Processor_Number [23:8],
+            RVPEID [7:7],
+            MPAM [6:6],
+            DPGS [5:5],
+            Last [4:4],
+            DirectLPI [3:3],
+            Dirty [2:2],
+            VLPIS [1:1],
+            PLPIS [0:0],
+        } reserved []),
+        layout!(GICR_SETLPIR: u64 {
+            pINTID [31:0],
+        } reserved [[63:32]]),
+        layout!(GICR_INVLPIR: u64 {
+            V [63:63],
+            vPEID [47:32],
+            INTID [31:0],
+        } reserved [[62:48]]),
+        layout!(GICR_INVALLR: u64 {
+            V [63:63],
+            vPEID [47:32],
+        } reserved [[62:48], [31:0]]),
+        layout!(GICR_PROPBASER: u64 {
+            OuterCache [58:56],
+            Physical_Address [51:12],
+            Shareability [11:10],
+            InnerCache [9:7],
+            IDbits [4:0],
+        } reserved [[63:59], [55:52], [6:5]]),
+        layout!(GICR_PENDBASER: u64 {
+            PTZ [62:62],
+            OuterCache [58:56],
+            Physical_Address [51:16],
+            Shareability [11:10],
+            InnerCache [9:7],
+        } reserved [[63:63], [61:59], [55:52], [15:12], [6:0]]),
+        layout!(GICR_VPROPBASER: u64 {
+            OuterCache [58:56],
+            Physical_Address [51:12],
+            Shareability [11:10],
+            InnerCache [9:7],
+            IDbits [4:0],
+        } reserved [[63:59], [55:52], [6:5]]),
+        layout!(GICR_VPENDBASER: u64 {
+            Valid [63:63],
+            IDAI [62:62],
+            PendingLast [61:61],
+            Dirty [60:60],
+            OuterCache [58:56],
+            Physical_Address [51:16],
+            Shareability [11:10],
+            InnerCache [9:7],
+        } reserved [[59:59], [55:52], [15:12], [6:0]]),
+        layout!(GITS_CTLR: u32 {
+            Quiescent [31:31],
+            UMSIirq [8:8],
+            ITS_Number [7:4],
+            ImDe [1:1],
+            Enabled [0:0],
+        } reserved [[30:9], [3:2]]),
+        layout!(GITS_MPIDR: u32 {
+            Aff3 [31:24],
+            Aff2 [23:16],
+            Aff1 [15:8],
+        } reserved [[7:0]]),
+        layout!(GITS_TRANSLATER: u32 {
+            EventID [31:0],
+        } reserved []),
+        layout!(GITS_TYPER: u64 {
+            INV [46:46],
+            UMSIirq [45:45],
+            UMSI [44:44],
+            nID [43:43],
+            SVPET [42:41],
+            VMAPP [40:40],
+            VSGI [39:39],
+            MPAM [38:38],
+            VMOVP [37:37],
+            CIL [36:36],
+            CIDbits [35:32],
+            HCC [31:24],
+            PTA [19:19],
+            SEIS [18:18],
+            Devbits [17:13],
+            ID_bits [12:8],
+            ITT_entry_size [7:4],
+            CCT [2:2],
+            Virtual [1:1],
+            Physical [0:0],
+        } reserved [[63:47], [23:20], [3:3]]),
+        layout!(GITS_UMSIR: u64 {
+            DeviceID [63:32],
+            EventID [31:0],
+        } reserved []),
+        layout!(GITS_CBASER: u64 {
+            Valid [63:63],
+            InnerCache [61:59],
+            OuterCache [55:53],
+            Physical_Address [51:12],
+            Shareability [11:10],
+            Size [7:0],
+        } reserved [[62:62], [58:56], [52:52], [9:8]]),
+        layout!(GITS_CWRITER: u64 {
+            Offset [19:5],
+            Retry [0:0],
+        } reserved [[63:20], [4:1]]),
+        layout!(GITS_CREADR: u64 {
+            Offset [19:5],
+            Stalled [0:0],
+        } reserved [[63:20], [4:1]]),
+        layout!(GITS_BASER: u64 {
+            Valid [63:63],
+            Indirect [62:62],
+            InnerCache [61:59],
+            Type [58:56],
+            OuterCache [55:53],
+            Entry_Size [52:48],
+            Physical_Address [47:12],
+            Shareability [11:10],
+            Page_Size [9:8],
+            Size [7:0],
+        } reserved []),
+        layout!(ITS_CMD_DW0: u64 {
+            DeviceID [63:32],
+            VCONF_addr [51:16],
+            SequenceNumber [47:32],
+            SgiIntid [35:32],
+            Priority [23:20],
+            Group [10:10],
+            PtzClear [9:9],
+            AllocEnable [8:8],
+            CommandType [7:0],
+        } overlapping),
+        layout!(ITS_CMD_DW1: u64 {
+            PhysicalId [63:32],
+            VpeId [47:32],
+            EventID [31:0],
+            ItsList [15:0],
+            Size [4:0],
+        } overlapping),
+        layout!(ITS_CMD_DW2: u64 {
+            Valid [63:63],
+            Doorbell [63:32],
+            RDbase [51:16],
+            IttAddr [51:8],
+            VirtualId [31:0],
+            Icid [15:0],
+            DoorbellValid [0:0],
+        } overlapping),
+        layout!(ITS_CMD_DW3: u64 {
+            Address [51:16],
+            DefaultDoorbell [31:0],
+            VptSize [4:0],
+        } overlapping),
+    ];
+    #[cfg(feature = "hv")]
+    let registers = registers.into_iter().chain(gich_registers()).collect();
+    registers
+}
+
+#[cfg(feature = "hv")]
+fn gich_registers() -> Vec<RegisterLayout> {
+    use super::gich::*;
+
+    alloc::vec![
+        layout!(GICH_HCR: u32 {
+            EOICount [31:27],
+            Reserved26_8 [26:8],
+            VGrp1DIE [7:7],
+            VGrp1EIE [6:6],
+            VGrp0DIE [5:5],
+            VGrp0EIE [4:4],
+            NPIE [3:3],
+            LRENPIE [2:2],
+            UIE [1:1],
+            En [0:0],
+        } reserved []),
+        layout!(GICH_VTR: u32 {
+            PRIbits [31:29],
+            PREbits [28:26],
+            IDbits [25:23],
+            SEIS [22:22],
+            A3V [21:21],
+            Reserved20_5 [20:5],
+            ListRegs [4:0],
+        } reserved []),
+        layout!(GICH_VMCR: u32 {
+            VPMR [31:24],
+            VBPR0 [23:21],
+            VBPR1 [20:18],
+            Reserved17_10 [17:10],
+            VEOIM [9:9],
+            Reserved8_5 [8:5],
+            VCBPR [4:4],
+            VFIQEn [3:3],
+            VAckCtl [2:2],
+            VENG1 [1:1],
+            VENG0 [0:0],
+        } reserved []),
+        layout!(GICH_MISR: u32 {
+            Reserved31_8 [31:8],
+            VGrp1D [7:7],
+            VGrp1E [6:6],
+            VGrp0D [5:5],
+            VGrp0E [4:4],
+            NP [3:3],
+            LRENP [2:2],
+            U [1:1],
+            EOI [0:0],
+        } reserved []),
+        layout!(GICH_EISR: u32 {
+            Reserved31_16 [31:16],
+            Status [15:0],
+        } reserved []),
+        layout!(GICH_ELRSR: u32 {
+            Reserved31_16 [31:16],
+            Status [15:0],
+        } reserved []),
+        layout!(GICH_APR: u32 {
+            ACTIVE_PRIORITY_BITS [31:0],
+        } reserved []),
+        layout!(GICH_LR: u32 {
+            HW [31:31],
+            Group [30:30],
+            State [29:28],
+            Priority [27:23],
+            Reserved22_20 [22:20],
+            pINTID [19:10],
+            vINTID [9:0],
+        } reserved []),
+        layout!(ICH_LR_EL2: u64 {
+            State [63:62],
+            HW [61:61],
+            Group [60:60],
+            NMI [59:59],
+            Reserved58_56 [58:56],
+            Priority [55:48],
+            Reserved47_45 [47:45],
+            pINTID [44:32],
+            vINTID [31:0],
+        } reserved []),
+    ]
+}
+
+/// Returns the sources of the module, whose register declarations the tables must cover.
+fn sources() -> Vec<&'static str> {
+    let sources = alloc::vec![
+        include_str!("mod.rs"),
+        include_str!("gicd.rs"),
+        include_str!("gicd_sgir.rs"),
+        include_str!("gicr.rs"),
+        include_str!("gits.rs"),
+        include_str!("icc_sgi1r.rs"),
+        include_str!("its_cmd.rs"),
+    ];
+    #[cfg(feature = "hv")]
+    let sources = sources
+        .into_iter()
+        .chain([
+            include_str!("gich/mod.rs"),
+            include_str!("gich/gich_apr.rs"),
+            include_str!("gich/gich_eisr.rs"),
+            include_str!("gich/gich_elrsr.rs"),
+            include_str!("gich/gich_frame.rs"),
+            include_str!("gich/gich_hcr.rs"),
+            include_str!("gich/gich_lr.rs"),
+            include_str!("gich/gich_misr.rs"),
+            include_str!("gich/gich_values.rs"),
+            include_str!("gich/gich_vmcr.rs"),
+            include_str!("gich/gich_vtr.rs"),
+            include_str!("gich/ich_lr.rs"),
+        ])
+        .collect();
+    sources
+}
+
+/// Returns the registers declared with `register_bitfields!` in `source`, each with the
+/// names of its fields.
+fn declared_registers(source: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut registers: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if line.starts_with("//") {
+            continue;
+        }
+        let register = line
+            .strip_prefix("pub ")
+            .and_then(|line| line.strip_suffix(" ["))
+            .filter(|name| {
+                name.bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_')
+            });
+        if let Some(name) = register {
+            registers.push((name, Vec::new()));
+        } else if let Some((field, _)) = line.split_once(" OFFSET(") {
+            let (name, fields) = registers.last_mut().expect("field outside a register");
+            assert!(!field.contains(' '), "{name}: unexpected field declaration");
+            fields.push(field);
+        }
+    }
+    registers
+}
+
+#[test]
+fn tables_cover_every_declaration() {
+    let tables = registers();
+    let mut declared = 0;
+    for (name, fields) in sources().into_iter().flat_map(declared_registers) {
+        declared += 1;
+        let Some(reg) = tables.iter().find(|reg| reg.name == name) else {
+            panic!("{name}: missing from the tables");
+        };
+        for field in &fields {
+            assert!(
+                reg.fields.iter().any(|f| f.name == *field),
+                "{name}.{field}: missing from the table"
+            );
+        }
+        assert_eq!(reg.fields.len(), fields.len(), "{name}: number of fields");
+    }
+    assert_eq!(declared, tables.len(), "tables list undeclared registers");
+}
+
+#[test]
+fn fields_match_architectural_layout() {
+    for reg in registers() {
+        for field in &reg.fields {
+            assert!(
+                field.lo <= field.hi && field.hi < reg.width,
+                "{}.{}: [{}:{}] outside the register",
+                reg.name,
+                field.name,
+                field.hi,
+                field.lo
+            );
+            assert_eq!(
+                field.shift, field.lo as usize,
+                "{}.{}: offset",
+                reg.name, field.name
+            );
+            assert_eq!(
+                field.mask,
+                range_mask(field.hi - field.lo, 0),
+                "{}.{}: width",
+                reg.name,
+                field.name
+            );
+        }
+    }
+}
+
+#[test]
+fn fields_do_not_overlap() {
+    for reg in registers().into_iter().filter(|reg| !reg.overlapping) {
+        let mut used = 0u64;
+        for field in &reg.fields {
+            let mask = field.mask << field.shift;
+            assert_eq!(
+                used & mask,
+                0,
+                "{}.{} overlaps another field",
+                reg.name,
+                field.name
+            );
+            used |= mask;
+        }
+    }
+}
+
+#[test]
+fn reserved_fields_are_complementary() {
+    for reg in registers().into_iter().filter(|reg| !reg.overlapping) {
+        let full = range_mask(reg.width - 1, 0);
+        let mut declared = 0u64;
+        let mut declared_reserved = 0u64;
+        for field in &reg.fields {
+            let mask = field.mask << field.shift;
+            declared |= mask;
+            if field.name.starts_with("Reserved") {
+                declared_reserved |= mask;
+            }
+        }
+        let mut reserved = 0u64;
+        for &(hi, lo) in reg.reserved {
+            let mask = range_mask(hi, lo);
+            assert_eq!(reserved & mask, 0, "{}: reserved ranges overlap", reg.name);
+            reserved |= mask;
+        }
+        assert_eq!(
+            declared & reserved,
+            0,
+            "{}: field declared over reserved bits",
+            reg.name
+        );
+        assert_eq!(
+            declared | reserved,
+            full,
+            "{}: bits neither declared nor reserved",
+            reg.name
+        );
+        if declared_reserved != 0 {
+            // Registers declaring their reserved bits must declare all of them.
+            assert_eq!(
+                reserved, 0,
+                "{}: reserved bits not declared as fields",
+                reg.name
+            );
+        }
+    }
+}
+
+#[test]
+fn reserved_field_names_match_their_range() {
+    for reg in registers() {
+        for field in &reg.fields {
+            let Some(range) = field.name.strip_prefix("Reserved") else {
+                continue;
+            };
+            let mut bounds = range.split('_').map(|bound| bound.parse::<u32>().unwrap());
+            let hi = bounds.next().unwrap();
+            let lo = bounds.next().unwrap_or(field.lo);
+            assert_eq!(
+                (hi, lo),
+                (field.hi, field.lo),
+                "{}.{}: name does not match its range",
+                reg.name,
+                field.name
+            );
+        }
+    }
+}
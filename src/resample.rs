@@ -0,0 +1,187 @@
+//! Resampling of level-sensitive interrupts driven by software device models.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use axerrno::{AxResult, ax_err};
+
+use crate::consts::SGI_NUM;
+use crate::interrupt::{TriggerMode, is_private_intid};
+use crate::vgicv3::Vgicv3;
+
+/// Notified when the guest deactivates a level-sensitive interrupt, so that the device
+/// model driving the line can re-assert it.
+///
+/// When the guest deactivates the interrupt its line is considered deasserted. If the
+/// device still has work for the guest, the resampler calls
+/// [`Vgicv3::set_irq_level`] with `level == true`, which makes the interrupt pending again.
+pub trait IrqResampler: Send + Sync {
+    /// Called after the guest running on `vcpu_id` has deactivated `intid`, without any
+    /// vGIC lock held.
+    fn resample(&self, vcpu_id: usize, intid: u32);
+}
+
+/// A resampler to be notified once the vGIC locks are released.
+pub(crate) type ResampleNotification = (usize, u32, Arc<dyn IrqResampler>);
+
+impl Vgicv3 {
+    /// Returns the resampler of an interrupt, using the bank of `vcpu_id` for private
+    /// interrupts.
+    pub(crate) fn resampler(&self, vcpu_id: usize, intid: u32) -> Option<Arc<dyn IrqResampler>> {
+        let owner = if is_private_intid(intid) { vcpu_id } else { 0 };
+        self.resamplers.lock().get(&(owner, intid)).cloned()
+    }
+
+    /// Registers (or, with `None`, removes) the resampler of a level-sensitive interrupt.
+    ///
+    /// While a resampler is registered, List registers presenting the interrupt request an
+    /// EOI maintenance interrupt, and the resampler is notified each time the guest
+    /// deactivates it.
+    ///
+    /// # Arguments
+    /// * `vcpu_id` - The vCPU whose banked interrupt is targeted, ignored for SPIs
+    /// * `intid` - The interrupt ID, 16 or above
+    /// * `resampler` - The resampler to notify
+    pub fn set_resampler(
+        &self,
+        vcpu_id: usize,
+        intid: u32,
+        resampler: Option<Arc<dyn IrqResampler>>,
+    ) -> AxResult {
+        if self.irq(vcpu_id, intid).is_none() {
+            return ax_err!(InvalidInput, "interrupt not implemented");
+        }
+        if (intid as usize) < SGI_NUM {
+            return ax_err!(InvalidInput, "SGIs cannot be resampled");
+        }
+        let owner = if is_private_intid(intid) { vcpu_id } else { 0 };
+        let mut resamplers = self.resamplers.lock();
+        match resampler {
+            Some(resampler) => resamplers.insert((owner, intid), resampler),
+            None => resamplers.remove(&(owner, intid)),
+        };
+        Ok(())
+    }
+
+    /// Accounts for the deactivation of an interrupt by the guest running on `vcpu_id`:
+    /// a level-sensitive interrupt with a resampler has its line deasserted, and the
+    /// resampler is appended to `notify`.
+    ///
+    /// Must not be called with the lock of the interrupt held.
+    pub(crate) fn resample_eoi(
+        &self,
+        vcpu_id: usize,
+        intid: u32,
+        notify: &mut Vec<ResampleNotification>,
+    ) {
+        let Some(resampler) = self.resampler(vcpu_id, intid) else {
+            return;
+        };
+        let mut irq = self.irq(vcpu_id, intid).unwrap().lock();
+        if irq.trigger != TriggerMode::Level {
+            return;
+        }
+        vgic_trace!("vgicv3: vCPU {vcpu_id} INTID {intid} resampled");
+        irq.line_level = false;
+        notify.push((vcpu_id, intid, resampler));
+    }
+
+    /// Notifies resamplers collected by [`Vgicv3::resample_eoi`].
+    pub(crate) fn notify_resamplers(notify: Vec<ResampleNotification>) {
+        for (vcpu_id, intid, resampler) in notify {
+            resampler.resample(vcpu_id, intid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spin::Mutex;
+
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::mock::{self, MockHost};
+    use crate::regs::gich::{ListRegister, LrState, MisrValue};
+
+    /// Records the interrupts it is notified of.
+    #[derive(Default)]
+    struct RecordingResampler {
+        calls: Mutex<Vec<(usize, u32)>>,
+    }
+
+    impl IrqResampler for RecordingResampler {
+        fn resample(&self, vcpu_id: usize, intid: u32) {
+            self.calls.lock().push((vcpu_id, intid));
+        }
+    }
+
+    /// Creates a vGIC where SPIs 40 and 41 are level-sensitive, asserted, routed to
+    /// `vcpu_id` and resampled, and loads them into the List registers of `vcpu_id`.
+    fn loaded_vgic(vcpu_id: usize) -> (Vgicv3, Arc<MockHost>, Arc<RecordingResampler>) {
+        let config = Vgicv3Config {
+            vcpu_num: 2,
+            ..Default::default()
+        };
+        let (vgic, host) = mock::vgic(config);
+        let resampler = Arc::new(RecordingResampler::default());
+        for intid in [40, 41] {
+            mock::enable_spi(&vgic, intid, 0xa0, vcpu_id);
+            vgic.set_resampler(0, intid, Some(resampler.clone()))
+                .unwrap();
+            vgic.set_irq_level(0, intid, true).unwrap();
+        }
+        vgic.flush_lrs(vcpu_id);
+        for n in 0..2 {
+            let lr = host.lr(n);
+            assert_ne!(lr.pintid & ListRegister::PINTID_EOI, 0);
+            // The guest acknowledges and deactivates both interrupts.
+            host.set_lr(
+                n,
+                ListRegister {
+                    state: LrState::Inactive,
+                    ..lr
+                },
+            );
+        }
+        (vgic, host, resampler)
+    }
+
+    #[test]
+    fn resampled_after_eoi_maintenance() {
+        let (vgic, host, resampler) = loaded_vgic(0);
+        host.regs.lock().misr = MisrValue {
+            eoi: true,
+            ..Default::default()
+        }
+        .into();
+        host.regs.lock().eisr = 0b11;
+        assert_eq!(vgic.handle_maintenance(0).eoi, [40, 41]);
+        assert_eq!(*resampler.calls.lock(), [(0, 40), (0, 41)]);
+
+        // The device behind INTID 40 still has work, the one behind 41 does not.
+        vgic.set_irq_level(0, 40, true).unwrap();
+        vgic.sync_lrs(0);
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+        // The EOI has been reported once.
+        assert_eq!(resampler.calls.lock().len(), 2);
+
+        vgic.flush_lrs(0);
+        assert_eq!(host.lr(0).vintid, 40);
+        assert_eq!(host.lr(0).state, LrState::Pending);
+        assert_eq!(host.lr(1).state, LrState::Inactive);
+    }
+
+    #[test]
+    fn resampled_on_sync_without_maintenance() {
+        let (vgic, host, resampler) = loaded_vgic(1);
+        vgic.sync_lrs(1);
+        assert_eq!(*resampler.calls.lock(), [(1, 40), (1, 41)]);
+        assert!(mock::ap_list(&vgic, 1).is_empty());
+
+        vgic.set_irq_level(0, 40, true).unwrap();
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+        vgic.flush_lrs(1);
+        assert_eq!(host.lr(0).vintid, 40);
+        assert_eq!(host.lr(1).state, LrState::Inactive);
+    }
+}
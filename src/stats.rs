@@ -0,0 +1,180 @@
+//! Per-interrupt statistics, enabled with the `stats` feature.
+
+#[cfg(feature = "stats")]
+use core::fmt::{self, Write};
+
+use crate::interrupt::VirtIrq;
+#[cfg(feature = "stats")]
+use crate::vgicv3::Vgicv3;
+
+/// Counters of a single interrupt (or of one bank of an SGI or PPI).
+#[cfg(feature = "stats")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IrqStats {
+    /// Times the interrupt was made pending by a wired edge or level, an MSI or an SGI.
+    pub injected: u64,
+    /// Times the interrupt was written to a List register.
+    pub lr_loads: u64,
+    /// Times the guest completed the interrupt, leaving its List register empty.
+    pub eois: u64,
+    /// Maintenance interrupts raised for the interrupt: the EOI maintenance interrupt
+    /// requested by its List register, or its deactivation by the guest while it was not
+    /// in a List register.
+    pub maintenance: u64,
+    /// Times the interrupt was left out of the List registers because all of them held
+    /// higher priority interrupts.
+    pub lr_overflows: u64,
+}
+
+impl VirtIrq {
+    /// Counts an injection.
+    pub fn count_injection(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.injected += 1;
+        }
+    }
+
+    /// Counts a List register load.
+    pub fn count_lr_load(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.lr_loads += 1;
+        }
+    }
+
+    /// Counts a completion by the guest.
+    pub fn count_eoi(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.eois += 1;
+        }
+    }
+
+    /// Counts a maintenance interrupt raised for the interrupt.
+    pub fn count_maintenance(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.maintenance += 1;
+        }
+    }
+
+    /// Counts a flush that left the interrupt out of the List registers.
+    #[cfg(feature = "stats")]
+    pub fn count_lr_overflow(&mut self) {
+        self.stats.lr_overflows += 1;
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Vgicv3 {
+    /// Returns the counters of an interrupt, using the bank of `vcpu_id` for SGIs and PPIs.
+    pub fn irq_stats(&self, vcpu_id: usize, intid: u32) -> Option<IrqStats> {
+        self.irq(vcpu_id, intid).map(|irq| irq.lock().stats)
+    }
+
+    /// Writes a table of the interrupts that have been injected, in the spirit of
+    /// `/proc/interrupts`.
+    ///
+    /// SGIs and PPIs have one line per vCPU bank, SPIs a single line.
+    pub fn dump_stats(&self, w: &mut dyn Write) -> fmt::Result {
+        writeln!(
+            w,
+            "{:>6} {:>5} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            "INTID", "VCPU", "INJECTED", "LR_LOADS", "EOIS", "MAINTENANCE", "OVERFLOWS"
+        )?;
+        let mut row = |vcpu: Option<usize>, irq: &VirtIrq| -> fmt::Result {
+            if irq.stats == IrqStats::default() {
+                return Ok(());
+            }
+            write!(w, "{:>6} ", irq.intid)?;
+            match vcpu {
+                Some(vcpu_id) => write!(w, "{vcpu_id:>5}")?,
+                None => write!(w, "{:>5}", "-")?,
+            }
+            let stats = &irq.stats;
+            writeln!(
+                w,
+                " {:>12} {:>12} {:>12} {:>12} {:>12}",
+                stats.injected, stats.lr_loads, stats.eois, stats.maintenance, stats.lr_overflows
+            )
+        };
+        for (vcpu_id, cpu) in self.cpus.iter().enumerate() {
+            for irq in cpu.private.iter().chain(&cpu.eppis) {
+                row(Some(vcpu_id), &irq.lock().clone())?;
+            }
+        }
+        for irq in self.spis.iter().chain(&self.espis) {
+            row(None, &irq.lock().clone())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+
+    use crate::config::Vgicv3Config;
+    use crate::mock;
+    use crate::regs::gich::{GICH_MISR, ListRegister, LrState};
+    use crate::resample::IrqResampler;
+
+    struct NopResampler;
+
+    impl IrqResampler for NopResampler {
+        fn resample(&self, _vcpu_id: usize, _intid: u32) {}
+    }
+
+    #[test]
+    fn lr_overflows_are_counted_per_interrupt() {
+        let (vgic, _host) = mock::vgic(Vgicv3Config::default());
+        for (n, intid) in (40..45).enumerate() {
+            mock::enable_spi(&vgic, intid, 0x10 * n as u8, 0);
+            vgic.set_irq_level(0, intid, true).unwrap();
+        }
+        vgic.flush_lrs(0);
+
+        let overflows: Vec<u64> = (40..45)
+            .map(|intid| vgic.irq_stats(0, intid).unwrap().lr_overflows)
+            .collect();
+        assert_eq!(overflows, [0, 0, 0, 0, 1]);
+        assert_eq!(vgic.irq_stats(0, 40).unwrap().lr_loads, 1);
+    }
+
+    #[test]
+    fn eoi_maintenance_is_counted_per_interrupt() {
+        let (vgic, host) = mock::vgic(Vgicv3Config::default());
+        for intid in [40, 41] {
+            mock::enable_spi(&vgic, intid, 0xa0, 0);
+            vgic.set_resampler(0, intid, Some(Arc::new(NopResampler)))
+                .unwrap();
+            vgic.set_irq_level(0, intid, true).unwrap();
+        }
+        vgic.flush_lrs(0);
+
+        // The guest completes the interrupt in LR1.
+        let lr = host.lr(1);
+        assert_ne!(lr.pintid & ListRegister::PINTID_EOI, 0);
+        host.set_lr(
+            1,
+            ListRegister {
+                state: LrState::Inactive,
+                ..lr
+            },
+        );
+        {
+            let mut regs = host.regs.lock();
+            regs.misr = GICH_MISR::EOI::SET.value;
+            regs.eisr = 1 << 1;
+        }
+        let actions = vgic.handle_maintenance(0);
+        assert_eq!(actions.eoi, [lr.vintid]);
+
+        let maintenance = |intid| vgic.irq_stats(0, intid).unwrap().maintenance;
+        assert_eq!(maintenance(lr.vintid), 1);
+        assert_eq!(maintenance(if lr.vintid == 40 { 41 } else { 40 }), 0);
+    }
+}
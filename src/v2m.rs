@@ -0,0 +1,145 @@
+//! GICv2m-style MSI frame.
+
+use alloc::sync::Arc;
+
+use axaddrspace::GuestPhysAddr;
+use axerrno::{AxResult, ax_err};
+use log::warn;
+
+use crate::consts::*;
+use crate::vgicv3::Vgicv3;
+
+/// Emulated GICv2m MSI frame.
+///
+/// Guests without an ITS driver signal MSIs by writing an SPI number to `MSI_SETSPI_NS`.
+/// Each write makes the SPI pending in the distributor of the [`Vgicv3`] as an edge, so the
+/// SPIs of the frame should be configured as edge-triggered by the guest.
+pub struct Gicv2mFrame {
+    pub(crate) vgic: Arc<Vgicv3>,
+    pub(crate) base: GuestPhysAddr,
+    pub(crate) spi_base: u32,
+    pub(crate) spi_num: u32,
+}
+
+impl Gicv2mFrame {
+    /// Creates an MSI frame at `base` that forwards to the `spi_num` SPIs from `spi_base`.
+    ///
+    /// # Returns
+    /// - `Ok(Gicv2mFrame)` on success
+    /// - `Err(AxError)` if the SPI range is empty or not implemented by the distributor
+    pub fn new(
+        vgic: Arc<Vgicv3>,
+        base: GuestPhysAddr,
+        spi_base: u32,
+        spi_num: u32,
+    ) -> AxResult<Self> {
+        let end = spi_base as usize + spi_num as usize;
+        if spi_num == 0
+            || (spi_base as usize) < SPI_ID_BASE
+            || end > vgic.config.irq_num().min(SPI_ID_MAX)
+            || spi_num as usize > V2M_SPI_NUM_MAX
+        {
+            return ax_err!(InvalidInput, "invalid SPI range for the MSI frame");
+        }
+        Ok(Self {
+            vgic,
+            base,
+            spi_base,
+            spi_num,
+        })
+    }
+
+    /// Reads the 32-bit register at `offset` within the frame.
+    pub(crate) fn handle_read32(&self, offset: usize) -> AxResult<usize> {
+        let value = match offset & !0x3 {
+            V2M_MSI_TYPER => (self.spi_base << 16) | self.spi_num,
+            V2M_MSI_IIDR => V2M_IIDR,
+            V2M_MSI_SETSPI_NS => 0,
+            _ => {
+                warn!("vgicv3: read of unimplemented MSI frame register {offset:#x}");
+                0
+            }
+        };
+        Ok(value as usize)
+    }
+
+    /// Writes the 32-bit register at `offset` within the frame.
+    pub(crate) fn handle_write32(&self, offset: usize, value: usize) {
+        match offset & !0x3 {
+            V2M_MSI_SETSPI_NS => {
+                let intid = value as u32 & GICD_SETSPI_INTID_MASK;
+                if !(self.spi_base..self.spi_base + self.spi_num).contains(&intid) {
+                    warn!("vgicv3: MSI frame write for SPI {intid} outside the frame");
+                    return;
+                }
+                // The range was validated at creation, so the SPI is implemented.
+                let _ = self.vgic.inject_spi_edge(intid);
+            }
+            _ => warn!("vgicv3: write of unimplemented MSI frame register {offset:#x}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::mock;
+
+    /// Creates a vGIC with SPIs 32 to 95.
+    fn vgic() -> Arc<Vgicv3> {
+        let config = Vgicv3Config {
+            spi_num: 64,
+            ..Default::default()
+        };
+        Arc::new(mock::vgic(config).0)
+    }
+
+    fn frame(vgic: &Arc<Vgicv3>, spi_base: u32, spi_num: u32) -> AxResult<Gicv2mFrame> {
+        Gicv2mFrame::new(vgic.clone(), 0x801_0000.into(), spi_base, spi_num)
+    }
+
+    #[test]
+    fn frame_must_cover_implemented_spis() {
+        let vgic = vgic();
+        assert!(frame(&vgic, 32, 64).is_ok());
+        assert!(frame(&vgic, 95, 1).is_ok());
+        // Empty, starting below the SPIs or ending beyond them.
+        assert!(frame(&vgic, 64, 0).is_err());
+        assert!(frame(&vgic, 16, 32).is_err());
+        assert!(frame(&vgic, 31, 1).is_err());
+        assert!(frame(&vgic, 64, 33).is_err());
+        assert!(frame(&vgic, 96, 1).is_err());
+    }
+
+    #[test]
+    fn msi_typer_encodes_the_spi_range() {
+        let vgic = vgic();
+        let frame = frame(&vgic, 80, 16).unwrap();
+        // Base SPI in bits [25:16], number of SPIs in bits [9:0].
+        assert_eq!(frame.handle_read32(V2M_MSI_TYPER).unwrap(), 0x0050_0010);
+        assert_eq!(
+            frame.handle_read32(V2M_MSI_IIDR).unwrap(),
+            V2M_IIDR as usize
+        );
+        assert_eq!(frame.handle_read32(V2M_MSI_SETSPI_NS).unwrap(), 0);
+    }
+
+    #[test]
+    fn setspi_only_triggers_spis_of_the_frame() {
+        let vgic = vgic();
+        let frame = frame(&vgic, 80, 16).unwrap();
+        let pending = |intid| vgic.irq(0, intid).unwrap().lock().is_pending();
+
+        for intid in [79, 96, 1023, 40] {
+            frame.handle_write32(V2M_MSI_SETSPI_NS, intid);
+        }
+        assert!(!pending(79) && !pending(40));
+
+        frame.handle_write32(V2M_MSI_SETSPI_NS, 80);
+        frame.handle_write32(V2M_MSI_SETSPI_NS, 95);
+        assert!(pending(80) && pending(95));
+        // The pending state is latched as an edge, even for a level-sensitive SPI.
+        assert!(!vgic.irq(0, 80).unwrap().lock().line_level);
+    }
+}
@@ -0,0 +1,589 @@
+//! Per-vCPU state and List register management.
+
+use alloc::vec::Vec;
+use core::array;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use log::warn;
+use spin::Mutex;
+use tock_registers::LocalRegisterCopy;
+
+use crate::consts::{EPPI_ID_BASE, GICH_LR_INTID_LIMIT, ICC_BPR0_MIN, PRIVATE_IRQ_NUM};
+use crate::interrupt::{IrqGroup, TriggerMode, VirtIrq};
+use crate::regs::gich::{GICH_HCR, GICH_VMCR, ListRegister, LrGroup, LrState};
+use crate::vgicv3::Vgicv3;
+
+/// Virtual CPU interface state of a single vCPU.
+pub(crate) struct VgicCpu {
+    /// Banked SGIs and PPIs.
+    pub private: [Mutex<VirtIrq>; PRIVATE_IRQ_NUM],
+    /// Banked Extended PPIs.
+    pub eppis: Vec<Mutex<VirtIrq>>,
+    /// Interrupts queued on this vCPU and the List registers presenting them, under the
+    /// ap_list lock.
+    pub lists: Mutex<CpuLists>,
+    /// `GICR_WAKER.ProcessorSleep` of the redistributor of this vCPU.
+    pub processor_sleep: AtomicBool,
+    /// Group enables of the CPU interface of this vCPU, bit 0 for Group 0 and bit 1 for
+    /// Group 1, as last observed. Mirrored outside of `lists` for 1-of-N routing, which is
+    /// evaluated under interrupt locks.
+    pub group_enables: AtomicU8,
+}
+
+/// State of a vCPU protected by its ap_list lock.
+pub(crate) struct CpuLists {
+    /// Interrupts that are pending or active on this vCPU and need a List register.
+    pub ap_list: Vec<u32>,
+    /// List register values written by the last flush.
+    pub lrs: Vec<ListRegister>,
+    /// State of the software CPU interface in the `GICH_VMCR` layout: priority mask,
+    /// binary points, EOI mode and group enables.
+    pub vmcr: u32,
+    /// Active priorities of the software CPU interface for Group 0 and Group 1, one bit per
+    /// preemption level as in `GICH_APR0`.
+    pub apr: [u32; 2],
+}
+
+impl VgicCpu {
+    /// Creates the reset state of the CPU interface of `vcpu_id`, with `eppi_num` Extended
+    /// PPIs.
+    pub fn new(vcpu_id: usize, eppi_num: usize) -> Self {
+        let private = array::from_fn(|intid| {
+            let mut irq = VirtIrq::new(intid as u32);
+            irq.targets = 1u8.checked_shl(vcpu_id as u32).unwrap_or(0);
+            Mutex::new(irq)
+        });
+        let eppis = (EPPI_ID_BASE..EPPI_ID_BASE + eppi_num)
+            .map(|intid| Mutex::new(VirtIrq::new(intid as u32)))
+            .collect();
+        Self {
+            private,
+            eppis,
+            lists: Mutex::new(CpuLists {
+                ap_list: Vec::new(),
+                lrs: Vec::new(),
+                vmcr: (GICH_VMCR::VBPR0.val(ICC_BPR0_MIN) + GICH_VMCR::VBPR1.val(ICC_BPR0_MIN + 1))
+                    .value,
+                apr: [0; 2],
+            }),
+            processor_sleep: AtomicBool::new(true),
+            group_enables: AtomicU8::new(0),
+        }
+    }
+}
+
+impl Vgicv3 {
+    /// Loads the interrupts queued on a vCPU into the List registers.
+    ///
+    /// Must be called on the physical CPU that is about to enter `vcpu_id`, with interrupts
+    /// disabled. Interrupts are loaded by priority; if there are more than the host
+    /// implements, the underflow maintenance interrupt is enabled so that the remaining
+    /// ones are loaded once the guest has handled some. Events posted to the vCPU with
+    /// [`Vgicv3::post_irq_level`] are applied first.
+    ///
+    /// With the software CPU interface, only the posted events are applied.
+    pub fn flush_lrs(&self, vcpu_id: usize) {
+        let mut kicks = Vec::new();
+        self.drain_injections(vcpu_id, &mut kicks);
+        if self.config.software_cpu_interface {
+            self.kick_vcpus(&kicks);
+            return;
+        }
+
+        let mut lists = self.cpus[vcpu_id].lists.lock();
+        // Sort keys, sampled taking the lock of each interrupt once.
+        let mut queued = Vec::with_capacity(lists.ap_list.len());
+        // Interrupts that cannot be presented stay queued. Configurations with such INTIDs
+        // are rejected by `HostGicCaps::validate`.
+        let mut unlistable = Vec::new();
+        for &intid in &lists.ap_list {
+            if !self.ich_lr && intid as usize >= GICH_LR_INTID_LIMIT {
+                warn!("vgicv3: INTID {intid} cannot be presented through GICH_LR");
+                unlistable.push(intid);
+                continue;
+            }
+            let Some(irq) = self.irq(vcpu_id, intid) else {
+                continue;
+            };
+            let irq = irq.lock();
+            if self.irq_target(vcpu_id, &irq) == Some(vcpu_id) {
+                queued.push((!irq.active, !irq.nmi, irq.priority, intid));
+            }
+        }
+        queued.sort_unstable();
+
+        let mut ap_list = Vec::with_capacity(queued.len());
+        let mut lrs = Vec::new();
+        let mut unlisted_active = false;
+        for &(inactive, _, _, intid) in &queued {
+            if lrs.len() == self.nr_lrs {
+                #[cfg(feature = "stats")]
+                self.irq(vcpu_id, intid).unwrap().lock().count_lr_overflow();
+                ap_list.push(intid);
+                unlisted_active |= !inactive;
+                continue;
+            }
+            let resample = self.resampler(vcpu_id, intid).is_some();
+            let mut irq = self.irq(vcpu_id, intid).unwrap().lock();
+            // The interrupt may have changed since it was sampled.
+            if self.irq_target(vcpu_id, &irq) != Some(vcpu_id) {
+                continue;
+            }
+            let n = lrs.len();
+            let lr = Self::encode_lr(&irq, resample && irq.trigger == TriggerMode::Level);
+            vgic_trace!("vgicv3: vCPU {vcpu_id} LR{n} <- {lr:?}");
+            self.write_list_register(n, lr);
+            irq.count_lr_load();
+            if self.config.nmi {
+                self.host.set_lr_nmi(n, irq.nmi);
+            }
+            lrs.push(lr);
+            ap_list.push(intid);
+        }
+        let cpu_lrs = lrs.len();
+        ap_list.extend(unlistable);
+        // `sync_lrs` forgets the values it has folded back, so clear every List register
+        // left over rather than only those written by the last flush.
+        for n in cpu_lrs..self.nr_lrs {
+            self.clear_list_register(n);
+        }
+
+        let mut hcr = LocalRegisterCopy::<u32, GICH_HCR::Register>::new(self.host.read_hcr());
+        hcr.modify(GICH_HCR::En::Enabled);
+        if ap_list.len() > cpu_lrs {
+            hcr.modify(GICH_HCR::UIE::Enabled);
+        } else {
+            hcr.modify(GICH_HCR::UIE::Disabled);
+        }
+        // Active interrupts left out of the List registers can only be deactivated by the
+        // guest through EOIs counted in GICH_HCR.EOICount.
+        hcr.modify(GICH_HCR::LRENPIE.val(unlisted_active as u32));
+        // Changes of the group enables are trapped, for 1-of-N SPIs to follow them without
+        // waiting for the next exit.
+        let enables = self.cpus[vcpu_id].group_enables.load(Ordering::Acquire);
+        let (group0, group1) = ((enables & 1) as u32, (enables >> 1 & 1) as u32);
+        hcr.modify(
+            GICH_HCR::VGrp0EIE.val(group0 ^ 1)
+                + GICH_HCR::VGrp0DIE.val(group0)
+                + GICH_HCR::VGrp1EIE.val(group1 ^ 1)
+                + GICH_HCR::VGrp1DIE.val(group1),
+        );
+        self.host.write_hcr(hcr.get());
+
+        lists.lrs = lrs;
+        lists.ap_list = ap_list;
+        drop(lists);
+
+        self.kick_vcpus(&kicks);
+    }
+
+    /// Folds the state of the List registers back into the emulated interrupts.
+    ///
+    /// Must be called on the physical CPU that has just exited from `vcpu_id`, before the
+    /// vCPU is scheduled out. Resamplers of interrupts deactivated by the guest and not yet
+    /// reported by [`Vgicv3::handle_maintenance`] are notified, and deactivated interrupts
+    /// still pending are queued on their current target. Does nothing with the software
+    /// CPU interface.
+    pub fn sync_lrs(&self, vcpu_id: usize) {
+        if self.config.software_cpu_interface {
+            return;
+        }
+        let mut notify = Vec::new();
+        let mut kicks = Vec::new();
+        let mut deactivated = Vec::new();
+        let vmcr = LocalRegisterCopy::<u32, GICH_VMCR::Register>::new(self.host.read_vmcr());
+        self.set_group_enables(vcpu_id, vmcr, &mut kicks);
+        let mut lists = self.cpus[vcpu_id].lists.lock();
+
+        for (n, written) in lists.lrs.iter().enumerate() {
+            let lr = self.read_list_register(n);
+            vgic_trace!("vgicv3: vCPU {vcpu_id} LR{n} -> {lr:?}");
+            let intid = written.vintid;
+            let Some(irq) = self.irq(vcpu_id, intid) else {
+                continue;
+            };
+            let mut irq = irq.lock();
+            if lr.state == LrState::Inactive {
+                irq.count_eoi();
+            }
+            if irq.active && !lr.state.is_active() {
+                deactivated.push(intid);
+            }
+            irq.active = lr.state.is_active();
+            if irq.active {
+                irq.active_vcpu = vcpu_id;
+            }
+            if written.state.is_pending() && !lr.state.is_pending() {
+                // The guest has acknowledged the interrupt. For SGIs only the source that
+                // was presented in the List register has been consumed.
+                irq.pending_latch = false;
+                if irq.is_sgi() {
+                    let source = written.pintid & ListRegister::PINTID_CPUID_MASK;
+                    irq.sgi_sources &= !(1 << source);
+                }
+            }
+            drop(irq);
+            // An EOI request still set has not been handled by `handle_maintenance` yet.
+            let eoi = lr.pintid & ListRegister::PINTID_EOI != 0;
+            if lr.state == LrState::Inactive && !lr.hw && eoi {
+                self.resample_eoi(vcpu_id, intid, &mut notify);
+            }
+        }
+        lists.lrs.clear();
+
+        lists.ap_list.retain(|&intid| {
+            self.irq(vcpu_id, intid)
+                .is_some_and(|irq| irq.lock().is_queued())
+        });
+        drop(lists);
+
+        // An SPI rerouted while active can only move to its new target now.
+        for intid in deactivated {
+            self.update_irq(vcpu_id, intid, &mut kicks);
+        }
+        Self::notify_resamplers(notify);
+        self.kick_vcpus(&kicks);
+    }
+
+    /// Records the group enables of the CPU interface of `vcpu_id` from its `GICH_VMCR`
+    /// layout value, re-evaluating 1-of-N SPIs if they have changed.
+    ///
+    /// Must be called without any vGIC lock held. vCPUs that need to be kicked are
+    /// appended to `kicks`.
+    pub(crate) fn set_group_enables(
+        &self,
+        vcpu_id: usize,
+        vmcr: LocalRegisterCopy<u32, GICH_VMCR::Register>,
+        kicks: &mut Vec<usize>,
+    ) {
+        let enables = (vmcr.read(GICH_VMCR::VENG0) | vmcr.read(GICH_VMCR::VENG1) << 1) as u8;
+        let previous = self.cpus[vcpu_id]
+            .group_enables
+            .swap(enables, Ordering::AcqRel);
+        if previous != enables {
+            self.update_irm_spis(kicks);
+        }
+    }
+
+    /// Reads List register `n` of the current physical CPU, as `ICH_LR<n>_EL2` if extended
+    /// INTIDs are implemented and as `GICH_LR<n>` otherwise.
+    pub(crate) fn read_list_register(&self, n: usize) -> ListRegister {
+        if self.ich_lr {
+            self.host.read_ich_lr(n).into()
+        } else {
+            self.host.read_lr(n).into()
+        }
+    }
+
+    /// Writes List register `n` of the current physical CPU, see
+    /// [`Vgicv3::read_list_register`].
+    pub(crate) fn write_list_register(&self, n: usize, lr: ListRegister) {
+        if self.ich_lr {
+            self.host.write_ich_lr(n, lr.into());
+        } else {
+            self.host.write_lr(n, lr.into());
+        }
+    }
+
+    /// Frees List register `n` of the current physical CPU.
+    fn clear_list_register(&self, n: usize) {
+        if self.ich_lr {
+            self.host.write_ich_lr(n, 0);
+        } else {
+            self.host.write_lr(n, 0);
+        }
+    }
+
+    /// Encodes the List register value presenting `irq` to the guest, requesting an EOI
+    /// maintenance interrupt if `resample` is set.
+    fn encode_lr(irq: &VirtIrq, resample: bool) -> ListRegister {
+        let state = match (irq.is_pending(), irq.active) {
+            (true, true) => LrState::ActiveAndPending,
+            (false, true) => LrState::Active,
+            _ => LrState::Pending,
+        };
+        // Secure interrupts are presented to the vCPU as virtual FIQs.
+        let group = if irq.group() == IrqGroup::Group1NonSecure {
+            LrGroup::Group1
+        } else {
+            LrGroup::Group0
+        };
+        // For software SGIs, pINTID[2:0] holds the ID of the requesting CPU.
+        let source = if irq.is_sgi() && irq.sgi_sources != 0 {
+            irq.sgi_sources.trailing_zeros() as u16
+        } else {
+            0
+        };
+        let eoi = if resample {
+            ListRegister::PINTID_EOI
+        } else {
+            0
+        };
+        ListRegister {
+            vintid: irq.intid,
+            pintid: source | eoi,
+            priority: irq.priority,
+            state,
+            group,
+            hw: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::hal::GichOps;
+    use crate::mock::{self, MockHost};
+
+    fn config(vcpu_num: usize) -> Vgicv3Config {
+        Vgicv3Config {
+            vcpu_num,
+            ..Default::default()
+        }
+    }
+
+    fn hcr(host: &MockHost) -> LocalRegisterCopy<u32, GICH_HCR::Register> {
+        LocalRegisterCopy::new(host.read_hcr())
+    }
+
+    #[test]
+    fn inject_flush_sync() {
+        let (vgic, host) = mock::vgic(config(1));
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+
+        vgic.flush_lrs(0);
+        let lr = host.lr(0);
+        assert_eq!(
+            (lr.vintid, lr.priority, lr.state, lr.group),
+            (40, 0xa0, LrState::Pending, LrGroup::Group1)
+        );
+        assert!(hcr(&host).is_set(GICH_HCR::En));
+        assert!(!hcr(&host).is_set(GICH_HCR::UIE));
+
+        // The guest acknowledges the interrupt and the device lowers its line.
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Active,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+        vgic.set_irq_level(0, 40, false).unwrap();
+        assert!(vgic.irq(0, 40).unwrap().lock().active);
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+
+        vgic.flush_lrs(0);
+        assert_eq!(host.lr(0).state, LrState::Active);
+
+        // The guest deactivates it.
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Inactive,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+        assert!(!vgic.irq(0, 40).unwrap().lock().active);
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+    }
+
+    #[test]
+    fn flush_loads_by_priority_and_requests_underflow() {
+        let (vgic, host) = mock::vgic(config(1));
+        let priorities = [(40, 0x80), (41, 0x20), (42, 0xf0), (43, 0x40), (44, 0x10)];
+        for (intid, priority) in priorities {
+            mock::enable_spi(&vgic, intid, priority, 0);
+            vgic.set_irq_level(0, intid, true).unwrap();
+        }
+
+        vgic.flush_lrs(0);
+        let loaded: Vec<u32> = (0..4).map(|n| host.lr(n).vintid).collect();
+        assert_eq!(loaded, [44, 41, 43, 40]);
+        assert!(hcr(&host).is_set(GICH_HCR::UIE));
+        assert_eq!(mock::ap_list(&vgic, 0).len(), 5);
+
+        // Once the guest has consumed one, the last one fits.
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Inactive,
+                ..host.lr(0)
+            },
+        );
+        vgic.sync_lrs(0);
+        vgic.set_irq_level(0, 44, false).unwrap();
+        vgic.flush_lrs(0);
+        let loaded: Vec<u32> = (0..4).map(|n| host.lr(n).vintid).collect();
+        assert_eq!(loaded, [41, 43, 40, 42]);
+        assert!(!hcr(&host).is_set(GICH_HCR::UIE));
+    }
+
+    #[test]
+    fn spi_migrates_between_ap_lists() {
+        let (vgic, host) = mock::vgic(config(2));
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+        host.take_kicks();
+
+        mock::route_spi(&vgic, 40, 1);
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+        assert_eq!(host.take_kicks(), [1]);
+
+        // The stale entry is dropped by the former target without loading it.
+        vgic.flush_lrs(0);
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+        assert!(vgic.cpus[0].lists.lock().lrs.is_empty());
+
+        vgic.flush_lrs(1);
+        assert_eq!(host.lr(0).vintid, 40);
+        assert_eq!(vgic.cpus[1].lists.lock().lrs.len(), 1);
+
+        // Rerouted back by vCPU 1, the pending SPI is queued on vCPU 0 again and only
+        // leaves vCPU 1 once its List registers are synced.
+        host.set_current_vcpu(1);
+        mock::route_spi(&vgic, 40, 0);
+        assert_eq!(host.take_kicks(), [0]);
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+        vgic.sync_lrs(1);
+        vgic.flush_lrs(1);
+        assert!(mock::ap_list(&vgic, 1).is_empty());
+    }
+
+    #[test]
+    fn deasserted_interrupt_leaves_no_stale_list_register() {
+        let (vgic, host) = mock::vgic(config(1));
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        vgic.flush_lrs(0);
+        vgic.sync_lrs(0);
+        vgic.set_irq_level(0, 40, false).unwrap();
+
+        vgic.flush_lrs(0);
+        vgic.sync_lrs(0);
+        vgic.flush_lrs(0);
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+        assert_eq!(host.lr(0).state, LrState::Inactive);
+    }
+
+    #[test]
+    fn active_spi_moves_only_once_deactivated() {
+        let (vgic, host) = mock::vgic(config(2));
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        vgic.flush_lrs(0);
+        let lr = host.lr(0);
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Active,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+
+        // Rerouted while active on vCPU 0, it is not presented to vCPU 1.
+        mock::route_spi(&vgic, 40, 1);
+        assert!(mock::ap_list(&vgic, 1).is_empty());
+        vgic.flush_lrs(1);
+        assert!(vgic.cpus[1].lists.lock().lrs.is_empty());
+        assert_eq!(host.lr(0).state, LrState::Inactive);
+
+        vgic.flush_lrs(0);
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+        assert_eq!(host.lr(0).state, LrState::ActiveAndPending);
+
+        // Once vCPU 0 deactivates it, the line still high makes it pending on vCPU 1.
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Pending,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+        assert_eq!(host.take_kicks(), [1]);
+        vgic.flush_lrs(0);
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+        vgic.flush_lrs(1);
+        assert_eq!(host.lr(0).vintid, 40);
+        assert_eq!(host.lr(0).state, LrState::Pending);
+    }
+
+    #[test]
+    fn extended_spis_use_ich_lrs() {
+        use crate::consts::*;
+
+        let config = Vgicv3Config {
+            espi_num: 32,
+            ..config(1)
+        };
+        let host = MockHost::new(MockHost::default_vtr());
+        assert!(Vgicv3::new(config.clone(), host).is_err());
+        let host = MockHost::with_ich_lr(MockHost::default_vtr());
+        let legacy = Vgicv3Config {
+            legacy_support: true,
+            ..config.clone()
+        };
+        assert!(Vgicv3::new(legacy, host).is_err());
+
+        let host = MockHost::with_ich_lr(MockHost::default_vtr());
+        let vgic = Vgicv3::new(config, host.clone()).unwrap();
+        vgic.handle_write32(
+            GICD_CTLR,
+            (GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS) as usize,
+        );
+        vgic.handle_write32(GICD_IGROUPRNE, 1 << 5);
+        vgic.handle_write32(GICD_ISENABLERNE, 1 << 5);
+        vgic.handle_write8(GICD_IPRIORITYRNE + 5, 0x60);
+        vgic.set_irq_level(0, 4101, true).unwrap();
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.set_irq_level(0, 40, true).unwrap();
+
+        vgic.flush_lrs(0);
+        let lr = host.lr(0);
+        assert_eq!(
+            (lr.vintid, lr.priority, lr.state, lr.group),
+            (4101, 0x60, LrState::Pending, LrGroup::Group1)
+        );
+        assert_eq!(host.regs.lock().ich_lrs[0] & 0xffff_ffff, 4101);
+        assert_eq!(host.lr(1).vintid, 40);
+        assert_eq!(host.regs.lock().lrs, [0; GICH_LR_NUM_MAX]);
+
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Active,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+        assert!(vgic.irq(0, 4101).unwrap().lock().active);
+    }
+
+    #[test]
+    fn posted_injection_is_applied_on_flush() {
+        let (vgic, host) = mock::vgic(config(2));
+        mock::enable_spi(&vgic, 40, 0xa0, 1);
+        host.take_kicks();
+
+        vgic.post_irq_level(1, 40, true).unwrap();
+        vgic.post_irq_level(1, 40, false).unwrap();
+        vgic.post_irq_level(1, 40, true).unwrap();
+        assert!(mock::ap_list(&vgic, 1).is_empty());
+        // A single kick covers the burst.
+        assert_eq!(host.take_kicks(), [1]);
+
+        vgic.flush_lrs(1);
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+        assert_eq!(host.lr(0).vintid, 40);
+        assert_eq!(host.lr(0).state, LrState::Pending);
+    }
+}
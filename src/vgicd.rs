@@ -0,0 +1,1127 @@
+//! Distributor register decode.
+
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+use log::warn;
+use spin::MutexGuard;
+use tock_registers::LocalRegisterCopy;
+
+use crate::config::{GicVersion, SecurityModel};
+use crate::consts::*;
+use crate::interrupt::{IrqGroup, TriggerMode, VirtIrq};
+use crate::regs::{GICD_SGIR, ICC_SGI1R_EL1};
+use crate::vgicv3::{Access, Vgicv3};
+
+/// Returns the offset of `offset` within the register bank starting at `base`, if any.
+fn bank_offset(offset: usize, base: usize, size: usize) -> Option<usize> {
+    (base..base + size).contains(&offset).then(|| offset - base)
+}
+
+/// Maps an offset within the Extended SPI register banks to the corresponding offset in
+/// the regular banks, whose layout they share.
+fn espi_bank_offset(offset: usize) -> Option<usize> {
+    const BANKS: [(usize, usize, usize); 12] = [
+        (GICD_IGROUPRNE, GICD_IGROUPR, GICD_BITMAP_BANK_SIZE),
+        (GICD_ISENABLERNE, GICD_ISENABLER, GICD_BITMAP_BANK_SIZE),
+        (GICD_ICENABLERNE, GICD_ICENABLER, GICD_BITMAP_BANK_SIZE),
+        (GICD_ISPENDRNE, GICD_ISPENDR, GICD_BITMAP_BANK_SIZE),
+        (GICD_ICPENDRNE, GICD_ICPENDR, GICD_BITMAP_BANK_SIZE),
+        (GICD_ISACTIVERNE, GICD_ISACTIVER, GICD_BITMAP_BANK_SIZE),
+        (GICD_ICACTIVERNE, GICD_ICACTIVER, GICD_BITMAP_BANK_SIZE),
+        (GICD_IPRIORITYRNE, GICD_IPRIORITYR, GICD_BYTEMAP_BANK_SIZE),
+        (GICD_ICFGRNE, GICD_ICFGR, GICD_ICFGR_BANK_SIZE),
+        (GICD_IGRPMODRNE, GICD_IGRPMODR, GICD_BITMAP_BANK_SIZE),
+        (GICD_INMIRNE, GICD_INMIR, GICD_BITMAP_BANK_SIZE),
+        (GICD_IROUTERNE, GICD_IROUTER, GICD_IROUTER_BANK_SIZE),
+    ];
+    BANKS
+        .iter()
+        .find_map(|&(ext, base, size)| bank_offset(offset, ext, size).map(|off| base + off))
+}
+
+impl Vgicv3 {
+    /// Reads the distributor register at the word-aligned `offset`.
+    pub(crate) fn gicd_read(&self, access: Access, offset: usize) -> u32 {
+        let (bank, intid_base) = match espi_bank_offset(offset) {
+            Some(bank) => (bank, ESPI_ID_BASE),
+            None => (offset, 0),
+        };
+        if let Some(value) = self.read_irq_bank(access, bank, intid_base) {
+            return value;
+        }
+        match offset {
+            GICD_CTLR => self.gicd_ctlr(access),
+            GICD_TYPER => self.gicd_typer(),
+            GICD_IIDR => VGIC_IIDR,
+            GICD_PIDR2_V2 if self.config.version == GicVersion::V2 => 0x2 << 4,
+            GICD_PIDR2_V3 if self.config.version == GicVersion::V3 => 0x3 << 4,
+            GICD_SGIR => 0,
+            GICD_SETSPI_NSR | GICD_CLRSPI_NSR | GICD_SETSPI_SR | GICD_CLRSPI_SR
+                if self.config.mbis =>
+            {
+                0
+            }
+            _ => {
+                warn!("vgicv3: read of unimplemented GICD register {offset:#x}");
+                0
+            }
+        }
+    }
+
+    /// Reads a register of the per-interrupt banks at `offset`, in the layout of the
+    /// distributor registers covering INTIDs from `intid_base`.
+    ///
+    /// Returns `None` if `offset` is not within one of the banks.
+    pub(crate) fn read_irq_bank(
+        &self,
+        access: Access,
+        offset: usize,
+        intid_base: usize,
+    ) -> Option<u32> {
+        if let Some(off) = bank_offset(offset, GICD_IGROUPR, GICD_BITMAP_BANK_SIZE) {
+            if !access.secure {
+                return Some(0);
+            }
+            return Some(self.read_bitmap(access, intid_base + off * 8, |irq| irq.group1));
+        }
+        if let Some(off) = bank_offset(offset, GICD_ISENABLER, GICD_BITMAP_BANK_SIZE)
+            .or_else(|| bank_offset(offset, GICD_ICENABLER, GICD_BITMAP_BANK_SIZE))
+        {
+            return Some(self.read_bitmap(access, intid_base + off * 8, |irq| irq.enabled));
+        }
+        if let Some(off) = bank_offset(offset, GICD_ISPENDR, GICD_BITMAP_BANK_SIZE)
+            .or_else(|| bank_offset(offset, GICD_ICPENDR, GICD_BITMAP_BANK_SIZE))
+        {
+            return Some(self.read_bitmap(access, intid_base + off * 8, VirtIrq::is_pending));
+        }
+        if let Some(off) = bank_offset(offset, GICD_ISACTIVER, GICD_BITMAP_BANK_SIZE)
+            .or_else(|| bank_offset(offset, GICD_ICACTIVER, GICD_BITMAP_BANK_SIZE))
+        {
+            return Some(self.read_bitmap(access, intid_base + off * 8, |irq| irq.active));
+        }
+        if let Some(off) = bank_offset(offset, GICD_IPRIORITYR, GICD_BYTEMAP_BANK_SIZE) {
+            return Some(self.read_bytemap(access, intid_base + off, |irq| {
+                Self::priority_view(access, irq.priority)
+            }));
+        }
+        if let Some(off) = bank_offset(offset, GICD_ITARGETSR, GICD_BYTEMAP_BANK_SIZE) {
+            if self.affinity_routing(access.secure) {
+                return Some(0);
+            }
+            return Some(self.read_bytemap(access, intid_base + off, |irq| irq.targets));
+        }
+        if let Some(off) = bank_offset(offset, GICD_ICFGR, GICD_ICFGR_BANK_SIZE) {
+            return Some(self.read_icfgr(access, intid_base + off * 4));
+        }
+        if let Some(off) = bank_offset(offset, GICD_IGRPMODR, GICD_BITMAP_BANK_SIZE) {
+            if !self.implements_igrpmodr() || !access.secure {
+                return Some(0);
+            }
+            return Some(self.read_bitmap(access, intid_base + off * 8, |irq| irq.grpmod));
+        }
+        // GICD_INMIR<n> overlaps the GICv2 identification registers, decode it only if NMIs
+        // are implemented.
+        if let Some(off) =
+            bank_offset(offset, GICD_INMIR, GICD_BITMAP_BANK_SIZE).filter(|_| self.config.nmi)
+        {
+            return Some(self.read_bitmap(access, intid_base + off * 8, |irq| irq.nmi));
+        }
+        if let Some(off) = bank_offset(offset, GICD_IROUTER, GICD_IROUTER_BANK_SIZE) {
+            if !self.affinity_routing(access.secure) {
+                return Some(0);
+            }
+            let intid = (intid_base + off / 8) as u32;
+            let Some(irq) = self.lock_accessible_irq(access, intid) else {
+                return Some(0);
+            };
+            return Some((irq.route >> (8 * (off & 0x4))) as u32);
+        }
+        if let Some(off) = bank_offset(offset, GICD_CPENDSGIR, GICD_SGI_PENDING_BANK_SIZE)
+            .or_else(|| bank_offset(offset, GICD_SPENDSGIR, GICD_SGI_PENDING_BANK_SIZE))
+        {
+            if self.affinity_routing(access.secure) {
+                return Some(0);
+            }
+            return Some(self.read_bytemap(access, intid_base + off, |irq| irq.sgi_sources));
+        }
+        None
+    }
+
+    /// Writes the bytes selected by `mask` of the distributor register at the word-aligned
+    /// `offset`.
+    pub(crate) fn gicd_write(
+        &self,
+        access: Access,
+        offset: usize,
+        value: u32,
+        mask: u32,
+        kicks: &mut Vec<usize>,
+    ) {
+        let (bank, intid_base) = match espi_bank_offset(offset) {
+            Some(bank) => (bank, ESPI_ID_BASE),
+            None => (offset, 0),
+        };
+        if self.write_irq_bank(access, bank, intid_base, value, mask, kicks) {
+            return;
+        }
+        match offset {
+            GICD_CTLR => {
+                // Concurrent writers retry, so that none of their changes is lost.
+                let _ = self
+                    .ctlr
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |ctlr| {
+                        Some(self.written_gicd_ctlr(ctlr, access, value, mask))
+                    });
+                self.update_all_irqs(kicks);
+            }
+            GICD_SGIR if !self.affinity_routing(access.secure) => {
+                self.write_sgir(access, value, kicks);
+            }
+            GICD_SETSPI_NSR | GICD_CLRSPI_NSR if self.config.mbis => {
+                let set = offset == GICD_SETSPI_NSR;
+                self.write_setclrspi(access, value, set, false, kicks);
+            }
+            GICD_SETSPI_SR | GICD_CLRSPI_SR if self.config.mbis => {
+                // Only implemented with two security states, and WI for Non-secure accesses.
+                if self.config.security == SecurityModel::TwoSecurityStates && access.secure {
+                    let set = offset == GICD_SETSPI_SR;
+                    self.write_setclrspi(access, value, set, true, kicks);
+                }
+            }
+            _ => {
+                warn!("vgicv3: write of unimplemented GICD register {offset:#x}");
+            }
+        }
+    }
+
+    /// Writes a register of the per-interrupt banks at `offset`, in the layout of the
+    /// distributor registers covering INTIDs from `intid_base`.
+    ///
+    /// Returns `false` if `offset` is not within one of the banks.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn write_irq_bank(
+        &self,
+        access: Access,
+        offset: usize,
+        intid_base: usize,
+        value: u32,
+        mask: u32,
+        kicks: &mut Vec<usize>,
+    ) -> bool {
+        if let Some(off) = bank_offset(offset, GICD_IGROUPR, GICD_BITMAP_BANK_SIZE) {
+            if !access.secure {
+                return true;
+            }
+            self.write_bitmap(access, intid_base + off * 8, mask, kicks, |irq, bit| {
+                irq.group1 = value & bit != 0
+            });
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ISENABLER, GICD_BITMAP_BANK_SIZE) {
+            self.write_bitmap(
+                access,
+                intid_base + off * 8,
+                value & mask,
+                kicks,
+                |irq, _| irq.enabled = true,
+            );
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ICENABLER, GICD_BITMAP_BANK_SIZE) {
+            self.write_bitmap(
+                access,
+                intid_base + off * 8,
+                value & mask,
+                kicks,
+                |irq, _| irq.enabled = irq.is_sgi() && !access.redistributor,
+            );
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ISPENDR, GICD_BITMAP_BANK_SIZE) {
+            self.write_bitmap(
+                access,
+                intid_base + off * 8,
+                value & mask,
+                kicks,
+                |irq, _| {
+                    if !irq.is_sgi() || access.redistributor {
+                        irq.pending_latch = true
+                    }
+                },
+            );
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ICPENDR, GICD_BITMAP_BANK_SIZE) {
+            self.write_bitmap(
+                access,
+                intid_base + off * 8,
+                value & mask,
+                kicks,
+                |irq, _| {
+                    if !irq.is_sgi() || access.redistributor {
+                        irq.pending_latch = false
+                    }
+                },
+            );
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ISACTIVER, GICD_BITMAP_BANK_SIZE) {
+            self.write_bitmap(
+                access,
+                intid_base + off * 8,
+                value & mask,
+                kicks,
+                |irq, _| {
+                    if !irq.active {
+                        irq.active_vcpu = self
+                            .routed_vcpu(access.vcpu_id, irq)
+                            .unwrap_or(access.vcpu_id);
+                    }
+                    irq.active = true
+                },
+            );
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ICACTIVER, GICD_BITMAP_BANK_SIZE) {
+            self.write_bitmap(
+                access,
+                intid_base + off * 8,
+                value & mask,
+                kicks,
+                |irq, _| irq.active = false,
+            );
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_IPRIORITYR, GICD_BYTEMAP_BANK_SIZE) {
+            self.write_bytemap(access, intid_base + off, value, mask, kicks, |irq, byte| {
+                irq.priority = Self::priority_from_view(access, byte)
+            });
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ITARGETSR, GICD_BYTEMAP_BANK_SIZE) {
+            if self.affinity_routing(access.secure) {
+                return true;
+            }
+            let valid = self.gicv2_cpu_mask();
+            self.write_bytemap(access, intid_base + off, value, mask, kicks, |irq, byte| {
+                if !irq.is_private() {
+                    irq.targets = byte & valid
+                }
+            });
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_ICFGR, GICD_ICFGR_BANK_SIZE) {
+            self.write_icfgr(access, intid_base + off * 4, value, mask, kicks);
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_IGRPMODR, GICD_BITMAP_BANK_SIZE) {
+            if !self.implements_igrpmodr() || !access.secure {
+                return true;
+            }
+            self.write_bitmap(access, intid_base + off * 8, mask, kicks, |irq, bit| {
+                irq.grpmod = value & bit != 0
+            });
+            return true;
+        }
+        if let Some(off) =
+            bank_offset(offset, GICD_INMIR, GICD_BITMAP_BANK_SIZE).filter(|_| self.config.nmi)
+        {
+            self.write_bitmap(access, intid_base + off * 8, mask, kicks, |irq, bit| {
+                irq.nmi = value & bit != 0
+            });
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_IROUTER, GICD_IROUTER_BANK_SIZE) {
+            if !self.affinity_routing(access.secure) {
+                return true;
+            }
+            let intid = (intid_base + off / 8) as u32;
+            let shift = 8 * (off & 0x4);
+            let Some(mut irq) = self.lock_accessible_irq(access, intid) else {
+                return true;
+            };
+            let mask = (mask as u64) << shift;
+            let route = (irq.route & !mask) | (((value as u64) << shift) & mask);
+            irq.route = route & (MPIDR_AFFINITY_MASK | GICD_IROUTER_IRM);
+            drop(irq);
+            self.update_irq(access.vcpu_id, intid, kicks);
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_CPENDSGIR, GICD_SGI_PENDING_BANK_SIZE) {
+            if self.affinity_routing(access.secure) {
+                return true;
+            }
+            self.write_bytemap(access, intid_base + off, value, mask, kicks, |irq, byte| {
+                irq.sgi_sources &= !byte
+            });
+            return true;
+        }
+        if let Some(off) = bank_offset(offset, GICD_SPENDSGIR, GICD_SGI_PENDING_BANK_SIZE) {
+            if self.affinity_routing(access.secure) {
+                return true;
+            }
+            let valid = self.gicv2_cpu_mask();
+            self.write_bytemap(access, intid_base + off, value, mask, kicks, |irq, byte| {
+                irq.sgi_sources |= byte & valid
+            });
+            return true;
+        }
+        false
+    }
+
+    /// Generates the SGI requested by a write to `GICD_SGIR`.
+    ///
+    /// The SGI is made pending on every target vCPU with the requester recorded as its
+    /// source, so that each source is acknowledged separately as GICv2 requires.
+    fn write_sgir(&self, access: Access, value: u32, kicks: &mut Vec<usize>) {
+        let requester = access.vcpu_id;
+        if requester >= self.config.vcpu_num {
+            warn!("vgicv3: GICD_SGIR write from invalid vCPU {requester}");
+            return;
+        }
+        let sgir = LocalRegisterCopy::<u32, GICD_SGIR::Register>::new(value);
+        let intid = sgir.read(GICD_SGIR::SGIINTID);
+        let valid = self.gicv2_cpu_mask();
+        let targets = match sgir.read_as_enum(GICD_SGIR::TargetListFilter) {
+            Some(GICD_SGIR::TargetListFilter::Value::ForwardToCPUTargetList) => {
+                sgir.read(GICD_SGIR::CPUTargetList) as u8 & valid
+            }
+            Some(GICD_SGIR::TargetListFilter::Value::ForwardToAllExceptRequester) => {
+                valid & !(1 << requester)
+            }
+            Some(GICD_SGIR::TargetListFilter::Value::ForwardToRequester) => 1 << requester,
+            _ => {
+                warn!("vgicv3: GICD_SGIR write with reserved TargetListFilter");
+                return;
+            }
+        };
+        // With two security states, a Secure write selects the group of the SGI through
+        // NSATT, while a Non-secure write only ever generates Group 1 SGIs.
+        let required_group1 = match self.config.security {
+            SecurityModel::SingleSecurityState => None,
+            SecurityModel::TwoSecurityStates if !access.secure => Some(true),
+            SecurityModel::TwoSecurityStates => Some(sgir.read(GICD_SGIR::NSATT) != 0),
+        };
+
+        for target in (0..self.config.vcpu_num).filter(|t| targets & (1 << t) != 0) {
+            let Some(irq) = self.irq(target, intid) else {
+                continue;
+            };
+            let mut irq = irq.lock();
+            if required_group1.is_some_and(|group1| group1 != irq.group1) {
+                continue;
+            }
+            irq.sgi_sources |= 1 << requester;
+            irq.count_injection();
+            drop(irq);
+            self.update_irq(target, intid, kicks);
+        }
+    }
+
+    /// Generates the SGIs requested by a write to `ICC_SGI1R_EL1`.
+    ///
+    /// Only SGIs configured as Group 1 of the security state of the requester are generated.
+    /// GICv3 SGIs carry no source, so they are latched like any other edge.
+    pub(crate) fn write_sgi1r(&self, access: Access, value: u64, kicks: &mut Vec<usize>) {
+        let sgi1r = LocalRegisterCopy::<u64, ICC_SGI1R_EL1::Register>::new(value);
+        let intid = sgi1r.read(ICC_SGI1R_EL1::INTID) as u32;
+        let group = match self.config.security {
+            SecurityModel::TwoSecurityStates if access.secure => IrqGroup::Group1Secure,
+            _ => IrqGroup::Group1NonSecure,
+        };
+        let all_but_self = sgi1r.read(ICC_SGI1R_EL1::IRM) != 0;
+        let cluster = (sgi1r.read(ICC_SGI1R_EL1::Aff3) << 32)
+            | (sgi1r.read(ICC_SGI1R_EL1::Aff2) << 16)
+            | (sgi1r.read(ICC_SGI1R_EL1::Aff1) << 8);
+        let aff0_base = sgi1r.read(ICC_SGI1R_EL1::RS) * 16;
+        let target_list = sgi1r.read(ICC_SGI1R_EL1::TargetList);
+
+        for target in 0..self.config.vcpu_num {
+            let selected = if all_but_self {
+                target != access.vcpu_id
+            } else {
+                let affinity = self.config.vcpu_affinity(target);
+                let aff0 = affinity & 0xff;
+                affinity & !0xff == cluster
+                    && (aff0_base..aff0_base + 16).contains(&aff0)
+                    && target_list & (1 << (aff0 - aff0_base)) != 0
+            };
+            if !selected {
+                continue;
+            }
+            let Some(irq) = self.irq(target, intid) else {
+                continue;
+            };
+            let mut irq = irq.lock();
+            if irq.group() != group {
+                continue;
+            }
+            irq.count_injection();
+            irq.pending_latch = true;
+            drop(irq);
+            self.update_irq(target, intid, kicks);
+        }
+    }
+
+    /// Handles a write to `GICD_SETSPI_NSR`, `GICD_CLRSPI_NSR` or their Secure variants.
+    ///
+    /// A set asserts a level-sensitive SPI and makes an edge-triggered one pending; a clear
+    /// deasserts a level-sensitive SPI and has no effect on an edge-triggered one, whose
+    /// pending state is only cleared by acknowledgement. The `_NSR` registers only affect
+    /// Non-secure Group 1 SPIs and the `_SR` ones only Secure SPIs, unless there is a single
+    /// security state.
+    fn write_setclrspi(
+        &self,
+        access: Access,
+        value: u32,
+        set: bool,
+        secure_reg: bool,
+        kicks: &mut Vec<usize>,
+    ) {
+        let intid = value & GICD_SETSPI_INTID_MASK;
+        let single = self.config.security == SecurityModel::SingleSecurityState;
+        let Some(irq) = self.irq(access.vcpu_id, intid) else {
+            warn!("vgicv3: message-based SPI write for unimplemented INTID {intid}");
+            return;
+        };
+        let mut irq = irq.lock();
+        if irq.is_private() || !single && secure_reg == (irq.group() == IrqGroup::Group1NonSecure) {
+            return;
+        }
+        if set {
+            irq.count_injection();
+        }
+        match (irq.trigger, set) {
+            (TriggerMode::Level, level) => irq.line_level = level,
+            (TriggerMode::Edge, true) => irq.pending_latch = true,
+            (TriggerMode::Edge, false) => {}
+        }
+        drop(irq);
+        self.update_irq(access.vcpu_id, intid, kicks);
+    }
+
+    /// Returns the mask of valid CPU interfaces in GICv2 target lists.
+    fn gicv2_cpu_mask(&self) -> u8 {
+        ((1u32 << self.config.vcpu_num.min(GICV2_CPU_NUM_MAX)) - 1) as u8
+    }
+
+    /// Returns the enable bits of `GICD_CTLR` implemented in the Secure view.
+    fn gicd_ctlr_enables(&self) -> u32 {
+        match (self.config.version, self.config.security) {
+            (GicVersion::V3, SecurityModel::TwoSecurityStates) => {
+                GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS | GICD_CTLR_ENABLE_GRP1S
+            }
+            _ => GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS,
+        }
+    }
+
+    /// Returns the `GICD_CTLR` value seen by the access.
+    fn gicd_ctlr(&self, access: Access) -> u32 {
+        let ctlr = self.ctlr.load(Ordering::Acquire);
+        let enables = ctlr & self.gicd_ctlr_enables();
+        match self.config.security {
+            SecurityModel::SingleSecurityState => match self.config.version {
+                GicVersion::V2 => enables,
+                GicVersion::V3 => enables | (ctlr & GICD_CTLR_ARE) | GICD_CTLR_DS,
+            },
+            SecurityModel::TwoSecurityStates if access.secure => {
+                enables | (ctlr & (GICD_CTLR_ARE_S | GICD_CTLR_ARE_NS))
+            }
+            SecurityModel::TwoSecurityStates => {
+                // The Non-secure view only exposes EnableGrp1NS: as EnableGrp1A (bit 1) with
+                // affinity routing, as EnableGrp1 (bit 0) without. ARE_NS appears at bit 4.
+                let grp1 = (ctlr & GICD_CTLR_ENABLE_GRP1NS != 0) as u32;
+                if ctlr & GICD_CTLR_ARE_NS != 0 {
+                    (grp1 << 1) | GICD_CTLR_ARE
+                } else {
+                    grp1
+                }
+            }
+        }
+    }
+
+    /// Returns `ctlr` updated from the bytes selected by `mask` of a write to `GICD_CTLR`.
+    ///
+    /// The affinity routing enables are RAO/WI without legacy support. Otherwise they can
+    /// only be changed while all groups are disabled, as changing them with a group enabled
+    /// is UNPREDICTABLE.
+    fn written_gicd_ctlr(&self, ctlr: u32, access: Access, value: u32, mask: u32) -> u32 {
+        let are_writable = self.config.version == GicVersion::V3
+            && self.config.supports_legacy()
+            && ctlr & self.gicd_ctlr_enables() == 0;
+        let (value, mask) = match self.config.security {
+            SecurityModel::SingleSecurityState => {
+                let are = if are_writable { GICD_CTLR_ARE } else { 0 };
+                (value, mask & (self.gicd_ctlr_enables() | are))
+            }
+            SecurityModel::TwoSecurityStates if access.secure => {
+                let are = if are_writable {
+                    GICD_CTLR_ARE_S | GICD_CTLR_ARE_NS
+                } else {
+                    0
+                };
+                (value, mask & (self.gicd_ctlr_enables() | are))
+            }
+            SecurityModel::TwoSecurityStates => {
+                // Translate the Non-secure view to the Secure layout.
+                let bit = if ctlr & GICD_CTLR_ARE_NS != 0 { 1 } else { 0 };
+                let to_secure = |v: u32| {
+                    let are_ns = if are_writable && v & GICD_CTLR_ARE != 0 {
+                        GICD_CTLR_ARE_NS
+                    } else {
+                        0
+                    };
+                    (((v >> bit) & 1) << 1) | are_ns
+                };
+                (to_secure(value), to_secure(mask))
+            }
+        };
+        (ctlr & !mask) | (value & mask)
+    }
+
+    /// Returns the `GICD_TYPER` value seen by the guest.
+    fn gicd_typer(&self) -> u32 {
+        let it_lines = (self.config.irq_num() / 32 - 1) as u32;
+        let cpu_number = (self.config.vcpu_num.min(GICV2_CPU_NUM_MAX) - 1) as u32;
+        let mut typer = it_lines | (cpu_number << 5);
+        if self.config.security == SecurityModel::TwoSecurityStates {
+            typer |= GICD_TYPER_SECURITY_EXTN;
+        }
+        if self.config.nmi {
+            typer |= GICD_TYPER_NMI;
+        }
+        if self.config.mbis {
+            typer |= GICD_TYPER_MBIS;
+        }
+        if self.config.aff3 != 0 {
+            typer |= GICD_TYPER_A3V;
+        }
+        if self.config.espi_num != 0 {
+            // ESPI_range: the largest Extended SPI is 32 * (ESPI_range + 1) + 4095.
+            typer |= GICD_TYPER_ESPI | ((self.config.espi_num / 32 - 1) as u32) << 27;
+        }
+        // IDbits, one less than the number of interrupt ID bits. LPIs are not supported.
+        let id_bits = self.config.intid_bits();
+        match self.config.version {
+            GicVersion::V2 => typer,
+            GicVersion::V3 => typer | ((id_bits - 1) << 19),
+        }
+    }
+
+    /// Returns whether `GICD_IGRPMODR<n>` is implemented, i.e. whether interrupts can be
+    /// assigned to Secure Group 1.
+    fn implements_igrpmodr(&self) -> bool {
+        self.config.version == GicVersion::V3
+            && self.config.security == SecurityModel::TwoSecurityStates
+    }
+
+    /// Locks interrupt `intid` if its registers can be accessed by `access`.
+    ///
+    /// With affinity routing, SGIs and PPIs are configured through the redistributors and
+    /// their distributor registers are RAZ/WI. Without it, they are banked in the distributor
+    /// and the redistributor registers are RAZ/WI. Non-secure accesses cannot observe or
+    /// modify Secure interrupts.
+    pub(crate) fn lock_accessible_irq(
+        &self,
+        access: Access,
+        intid: u32,
+    ) -> Option<MutexGuard<'_, VirtIrq>> {
+        let irq = self.irq(access.vcpu_id, intid)?.lock();
+        let banked_in_gicr = irq.is_private() && self.affinity_routing(access.secure);
+        if access.redistributor != banked_in_gicr {
+            return None;
+        }
+        (access.secure || irq.group() == IrqGroup::Group1NonSecure).then_some(irq)
+    }
+
+    /// Converts a stored priority to the value seen by the access.
+    ///
+    /// Non-secure accesses with two security states see the Non-secure half of the priority
+    /// range shifted left by one bit.
+    fn priority_view(access: Access, priority: u8) -> u8 {
+        if access.secure {
+            priority
+        } else {
+            priority << 1
+        }
+    }
+
+    /// Converts a priority written by the access to the stored value.
+    fn priority_from_view(access: Access, byte: u8) -> u8 {
+        let priority = if access.secure {
+            byte
+        } else {
+            (byte >> 1) | 0x80
+        };
+        priority & GIC_PRIORITY_MASK
+    }
+
+    /// Reads a one-bit-per-interrupt register covering 32 interrupts from `first`.
+    fn read_bitmap(&self, access: Access, first: usize, f: impl Fn(&VirtIrq) -> bool) -> u32 {
+        (0..32).fold(0, |acc, i| {
+            let intid = (first + i) as u32;
+            match self.lock_accessible_irq(access, intid) {
+                Some(irq) if f(&irq) => acc | (1 << i),
+                _ => acc,
+            }
+        })
+    }
+
+    /// Applies `f` to every interrupt whose bit is set in `bits`, starting from `first`.
+    fn write_bitmap(
+        &self,
+        access: Access,
+        first: usize,
+        bits: u32,
+        kicks: &mut Vec<usize>,
+        f: impl Fn(&mut VirtIrq, u32),
+    ) {
+        for i in (0..32).filter(|i| bits & (1 << i) != 0) {
+            let intid = (first + i) as u32;
+            let Some(mut irq) = self.lock_accessible_irq(access, intid) else {
+                continue;
+            };
+            f(&mut irq, 1 << i);
+            drop(irq);
+            self.update_irq(access.vcpu_id, intid, kicks);
+        }
+    }
+
+    /// Reads a one-byte-per-interrupt register covering 4 interrupts from `first`.
+    fn read_bytemap(&self, access: Access, first: usize, f: impl Fn(&VirtIrq) -> u8) -> u32 {
+        (0..4).fold(0, |acc, i| {
+            let intid = (first + i) as u32;
+            match self.lock_accessible_irq(access, intid) {
+                Some(irq) => acc | ((f(&irq) as u32) << (8 * i)),
+                None => acc,
+            }
+        })
+    }
+
+    /// Applies `f` to every interrupt whose byte is selected by `mask`, starting from `first`.
+    #[allow(clippy::too_many_arguments)]
+    fn write_bytemap(
+        &self,
+        access: Access,
+        first: usize,
+        value: u32,
+        mask: u32,
+        kicks: &mut Vec<usize>,
+        f: impl Fn(&mut VirtIrq, u8),
+    ) {
+        for i in (0..4).filter(|i| mask & (0xff << (8 * i)) != 0) {
+            let intid = (first + i) as u32;
+            let Some(mut irq) = self.lock_accessible_irq(access, intid) else {
+                continue;
+            };
+            f(&mut irq, (value >> (8 * i)) as u8);
+            drop(irq);
+            self.update_irq(access.vcpu_id, intid, kicks);
+        }
+    }
+
+    /// Reads a `GICD_ICFGR<n>` register covering 16 interrupts from `first`.
+    fn read_icfgr(&self, access: Access, first: usize) -> u32 {
+        (0..16).fold(0, |acc, i| {
+            let intid = (first + i) as u32;
+            match self.lock_accessible_irq(access, intid) {
+                Some(irq) if irq.trigger == TriggerMode::Edge => acc | (0b10 << (2 * i)),
+                _ => acc,
+            }
+        })
+    }
+
+    /// Writes a `GICD_ICFGR<n>` register covering 16 interrupts from `first`.
+    ///
+    /// The configuration of SGIs is fixed to edge-triggered.
+    fn write_icfgr(
+        &self,
+        access: Access,
+        first: usize,
+        value: u32,
+        mask: u32,
+        kicks: &mut Vec<usize>,
+    ) {
+        for i in (0..16).filter(|i| mask & (0b10 << (2 * i)) != 0) {
+            let intid = (first + i) as u32;
+            if intid < SGI_NUM as u32 {
+                continue;
+            }
+            let Some(mut irq) = self.lock_accessible_irq(access, intid) else {
+                continue;
+            };
+            irq.trigger = if value & (0b10 << (2 * i)) != 0 {
+                TriggerMode::Edge
+            } else {
+                TriggerMode::Level
+            };
+            drop(irq);
+            self.update_irq(access.vcpu_id, intid, kicks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use super::*;
+    use crate::config::Vgicv3Config;
+    use crate::mock;
+    use crate::regs::gich::{ListRegister, LrState};
+
+    fn access(vcpu_id: usize, secure: bool) -> Access {
+        Access {
+            vcpu_id,
+            secure,
+            redistributor: false,
+        }
+    }
+
+    /// Writes a distributor register, returning the vCPUs to kick.
+    fn write(vgic: &Vgicv3, access: Access, offset: usize, value: u32) -> Vec<usize> {
+        let mut kicks = Vec::new();
+        vgic.gicd_write(access, offset, value, u32::MAX, &mut kicks);
+        kicks
+    }
+
+    /// Creates a vGIC on a mock host implementing legacy operation.
+    fn new_vgic(config: Vgicv3Config) -> (Vgicv3, Arc<mock::MockHost>) {
+        let host = mock::MockHost::with_gicv(mock::MockHost::default_vtr());
+        let vgic = Vgicv3::new(config, host.clone()).unwrap();
+        (vgic, host)
+    }
+
+    /// Creates a vGIC with two security states and the given legacy support.
+    fn secure_vgic(legacy_support: bool) -> Vgicv3 {
+        new_vgic(Vgicv3Config {
+            security: SecurityModel::TwoSecurityStates,
+            legacy_support,
+            ..Default::default()
+        })
+        .0
+    }
+
+    /// Creates a vGIC of `vcpu_num` vCPUs with affinity routing disabled and both groups
+    /// enabled.
+    fn legacy_vgic(vcpu_num: usize) -> (Vgicv3, Arc<mock::MockHost>) {
+        let (vgic, host) = new_vgic(Vgicv3Config {
+            vcpu_num,
+            legacy_support: true,
+            ..Default::default()
+        });
+        let enables = GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS;
+        write(&vgic, access(0, true), GICD_CTLR, enables);
+        (vgic, host)
+    }
+
+    /// Returns the `GICD_SGIR` value generating `intid` with the given target list filter.
+    fn sgir(filter: u32, targets: u8, intid: u32) -> u32 {
+        (filter << 24) | ((targets as u32) << 16) | intid
+    }
+
+    fn sgi_sources(vgic: &Vgicv3, vcpu_id: usize, intid: u32) -> u8 {
+        vgic.irq(vcpu_id, intid).unwrap().lock().sgi_sources
+    }
+
+    #[test]
+    fn sgir_target_list_filters() {
+        let (vgic, _host) = legacy_vgic(4);
+
+        // Filter 0: the CPU target list, restricted to the implemented vCPUs.
+        let kicks = write(&vgic, access(1, true), GICD_SGIR, sgir(0, 0xf5, 3));
+        assert_eq!(kicks, [0, 2]);
+        let sources: Vec<u8> = (0..4).map(|t| sgi_sources(&vgic, t, 3)).collect();
+        assert_eq!(sources, [0b10, 0, 0b10, 0]);
+
+        // Filter 1: every vCPU but the requester, whatever the target list.
+        let kicks = write(&vgic, access(2, true), GICD_SGIR, sgir(1, 0x04, 4));
+        assert_eq!(kicks, [0, 1, 3]);
+        let sources: Vec<u8> = (0..4).map(|t| sgi_sources(&vgic, t, 4)).collect();
+        assert_eq!(sources, [0b100, 0b100, 0, 0b100]);
+
+        // Filter 2: the requester only.
+        let kicks = write(&vgic, access(3, true), GICD_SGIR, sgir(2, 0x01, 5));
+        assert_eq!(kicks, [3]);
+        let sources: Vec<u8> = (0..4).map(|t| sgi_sources(&vgic, t, 5)).collect();
+        assert_eq!(sources, [0, 0, 0, 0b1000]);
+
+        // Filter 3 is reserved.
+        assert!(write(&vgic, access(0, true), GICD_SGIR, sgir(3, 0x0f, 6)).is_empty());
+        assert!((0..4).all(|t| sgi_sources(&vgic, t, 6) == 0));
+    }
+
+    #[test]
+    fn sgir_with_empty_target_list_generates_nothing() {
+        let (vgic, _host) = legacy_vgic(2);
+        assert!(write(&vgic, access(0, true), GICD_SGIR, sgir(0, 0, 1)).is_empty());
+        // Targets beyond the implemented vCPUs are ignored.
+        assert!(write(&vgic, access(0, true), GICD_SGIR, sgir(0, 0xfc, 1)).is_empty());
+        assert!((0..2).all(|t| sgi_sources(&vgic, t, 1) == 0));
+        assert!(mock::ap_list(&vgic, 0).is_empty() && mock::ap_list(&vgic, 1).is_empty());
+    }
+
+    #[test]
+    fn sgir_is_ignored_with_affinity_routing() {
+        let (vgic, _host) = mock::vgic(Vgicv3Config {
+            vcpu_num: 2,
+            ..Default::default()
+        });
+        assert!(write(&vgic, access(0, true), GICD_SGIR, sgir(0, 0b10, 1)).is_empty());
+        assert_eq!(sgi_sources(&vgic, 1, 1), 0);
+    }
+
+    #[test]
+    fn acknowledge_clears_only_the_presented_source() {
+        let (vgic, host) = legacy_vgic(3);
+        write(&vgic, access(2, true), GICD_SGIR, sgir(0, 0b001, 7));
+        write(&vgic, access(1, true), GICD_SGIR, sgir(0, 0b001, 7));
+        assert_eq!(sgi_sources(&vgic, 0, 7), 0b110);
+
+        // The lowest source is presented first, in pINTID[2:0].
+        vgic.flush_lrs(0);
+        let lr = host.lr(0);
+        assert_eq!((lr.vintid, lr.pintid, lr.state), (7, 1, LrState::Pending));
+
+        // The guest acknowledges and completes it: only source 1 is consumed.
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Inactive,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+        assert_eq!(sgi_sources(&vgic, 0, 7), 0b100);
+        assert_eq!(
+            vgic.gicd_read(access(0, true), GICD_SPENDSGIR + 4) >> 24,
+            0b100
+        );
+
+        vgic.flush_lrs(0);
+        let lr = host.lr(0);
+        assert_eq!((lr.vintid, lr.pintid, lr.state), (7, 2, LrState::Pending));
+        host.set_lr(
+            0,
+            ListRegister {
+                state: LrState::Inactive,
+                ..lr
+            },
+        );
+        vgic.sync_lrs(0);
+        assert_eq!(sgi_sources(&vgic, 0, 7), 0);
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+    }
+
+    #[test]
+    fn non_secure_ctlr_view_with_affinity_routing() {
+        let vgic = secure_vgic(false);
+        let (secure, non_secure) = (access(0, true), access(0, false));
+        let all = GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS | GICD_CTLR_ENABLE_GRP1S;
+        write(&vgic, secure, GICD_CTLR, all);
+        let are = GICD_CTLR_ARE_S | GICD_CTLR_ARE_NS;
+        assert_eq!(vgic.gicd_read(secure, GICD_CTLR), all | are);
+        // EnableGrp1NS appears as EnableGrp1A at bit 1, and ARE_NS at bit 4.
+        assert_eq!(vgic.gicd_read(non_secure, GICD_CTLR), 0b10 | GICD_CTLR_ARE);
+
+        // Bit 0 of the Non-secure view is RES0, and the Secure enables are not reachable.
+        write(&vgic, non_secure, GICD_CTLR, 0b01);
+        assert_eq!(
+            vgic.gicd_read(secure, GICD_CTLR),
+            GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1S | are
+        );
+        assert_eq!(vgic.gicd_read(non_secure, GICD_CTLR), GICD_CTLR_ARE);
+        write(&vgic, non_secure, GICD_CTLR, 0b10);
+        assert_eq!(vgic.gicd_read(secure, GICD_CTLR), all | are);
+    }
+
+    #[test]
+    fn non_secure_ctlr_view_without_affinity_routing() {
+        let vgic = secure_vgic(true);
+        let (secure, non_secure) = (access(0, true), access(0, false));
+        write(&vgic, secure, GICD_CTLR, GICD_CTLR_ENABLE_GRP1NS);
+        // EnableGrp1NS appears as EnableGrp1 at bit 0.
+        assert_eq!(vgic.gicd_read(non_secure, GICD_CTLR), 0b01);
+        write(&vgic, non_secure, GICD_CTLR, 0);
+        assert_eq!(vgic.gicd_read(secure, GICD_CTLR), 0);
+
+        // With all groups disabled, the Non-secure side can set ARE_NS only.
+        write(&vgic, non_secure, GICD_CTLR, GICD_CTLR_ARE);
+        assert_eq!(vgic.gicd_read(secure, GICD_CTLR), GICD_CTLR_ARE_NS);
+        assert!(vgic.affinity_routing(false) && !vgic.affinity_routing(true));
+        write(&vgic, non_secure, GICD_CTLR, GICD_CTLR_ARE | 0b10);
+        assert_eq!(vgic.gicd_read(non_secure, GICD_CTLR), GICD_CTLR_ARE | 0b10);
+        assert_eq!(
+            vgic.gicd_read(secure, GICD_CTLR),
+            GICD_CTLR_ARE_NS | GICD_CTLR_ENABLE_GRP1NS
+        );
+        // ARE_NS is locked while the group is enabled.
+        write(&vgic, non_secure, GICD_CTLR, 0b10);
+        assert!(vgic.affinity_routing(false));
+    }
+
+    #[test]
+    fn single_security_state_ctlr_has_ds_and_no_secure_group() {
+        let (vgic, _host) = new_vgic(Vgicv3Config::default());
+        let all = GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ENABLE_GRP1NS | GICD_CTLR_ENABLE_GRP1S;
+        write(&vgic, access(0, true), GICD_CTLR, all);
+        assert_eq!(
+            vgic.gicd_read(access(0, true), GICD_CTLR),
+            GICD_CTLR_DS | GICD_CTLR_ARE | GICD_CTLR_ENABLE_GRP1NS | GICD_CTLR_ENABLE_GRP0
+        );
+
+        // With two security states DS is clear, and EnableGrp1S is implemented.
+        let vgic = secure_vgic(false);
+        write(&vgic, access(0, true), GICD_CTLR, all);
+        let ctlr = vgic.gicd_read(access(0, true), GICD_CTLR);
+        assert_eq!(ctlr & (GICD_CTLR_DS | all), all);
+    }
+
+    #[test]
+    fn igrpmodr_and_igroupr_select_the_group() {
+        let vgic = secure_vgic(false);
+        let (secure, non_secure) = (access(0, true), access(0, false));
+        // SPIs 40..44 take IGROUPR:IGRPMODR = 0:0, 0:1, 1:0 and 1:1.
+        write(&vgic, secure, GICD_IGROUPR + 4, 0b1100 << 8);
+        write(&vgic, secure, GICD_IGRPMODR + 4, 0b1010 << 8);
+        write(&vgic, secure, GICD_ISENABLER + 4, 0b1111 << 8);
+        let groups: Vec<IrqGroup> = (40..44)
+            .map(|intid| vgic.irq(0, intid).unwrap().lock().group())
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                IrqGroup::Group0,
+                IrqGroup::Group1Secure,
+                IrqGroup::Group1NonSecure,
+                IrqGroup::Group1NonSecure,
+            ]
+        );
+
+        // Both registers are RAZ/WI to Non-secure accesses, which only observe the
+        // Non-secure Group 1 interrupts.
+        assert_eq!(vgic.gicd_read(non_secure, GICD_IGROUPR + 4), 0);
+        assert_eq!(vgic.gicd_read(non_secure, GICD_IGRPMODR + 4), 0);
+        write(&vgic, non_secure, GICD_IGROUPR + 4, 0b0011 << 8);
+        write(&vgic, non_secure, GICD_IGRPMODR + 4, 0);
+        assert_eq!(vgic.gicd_read(secure, GICD_IGROUPR + 4), 0b1100 << 8);
+        assert_eq!(vgic.gicd_read(secure, GICD_IGRPMODR + 4), 0b1010 << 8);
+        assert_eq!(vgic.gicd_read(non_secure, GICD_ISENABLER + 4), 0b1100 << 8);
+
+        // With a single security state IGRPMODR is RAZ/WI and there is no Secure Group 1.
+        let (vgic, _host) = new_vgic(Vgicv3Config::default());
+        write(&vgic, secure, GICD_IGRPMODR + 4, 0b10 << 8);
+        assert_eq!(vgic.gicd_read(secure, GICD_IGRPMODR + 4), 0);
+        assert_eq!(vgic.irq(0, 41).unwrap().lock().group(), IrqGroup::Group0);
+    }
+
+    #[test]
+    fn are_switches_between_itargetsr_and_irouter() {
+        let (vgic, _host) = legacy_vgic(2);
+        let acc = access(0, true);
+        let routed = |vgic: &Vgicv3| vgic.routed_vcpu(0, &vgic.irq(0, 40).unwrap().lock());
+        let irouter = GICD_IROUTER + 8 * 40;
+        let affinity = |vcpu_id| vgic.config().vcpu_affinity(vcpu_id) as u32;
+
+        // Without affinity routing, SPI 40 follows GICD_ITARGETSR and GICD_IROUTER is RAZ/WI.
+        write(&vgic, acc, GICD_ITARGETSR + 40, 0b10);
+        write(&vgic, acc, irouter, affinity(0));
+        assert_eq!(routed(&vgic), Some(1));
+        assert_eq!(vgic.gicd_read(acc, GICD_ITARGETSR + 40), 0b10);
+        assert_eq!(vgic.gicd_read(acc, irouter), 0);
+
+        // ARE can only be set with the groups disabled.
+        write(&vgic, acc, GICD_CTLR, GICD_CTLR_ENABLE_GRP0 | GICD_CTLR_ARE);
+        assert!(!vgic.affinity_routing(true));
+        write(&vgic, acc, GICD_CTLR, 0);
+        write(&vgic, acc, GICD_CTLR, GICD_CTLR_ARE);
+        assert!(vgic.affinity_routing(true));
+
+        // With it, SPI 40 follows GICD_IROUTER and GICD_ITARGETSR is RAZ/WI.
+        assert_eq!(routed(&vgic), Some(0));
+        write(&vgic, acc, irouter, affinity(1));
+        write(&vgic, acc, GICD_ITARGETSR + 40, 0b01);
+        assert_eq!(routed(&vgic), Some(1));
+        assert_eq!(vgic.gicd_read(acc, irouter), affinity(1));
+        assert_eq!(vgic.gicd_read(acc, GICD_ITARGETSR + 40), 0);
+        // SGIs and PPIs move to the redistributor.
+        assert_eq!(vgic.gicd_read(acc, GICD_ISENABLER), 0);
+
+        // Clearing it restores the GICD_ITARGETSR routing.
+        write(&vgic, acc, GICD_CTLR, 0);
+        assert!(!vgic.affinity_routing(true));
+        assert_eq!(vgic.gicd_read(acc, GICD_ITARGETSR + 40), 0b10);
+        assert_eq!(vgic.gicd_read(acc, GICD_ISENABLER), 0xffff);
+        write(&vgic, acc, GICD_ITARGETSR + 40, 0b01);
+        assert_eq!(routed(&vgic), Some(0));
+    }
+
+    fn is_pending(vgic: &Vgicv3, intid: u32) -> bool {
+        vgic.irq(0, intid).unwrap().lock().is_pending()
+    }
+
+    #[test]
+    fn setspi_and_clrspi_follow_the_trigger_mode() {
+        let (vgic, _host) = new_vgic(Vgicv3Config {
+            mbis: true,
+            ..Default::default()
+        });
+        let acc = access(0, true);
+        // SPI 41 is edge-triggered, SPI 40 level-sensitive.
+        write(&vgic, acc, GICD_ICFGR + 8, 0b10 << 18);
+
+        // A set asserts the level, a clear deasserts it.
+        write(&vgic, acc, GICD_SETSPI_NSR, 40);
+        assert!(vgic.irq(0, 40).unwrap().lock().line_level);
+        assert!(is_pending(&vgic, 40));
+        write(&vgic, acc, GICD_CLRSPI_NSR, 40);
+        assert!(!is_pending(&vgic, 40));
+
+        // A set latches the edge, which a clear leaves pending.
+        write(&vgic, acc, GICD_SETSPI_NSR, 41);
+        assert!(is_pending(&vgic, 41));
+        write(&vgic, acc, GICD_CLRSPI_NSR, 41);
+        assert!(is_pending(&vgic, 41));
+        assert!(!vgic.irq(0, 41).unwrap().lock().line_level);
+    }
+
+    #[test]
+    fn setspi_ignores_intids_outside_the_spis() {
+        let (vgic, _host) = new_vgic(Vgicv3Config {
+            mbis: true,
+            ..Default::default()
+        });
+        let acc = access(0, true);
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        // Private interrupts, SPIs beyond spi_num and special or reserved INTIDs.
+        for value in [5, 27, 96, 1020, 1023, 0x1fff] {
+            assert!(write(&vgic, acc, GICD_SETSPI_NSR, value).is_empty());
+        }
+        assert!(!is_pending(&vgic, 5) && !is_pending(&vgic, 27));
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+    }
+
+    #[test]
+    fn setspi_registers_are_restricted_to_their_security_state() {
+        let (vgic, _host) = new_vgic(Vgicv3Config {
+            security: SecurityModel::TwoSecurityStates,
+            mbis: true,
+            ..Default::default()
+        });
+        let (secure, non_secure) = (access(0, true), access(0, false));
+        // SPI 40 is Group 0, SPI 41 Non-secure Group 1.
+        write(&vgic, secure, GICD_IGROUPR + 4, 1 << 9);
+
+        write(&vgic, non_secure, GICD_SETSPI_NSR, 40);
+        write(&vgic, secure, GICD_SETSPI_SR, 41);
+        assert!(!is_pending(&vgic, 40) && !is_pending(&vgic, 41));
+        // GICD_SETSPI_SR is WI to Non-secure accesses.
+        write(&vgic, non_secure, GICD_SETSPI_SR, 40);
+        assert!(!is_pending(&vgic, 40));
+
+        write(&vgic, secure, GICD_SETSPI_SR, 40);
+        write(&vgic, non_secure, GICD_SETSPI_NSR, 41);
+        assert!(is_pending(&vgic, 40) && is_pending(&vgic, 41));
+    }
+}
@@ -0,0 +1,231 @@
+//! Redistributor register decode.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+use axerrno::{AxResult, ax_err};
+use log::{error, warn};
+
+use crate::config::GicVersion;
+use crate::consts::*;
+use crate::vgicv3::{Access, Vgicv3};
+
+/// Redistributor region of a [`Vgicv3`] in [`GicVersion::V3`] mode.
+///
+/// The region starts at [`Vgicv3Config::gicr_base`](crate::Vgicv3Config::gicr_base) and
+/// holds one `RD_base` and `SGI_base` frame pair per vCPU, in vCPU order. It is registered
+/// with the hypervisor as a device of its own, next to the distributor.
+pub struct Vgicr {
+    pub(crate) vgic: Arc<Vgicv3>,
+}
+
+impl Vgicr {
+    /// Creates the redistributor region of `vgic`.
+    ///
+    /// # Returns
+    /// - `Ok(Vgicr)` on success
+    /// - `Err(AxError)` if `vgic` presents a GICv2, which has no redistributors
+    pub fn new(vgic: Arc<Vgicv3>) -> AxResult<Self> {
+        if vgic.config.version != GicVersion::V3 {
+            return ax_err!(Unsupported, "GICv2 has no redistributors");
+        }
+        Ok(Self { vgic })
+    }
+
+    /// Returns the size of the redistributor region in bytes.
+    pub fn size(&self) -> usize {
+        self.vgic.config.vcpu_num * GICR_STRIDE
+    }
+
+    /// Handles a read of `width` bytes at `addr`, an offset within the region.
+    ///
+    /// Accesses narrower than 32 bits read the containing register, 64-bit accesses (used
+    /// for `GICR_TYPER`) are split into two 32-bit reads.
+    pub(crate) fn handle_read(&self, addr: usize, width: usize) -> AxResult<usize> {
+        match width {
+            1 | 2 | 4 => {
+                let value = self.handle_read32(addr & !0x3)?;
+                let mask = (1usize << (8 * width)) - 1;
+                Ok((value >> (8 * (addr & 0x3))) & mask)
+            }
+            8 => {
+                let low = self.handle_read32(addr)?;
+                let high = self.handle_read32(addr + 4)?;
+                Ok(low | (high << 32))
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// Handles a write of `width` bytes at `addr`, an offset within the region.
+    pub(crate) fn handle_write(&self, addr: usize, width: usize, value: usize) {
+        match width {
+            1 | 2 => {
+                let shift = 8 * (addr & 0x3);
+                let mask = ((1u32 << (8 * width)) - 1) << shift;
+                self.handle_write_masked(addr & !0x3, (value as u32) << shift, mask);
+            }
+            4 => self.handle_write_masked(addr, value as u32, u32::MAX),
+            8 => {
+                self.handle_write_masked(addr, value as u32, u32::MAX);
+                self.handle_write_masked(addr + 4, (value >> 32) as u32, u32::MAX);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads the 32-bit register at the word-aligned `addr`.
+    fn handle_read32(&self, addr: usize) -> AxResult<usize> {
+        let Some(access) = self.access(addr) else {
+            error!("vgicv3: read outside the redistributor region at {addr:#x}");
+            return ax_err!(InvalidInput);
+        };
+        let value = self.vgic.gicr_read(access, addr % GICR_STRIDE);
+        vgic_trace!("vgicv3: GICR[{addr:#x}] -> {value:#x}");
+        Ok(value as usize)
+    }
+
+    /// Writes the bytes selected by `mask` of the register at the word-aligned `addr`.
+    fn handle_write_masked(&self, addr: usize, value: u32, mask: u32) {
+        let Some(access) = self.access(addr) else {
+            error!("vgicv3: write outside the redistributor region at {addr:#x}");
+            return;
+        };
+        vgic_trace!("vgicv3: GICR[{addr:#x}] <- {value:#x} (mask {mask:#x})");
+        let mut kicks = Vec::new();
+        self.vgic
+            .gicr_write(access, addr % GICR_STRIDE, value, mask, &mut kicks);
+        self.vgic.kick_vcpus(&kicks);
+    }
+
+    /// Describes an access to the redistributor containing `addr`.
+    fn access(&self, addr: usize) -> Option<Access> {
+        let vcpu_id = addr / GICR_STRIDE;
+        (vcpu_id < self.vgic.config.vcpu_num).then(|| Access {
+            vcpu_id,
+            redistributor: true,
+            ..self.vgic.current_access()
+        })
+    }
+}
+
+/// Returns the INTID offset of the SGI, PPI or Extended PPI register at `offset` within
+/// `SGI_base`, or `None` if `offset` is not one of them.
+///
+/// These registers share the layout of the distributor banks: the first register of each
+/// bank covers the SGIs and PPIs (`GICR_ISENABLER0`, ...), the following ones the
+/// Extended PPIs (`GICR_ISENABLER<n>E`, ...).
+fn banked_intid_base(offset: usize) -> Option<usize> {
+    const BITMAP_BANKS: [usize; 9] = [
+        GICD_IGROUPR,
+        GICD_ISENABLER,
+        GICD_ICENABLER,
+        GICD_ISPENDR,
+        GICD_ICPENDR,
+        GICD_ISACTIVER,
+        GICD_ICACTIVER,
+        GICD_IGRPMODR,
+        GICD_INMIR,
+    ];
+    const REGS: usize = 1 + EPPI_NUM_MAX / 32;
+    let index = if (GICD_IPRIORITYR..GICD_IPRIORITYR + REGS * 0x20).contains(&offset) {
+        (offset - GICD_IPRIORITYR) / 0x20
+    } else if (GICD_ICFGR..GICD_ICFGR + REGS * 0x8).contains(&offset) {
+        (offset - GICD_ICFGR) / 0x8
+    } else if BITMAP_BANKS.contains(&(offset & !0x7f)) && offset & 0x7f < REGS * 4 {
+        (offset & 0x7f) / 4
+    } else {
+        return None;
+    };
+    Some(if index == 0 {
+        0
+    } else {
+        EPPI_ID_BASE - PRIVATE_IRQ_NUM
+    })
+}
+
+impl Vgicv3 {
+    /// Reads the register at the word-aligned `offset` within the frames of the
+    /// redistributor of `access.vcpu_id`.
+    pub(crate) fn gicr_read(&self, access: Access, offset: usize) -> u32 {
+        if let Some(off) = offset.checked_sub(GICR_SGI_BASE) {
+            if let Some(intid_base) = banked_intid_base(off) {
+                return self.read_irq_bank(access, off, intid_base).unwrap_or(0);
+            }
+            if off != GICR_NSACR {
+                warn!("vgicv3: read of unimplemented GICR SGI register {off:#x}");
+            }
+            return 0;
+        }
+
+        let vcpu_id = access.vcpu_id;
+        match offset {
+            GICR_CTLR | GICR_STATUSR => 0,
+            GICR_IIDR => VGIC_IIDR,
+            GICR_TYPER => {
+                let last = if vcpu_id + 1 == self.config.vcpu_num {
+                    GICR_TYPER_LAST
+                } else {
+                    0
+                };
+                // PPInum: the number of Extended PPIs in blocks of 32.
+                let ppi_num = (self.config.eppi_num / 32) as u32;
+                (ppi_num << 27) | ((vcpu_id as u32) << 8) | last
+            }
+            GICR_TYPER_HIGH => {
+                // Aff3 is moved next to Aff2 in the upper half of GICR_TYPER.
+                let affinity = self.config.vcpu_affinity(vcpu_id);
+                ((affinity >> 8) as u32 & 0xff00_0000) | (affinity as u32 & 0x00ff_ffff)
+            }
+            GICR_WAKER => {
+                if self.cpus[vcpu_id].processor_sleep.load(Ordering::Acquire) {
+                    GICR_WAKER_PROCESSOR_SLEEP | GICR_WAKER_CHILDREN_ASLEEP
+                } else {
+                    0
+                }
+            }
+            GICR_PIDR2 => 0x3 << 4,
+            _ => {
+                warn!("vgicv3: read of unimplemented GICR register {offset:#x}");
+                0
+            }
+        }
+    }
+
+    /// Writes the bytes selected by `mask` of the register at the word-aligned `offset`
+    /// within the frames of the redistributor of `access.vcpu_id`.
+    pub(crate) fn gicr_write(
+        &self,
+        access: Access,
+        offset: usize,
+        value: u32,
+        mask: u32,
+        kicks: &mut Vec<usize>,
+    ) {
+        if let Some(off) = offset.checked_sub(GICR_SGI_BASE) {
+            if let Some(intid_base) = banked_intid_base(off) {
+                self.write_irq_bank(access, off, intid_base, value, mask, kicks);
+            } else if off != GICR_NSACR {
+                warn!("vgicv3: write of unimplemented GICR SGI register {off:#x}");
+            }
+            return;
+        }
+
+        match offset {
+            GICR_CTLR | GICR_STATUSR => {}
+            GICR_WAKER if mask & GICR_WAKER_PROCESSOR_SLEEP != 0 => {
+                // The redistributor wakes up and goes to sleep instantly.
+                let sleep = value & GICR_WAKER_PROCESSOR_SLEEP != 0;
+                let previous = self.cpus[access.vcpu_id]
+                    .processor_sleep
+                    .swap(sleep, Ordering::AcqRel);
+                if previous != sleep {
+                    self.update_irm_spis(kicks);
+                }
+            }
+            GICR_WAKER => {}
+            _ => warn!("vgicv3: write of unimplemented GICR register {offset:#x}"),
+        }
+    }
+}
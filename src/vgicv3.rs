@@ -1,21 +1,97 @@
-use axerrno::AxResult;
-use log::error;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use axaddrspace::{GuestPhysAddr, HostPhysAddr};
+use axerrno::{AxResult, ax_err, ax_err_type};
+use log::{error, warn};
 use spin::Mutex;
 
+use crate::caps::HostGicCaps;
+use crate::config::{GicVersion, SecurityModel, Vgicv3Config};
+use crate::consts::*;
+use crate::hal::VgicHostOps;
+use crate::inject::InjectQueue;
+use crate::interrupt::{IrqGroup, TriggerMode, VirtIrq, is_private_intid};
+use crate::regs::gich::VtrInfo;
+use crate::resample::IrqResampler;
+use crate::vcpu::VgicCpu;
+
 /// Virtual Generic Interrupt Controller v3 (VGICv3) emulator.
 ///
 /// This structure emulates the behavior of ARM's Generic Interrupt Controller version 3
 /// in a virtualized environment. It handles interrupt distribution and prioritization
 /// for virtual machines, providing register-level emulation of GICv3 features.
+///
+/// Depending on [`Vgicv3Config::version`], the distributor is presented either as a native
+/// GICv3 distributor or as a GICv2 distributor whose CPU interface is the host GICV frame.
+///
+/// # Locking
+///
+/// Each interrupt has its own lock, and so does the list of interrupts queued on each
+/// vCPU (its ap_list, together with the List register values last written and the state
+/// of the software CPU interface). `GICD_CTLR`, `GICR_WAKER.ProcessorSleep` and the
+/// mirrored group enables of each vCPU are atomics. Locks are always taken in this order, and
+/// at most one of each kind is held at a time:
+///
+/// 1. The consumer lock of the injection queue of a vCPU.
+/// 2. The ap_list lock of a vCPU.
+/// 3. The lock of an interrupt.
+/// 4. The resampler table.
+///
+/// A state change updates the interrupt under its lock, releases it, and only then takes
+/// the ap_list lock of the target vCPU to queue it, in [`Vgicv3::update_irq`]. Nothing
+/// is held while taking an ap_list lock, so a vCPU generating an SGI never waits for
+/// another vCPU syncing its List registers while holding a lock that one needs, and an
+/// injection only ever contends with the vCPU it targets.
 pub struct Vgicv3 {
-    // Note: Actual implementation would contain internal state fields here.
-    // Fields might include:
-    // - Distributor registers state
-    // - Redistributor registers
-    // - CPU interface state
-    // - Interrupt priority configurations
-    // - Interrupt routing information
-    // (Currently empty as this appears to be a work-in-progress implementation)
+    pub(crate) config: Vgicv3Config,
+    pub(crate) host: Arc<dyn VgicHostOps>,
+    /// Number of List registers implemented by the host.
+    pub(crate) nr_lrs: usize,
+    /// `GICH_VTR` of the host, `None` with the software CPU interface.
+    pub(crate) vtr: Option<VtrInfo>,
+    /// Whether List registers are accessed as `ICH_LR<n>_EL2`, which extended INTIDs
+    /// require.
+    pub(crate) ich_lr: bool,
+    /// Distributor control register (`GICD_CTLR`): the group enables and the affinity
+    /// routing enables, in the layout of the Secure view.
+    pub(crate) ctlr: AtomicU32,
+    /// Shared Peripheral Interrupts.
+    pub(crate) spis: Vec<Mutex<VirtIrq>>,
+    /// Extended Shared Peripheral Interrupts.
+    pub(crate) espis: Vec<Mutex<VirtIrq>>,
+    /// Per-vCPU state, including the banked SGIs and PPIs.
+    pub(crate) cpus: Vec<VgicCpu>,
+    /// Resamplers of level-sensitive interrupts, keyed by the vCPU owning the interrupt (0
+    /// for shared interrupts) and INTID.
+    pub(crate) resamplers: Mutex<BTreeMap<(usize, u32), Arc<dyn IrqResampler>>>,
+    /// Per-vCPU queues of posted line level events.
+    pub(crate) inject_queues: Vec<InjectQueue>,
+}
+
+/// Originator of a register access.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Access {
+    /// Selects the bank of SGIs and PPIs: the vCPU performing a distributor access, or the
+    /// vCPU owning the accessed redistributor.
+    pub vcpu_id: usize,
+    /// Whether the access is Secure. Always set with a single security state.
+    pub secure: bool,
+    /// Whether the access targets the `SGI_base` frame of a redistributor.
+    pub redistributor: bool,
+}
+
+/// Stage-2 mapping that backs the guest's GICv2 CPU interface with the host GICV frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GiccMapping {
+    /// Guest physical address of the CPU interface.
+    pub guest_base: GuestPhysAddr,
+    /// Host physical address of the GICV frame.
+    pub host_base: HostPhysAddr,
+    /// Size of the mapping in bytes.
+    pub size: usize,
 }
 
 impl Vgicv3 {
@@ -24,10 +100,383 @@ impl Vgicv3 {
     /// Initializes a virtual GICv3 controller with default state.
     /// This should typically be called once per virtual machine instance.
     ///
+    /// # Arguments
+    /// * `config` - Layout and feature set presented to the guest
+    /// * `host` - Hypervisor services and access to the host virtual CPU interface
+    ///
     /// # Returns
-    /// Returns a new `Vgicv3` instance with default initialization.
-    pub fn new() -> Vgicv3 {
-        Vgicv3 {}
+    /// - `Ok(Vgicv3)` with all interrupts in their reset state
+    /// - `Err(AxError)` if the configuration is invalid or cannot be emulated on this host,
+    ///   see [`HostGicCaps::validate`]
+    pub fn new(config: Vgicv3Config, host: Arc<dyn VgicHostOps>) -> AxResult<Vgicv3> {
+        if config.vcpu_num == 0 {
+            return ax_err!(InvalidInput, "vGIC requires at least one vCPU");
+        }
+        if SPI_ID_BASE + config.spi_num > SPI_ID_MAX {
+            return ax_err!(InvalidInput, "too many SPIs");
+        }
+        if config.espi_num > ESPI_NUM_MAX
+            || !config.espi_num.is_multiple_of(32)
+            || config.eppi_num > EPPI_NUM_MAX
+            || !config.eppi_num.is_multiple_of(32)
+        {
+            return ax_err!(InvalidInput, "invalid number of extended SPIs or PPIs");
+        }
+        if (config.espi_num != 0 || config.eppi_num != 0)
+            && (config.version != GicVersion::V3 || config.legacy_support)
+        {
+            return ax_err!(
+                InvalidInput,
+                "extended SPIs and PPIs require GICv3 without legacy support"
+            );
+        }
+        if config.mbis && config.version != GicVersion::V3 {
+            return ax_err!(InvalidInput, "message-based SPIs require GICv3");
+        }
+        if config.software_cpu_interface
+            && (config.version != GicVersion::V3 || config.legacy_support || config.nmi)
+        {
+            return ax_err!(
+                InvalidInput,
+                "the software CPU interface requires GICv3 without legacy support or NMIs"
+            );
+        }
+        if config.nmi && config.version != GicVersion::V3 {
+            return ax_err!(InvalidInput, "NMIs require GICv3");
+        }
+        if config.supports_legacy() && config.vcpu_num > GICV2_CPU_NUM_MAX {
+            return ax_err!(
+                InvalidInput,
+                "legacy operation supports at most 8 CPU interfaces"
+            );
+        }
+
+        let vtr = (!config.software_cpu_interface).then(|| VtrInfo::from(host.read_vtr()));
+        if let Some(vtr) = vtr {
+            HostGicCaps::from_vtr(vtr, &*host)?.validate(&config)?;
+        }
+        let nr_lrs = vtr.map_or(0, |vtr| vtr.list_regs.min(GICH_LR_NUM_MAX));
+        let ich_lr = vtr.is_some() && 1usize << config.intid_bits() > GICH_LR_INTID_LIMIT;
+
+        // Without legacy support affinity routing is permanently enabled.
+        let ctlr = match (config.supports_legacy(), config.security) {
+            (true, _) => 0,
+            (false, SecurityModel::SingleSecurityState) => GICD_CTLR_ARE,
+            (false, SecurityModel::TwoSecurityStates) => GICD_CTLR_ARE_S | GICD_CTLR_ARE_NS,
+        };
+        let cpus = (0..config.vcpu_num)
+            .map(|vcpu_id| VgicCpu::new(vcpu_id, config.eppi_num))
+            .collect();
+        // The last bank of SPIs may extend over the special INTIDs 1020 to 1023, which are
+        // left unimplemented.
+        // The last bank of SPIs may extend over the special INTIDs 1020 to 1023, which are
+        // left unimplemented.
+        let spis = (SPI_ID_BASE..config.irq_num().min(SPI_ID_MAX))
+            .map(|intid| Mutex::new(VirtIrq::new(intid as u32)))
+            .collect();
+        let espis = (ESPI_ID_BASE..ESPI_ID_BASE + config.espi_num)
+            .map(|intid| Mutex::new(VirtIrq::new(intid as u32)))
+            .collect();
+
+        let inject_queues = (0..config.vcpu_num).map(|_| InjectQueue::new()).collect();
+
+        Ok(Vgicv3 {
+            config,
+            host,
+            nr_lrs,
+            vtr,
+            ich_lr,
+            ctlr: AtomicU32::new(ctlr),
+            spis,
+            espis,
+            cpus,
+            resamplers: Mutex::new(BTreeMap::new()),
+            inject_queues,
+        })
+    }
+
+    /// Returns the configuration this instance was created with.
+    pub fn config(&self) -> &Vgicv3Config {
+        &self.config
+    }
+
+    /// Looks up an interrupt, using the bank of `vcpu_id` for SGIs, PPIs and Extended PPIs.
+    pub(crate) fn irq(&self, vcpu_id: usize, intid: u32) -> Option<&Mutex<VirtIrq>> {
+        let intid = intid as usize;
+        match intid {
+            0..PRIVATE_IRQ_NUM => self.cpus.get(vcpu_id).map(|cpu| &cpu.private[intid]),
+            SPI_ID_BASE..SPI_ID_MAX => self.spis.get(intid - SPI_ID_BASE),
+            EPPI_ID_BASE..ESPI_ID_BASE => self
+                .cpus
+                .get(vcpu_id)
+                .and_then(|cpu| cpu.eppis.get(intid - EPPI_ID_BASE)),
+            ESPI_ID_BASE.. => self.espis.get(intid - ESPI_ID_BASE),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the distributor forwards interrupts of the given group.
+    pub(crate) fn group_enabled(&self, group: IrqGroup) -> bool {
+        let bit = match group {
+            IrqGroup::Group0 => GICD_CTLR_ENABLE_GRP0,
+            IrqGroup::Group1NonSecure => GICD_CTLR_ENABLE_GRP1NS,
+            IrqGroup::Group1Secure => GICD_CTLR_ENABLE_GRP1S,
+        };
+        self.ctlr.load(Ordering::Acquire) & bit != 0
+    }
+
+    /// Returns the mapping of the guest GICv2 CPU interface onto the host GICV frame.
+    ///
+    /// The hypervisor must install this mapping in the stage-2 page tables of the guest.
+    /// Returns `None` unless the vGIC supports legacy operation.
+    pub fn gicc_mapping(&self) -> Option<GiccMapping> {
+        if !self.config.supports_legacy() {
+            return None;
+        }
+        self.host.gicv_base().map(|host_base| GiccMapping {
+            guest_base: self.config.gicc_base,
+            host_base,
+            size: GICC_FRAME_SIZE,
+        })
+    }
+
+    /// Sets the input line level of a PPI or SPI.
+    ///
+    /// For edge-triggered interrupts a rising edge makes the interrupt pending. For
+    /// level-sensitive interrupts the interrupt is pending for as long as the line is high.
+    ///
+    /// # Arguments
+    /// * `vcpu_id` - The vCPU whose banked interrupt is targeted, ignored for SPIs
+    /// * `intid` - The interrupt ID, 16 or above
+    /// * `level` - The new line level
+    pub fn set_irq_level(&self, vcpu_id: usize, intid: u32, level: bool) -> AxResult {
+        let mut kicks = Vec::new();
+        let result = self.apply_irq_level(vcpu_id, intid, level, &mut kicks);
+        self.kick_vcpus(&kicks);
+        result
+    }
+
+    /// Implements [`Vgicv3::set_irq_level`], appending the vCPUs that need to be kicked to
+    /// `kicks`.
+    pub(crate) fn apply_irq_level(
+        &self,
+        vcpu_id: usize,
+        intid: u32,
+        level: bool,
+        kicks: &mut Vec<usize>,
+    ) -> AxResult {
+        if (intid as usize) < SGI_NUM {
+            return ax_err!(InvalidInput, "SGIs cannot be injected as wired interrupts");
+        }
+        let mut irq = self
+            .irq(vcpu_id, intid)
+            .ok_or(ax_err_type!(InvalidInput, "interrupt not implemented"))?
+            .lock();
+        if level && !irq.line_level {
+            vgic_trace!("vgicv3: INTID {intid} asserted on vCPU {vcpu_id}");
+            irq.count_injection();
+            if irq.trigger == TriggerMode::Edge {
+                irq.pending_latch = true;
+            }
+        }
+        irq.line_level = level;
+        drop(irq);
+        self.update_irq(vcpu_id, intid, kicks);
+        Ok(())
+    }
+
+    /// Emulates a write to `ICC_SGI1R_EL1` by the current vCPU.
+    ///
+    /// The hypervisor must trap the register and forward the written value. Writes are
+    /// ignored while affinity routing is disabled, as SGIs are then generated through
+    /// `GICD_SGIR`.
+    pub fn handle_sgi1r_write(&self, value: u64) {
+        let access = self.current_access();
+        if access.vcpu_id >= self.config.vcpu_num {
+            error!(
+                "vgicv3: ICC_SGI1R_EL1 write from invalid vCPU {}",
+                access.vcpu_id
+            );
+            return;
+        }
+        if !self.affinity_routing(access.secure) {
+            warn!("vgicv3: ICC_SGI1R_EL1 write with affinity routing disabled");
+            return;
+        }
+        let mut kicks = Vec::new();
+        self.write_sgi1r(access, value, &mut kicks);
+        self.kick_vcpus(&kicks);
+    }
+
+    /// Makes an SPI pending as if an edge had been signalled on its input.
+    ///
+    /// Used for message-based interrupts, which have no line level. The pending state is
+    /// latched regardless of the configured trigger mode.
+    ///
+    /// # Arguments
+    /// * `intid` - The INTID of an SPI or Extended SPI
+    pub fn inject_spi_edge(&self, intid: u32) -> AxResult {
+        let mut irq = self
+            .irq(0, intid)
+            .filter(|_| !is_private_intid(intid))
+            .ok_or(ax_err_type!(InvalidInput, "SPI not implemented"))?
+            .lock();
+        vgic_trace!("vgicv3: INTID {intid} injected as an edge");
+        irq.count_injection();
+        irq.pending_latch = true;
+        drop(irq);
+        let mut kicks = Vec::new();
+        self.update_irq(0, intid, &mut kicks);
+        self.kick_vcpus(&kicks);
+        Ok(())
+    }
+
+    /// Returns the vCPU an interrupt must currently be presented to.
+    ///
+    /// `vcpu_id` selects the bank for SGIs and PPIs. Returns `None` if the interrupt is not
+    /// deliverable (inactive and not pending, disabled, or without a valid target). An
+    /// active SPI stays on the vCPU it is active on until it is deactivated, however it has
+    /// been routed since.
+    pub(crate) fn irq_target(&self, vcpu_id: usize, irq: &VirtIrq) -> Option<usize> {
+        if irq.active {
+            return Some(if irq.is_private() {
+                vcpu_id
+            } else {
+                irq.active_vcpu
+            });
+        }
+        if !(irq.enabled && irq.is_pending() && self.group_enabled(irq.group())) {
+            return None;
+        }
+        self.routed_vcpu(vcpu_id, irq)
+    }
+
+    /// Returns the vCPU an interrupt is routed to by `GICD_IROUTER<n>` or
+    /// `GICD_ITARGETSR<n>`, regardless of its state.
+    pub(crate) fn routed_vcpu(&self, vcpu_id: usize, irq: &VirtIrq) -> Option<usize> {
+        if irq.is_private() {
+            return Some(vcpu_id);
+        }
+        if self.affinity_routing(irq.group() != IrqGroup::Group1NonSecure) {
+            if irq.route & GICD_IROUTER_IRM != 0 {
+                return Some(self.irm_target(irq.group()));
+            }
+            let affinity = irq.route & MPIDR_AFFINITY_MASK;
+            (0..self.config.vcpu_num).find(|&id| self.config.vcpu_affinity(id) == affinity)
+        } else {
+            let target = irq.targets.trailing_zeros() as usize;
+            (target < self.config.vcpu_num).then_some(target)
+        }
+    }
+
+    /// Returns the vCPU a 1-of-N SPI of `group` (`GICD_IROUTER<n>.Interrupt_Routing_Mode`
+    /// set) is delivered to: the first vCPU whose redistributor is awake and whose CPU
+    /// interface has `group` enabled, or vCPU 0 if there is none.
+    ///
+    /// The choice only changes when the vCPUs wake up, go to sleep or change their group
+    /// enables, after which [`Vgicv3::update_irm_spis`] moves the pending SPIs.
+    fn irm_target(&self, group: IrqGroup) -> usize {
+        let bit = if group == IrqGroup::Group0 { 1 } else { 2 };
+        self.cpus
+            .iter()
+            .position(|cpu| {
+                !cpu.processor_sleep.load(Ordering::Acquire)
+                    && cpu.group_enables.load(Ordering::Acquire) & bit != 0
+            })
+            .unwrap_or(0)
+    }
+
+    /// Re-evaluates the pending 1-of-N SPIs after the set of vCPUs they can be delivered
+    /// to has changed.
+    ///
+    /// Active ones are left where they are, so that they are only delivered to another
+    /// vCPU once deactivated.
+    pub(crate) fn update_irm_spis(&self, kicks: &mut Vec<usize>) {
+        let espis = ESPI_ID_BASE..ESPI_ID_BASE + self.config.espi_num;
+        for intid in (SPI_ID_BASE..self.config.irq_num()).chain(espis) {
+            let irm = self.irq(0, intid as u32).is_some_and(|irq| {
+                let irq = irq.lock();
+                irq.route & GICD_IROUTER_IRM != 0 && !irq.active
+            });
+            if irm {
+                self.update_irq(0, intid as u32, kicks);
+            }
+        }
+    }
+
+    /// Re-evaluates an interrupt after a state change and queues it on its target vCPU.
+    ///
+    /// Must be called without any ap_list or interrupt lock held. The target is evaluated
+    /// under the lock of the interrupt, which is released before taking the ap_list lock of
+    /// the target. If the interrupt is changed in between, the change is followed by its
+    /// own update, so the interrupt always ends up queued on its latest target; entries
+    /// left on a former target are dropped by [`Vgicv3::flush_lrs`].
+    ///
+    /// vCPUs that need to be kicked to observe the change are appended to `kicks`.
+    pub(crate) fn update_irq(&self, vcpu_id: usize, intid: u32, kicks: &mut Vec<usize>) {
+        let Some(irq) = self.irq(vcpu_id, intid) else {
+            return;
+        };
+        let Some(target) = self.irq_target(vcpu_id, &irq.lock()) else {
+            return;
+        };
+        let mut lists = self.cpus[target].lists.lock();
+        if !lists.ap_list.contains(&intid) {
+            lists.ap_list.push(intid);
+            if !kicks.contains(&target) {
+                kicks.push(target);
+            }
+        }
+    }
+
+    /// Re-evaluates every interrupt, e.g. after a group has been enabled in `GICD_CTLR`.
+    pub(crate) fn update_all_irqs(&self, kicks: &mut Vec<usize>) {
+        for vcpu_id in 0..self.config.vcpu_num {
+            let eppis = EPPI_ID_BASE..EPPI_ID_BASE + self.config.eppi_num;
+            for intid in (0..PRIVATE_IRQ_NUM).chain(eppis) {
+                self.update_irq(vcpu_id, intid as u32, kicks);
+            }
+        }
+        let espis = ESPI_ID_BASE..ESPI_ID_BASE + self.config.espi_num;
+        for intid in (SPI_ID_BASE..self.config.irq_num()).chain(espis) {
+            self.update_irq(0, intid as u32, kicks);
+        }
+    }
+
+    /// Kicks the given vCPUs, except the one performing the current access.
+    pub(crate) fn kick_vcpus(&self, kicks: &[usize]) {
+        if kicks.is_empty() {
+            return;
+        }
+        let current = self.host.current_vcpu_id();
+        for &vcpu_id in kicks.iter().filter(|&&id| id != current) {
+            self.host.kick_vcpu(vcpu_id);
+        }
+    }
+
+    /// Describes the register access currently being emulated.
+    pub(crate) fn current_access(&self) -> Access {
+        Access {
+            vcpu_id: self.host.current_vcpu_id(),
+            secure: self.config.security == SecurityModel::SingleSecurityState
+                || self.host.current_access_secure(),
+            redistributor: false,
+        }
+    }
+
+    /// Returns whether affinity routing is enabled for the given security state.
+    ///
+    /// With affinity routing, SPIs are routed by `GICD_IROUTER<n>`, SGIs are generated through
+    /// `ICC_SGI1R_EL1` and SGIs and PPIs are configured through the redistributors. Without
+    /// it, the distributor behaves as a GICv2 distributor: SPIs are routed by
+    /// `GICD_ITARGETSR<n>`, SGIs are generated through `GICD_SGIR` and SGIs and PPIs are
+    /// banked in the distributor.
+    pub(crate) fn affinity_routing(&self, secure: bool) -> bool {
+        let bit = match self.config.security {
+            SecurityModel::TwoSecurityStates if !secure => GICD_CTLR_ARE_NS,
+            _ => GICD_CTLR_ARE_S,
+        };
+        self.ctlr.load(Ordering::Acquire) & bit != 0
     }
 
     /// Handles 8-bit read operations from GICv3 registers.
@@ -44,7 +493,7 @@ impl Vgicv3 {
     /// - `Err(AxError)` if the underlying 32-bit read fails
     pub(crate) fn handle_read8(&self, addr: usize) -> AxResult<usize> {
         let value = self.handle_read32(addr)?;
-        return Ok((value >> (8 * (addr & 0x3))) & 0xff);
+        Ok((value >> (8 * (addr & 0x3))) & 0xff)
     }
 
     /// Handles 16-bit read operations from GICv3 registers.
@@ -60,25 +509,46 @@ impl Vgicv3 {
     /// - `Err(AxError)` if the underlying 32-bit read fails
     pub(crate) fn handle_read16(&self, addr: usize) -> AxResult<usize> {
         let value = self.handle_read32(addr)?;
-        return Ok((value >> (8 * (addr & 0x3))) & 0xffff);
+        Ok((value >> (8 * (addr & 0x3))) & 0xffff)
     }
 
     /// Handles 32-bit read operations from GICv3 registers.
     ///
-    /// Primary method for reading GICv3 distributor and CPU interface registers.
-    /// This should implement the actual register map emulation logic.
+    /// Primary method for reading GICv3 distributor registers. The banked SGI and PPI
+    /// registers are resolved against the vCPU performing the access.
     ///
     /// # Arguments
-    /// * `addr` - The word-aligned register address to read from
+    /// * `addr` - The register offset within the distributor frame
     ///
     /// # Returns
     /// - `Ok(usize)` containing the 32-bit register value on success
-    /// - `Err(AxError)` for invalid addresses or unsupported operations
-    ///
-    /// # Note
-    /// Current implementation is incomplete (marked with todo!)
+    /// - `Err(AxError)` for addresses outside the distributor frame
     pub fn handle_read32(&self, addr: usize) -> AxResult<usize> {
-        todo!()
+        if addr >= GICD_FRAME_SIZE {
+            error!("vgicv3: read outside the distributor frame at {addr:#x}");
+            return ax_err!(InvalidInput);
+        }
+        let access = self.current_access();
+        let value = self.gicd_read(access, addr & !0x3);
+        vgic_trace!(
+            "vgicv3: vCPU {} GICD[{addr:#x}] -> {value:#x}",
+            access.vcpu_id
+        );
+        Ok(value as usize)
+    }
+
+    /// Handles 64-bit read operations, used for the `GICD_IROUTER<n>` registers.
+    ///
+    /// # Arguments
+    /// * `addr` - The doubleword-aligned register offset to read from
+    ///
+    /// # Returns
+    /// - `Ok(usize)` containing the 64-bit value on success
+    /// - `Err(AxError)` if either 32-bit half cannot be read
+    pub(crate) fn handle_read64(&self, addr: usize) -> AxResult<usize> {
+        let low = self.handle_read32(addr)?;
+        let high = self.handle_read32(addr + 4)?;
+        Ok(low | (high << 32))
     }
 
     /// Handles 8-bit write operations to GICv3 registers.
@@ -90,7 +560,8 @@ impl Vgicv3 {
     /// * `addr` - The byte-aligned register address to write to
     /// * `value` - The 8-bit value to write (stored in the lower 8 bits of the parameter)
     pub fn handle_write8(&self, addr: usize, value: usize) {
-        self.handle_write32(addr, value);
+        let shift = 8 * (addr & 0x3);
+        self.handle_write_masked(addr, ((value as u32) & 0xff) << shift, 0xff << shift);
     }
 
     /// Handles 16-bit write operations to GICv3 registers.
@@ -102,21 +573,182 @@ impl Vgicv3 {
     /// * `addr` - The halfword-aligned register address to write to
     /// * `value` - The 16-bit value to write (stored in the lower 16 bits of the parameter)
     pub fn handle_write16(&self, addr: usize, value: usize) {
-        self.handle_write32(addr, value);
+        let shift = 8 * (addr & 0x3);
+        self.handle_write_masked(addr, ((value as u32) & 0xffff) << shift, 0xffff << shift);
     }
 
     /// Handles 32-bit write operations to GICv3 registers.
     ///
-    /// Primary method for writing to GICv3 registers. This should implement the actual
-    /// register emulation logic, including interrupt state updates and configuration.
+    /// Primary method for writing to GICv3 registers. This updates the emulated interrupt
+    /// state and queues interrupts that became deliverable on their target vCPUs.
     ///
     /// # Arguments
-    /// * `addr` - The word-aligned register address to write to
+    /// * `addr` - The register offset within the distributor frame
     /// * `value` - The 32-bit value to write
-    ///
-    /// # Note
-    /// Current implementation is incomplete (marked with todo!)
     pub fn handle_write32(&self, addr: usize, value: usize) {
-        todo!()
+        self.handle_write_masked(addr, value as u32, u32::MAX);
+    }
+
+    /// Handles 64-bit write operations, used for the `GICD_IROUTER<n>` registers.
+    ///
+    /// # Arguments
+    /// * `addr` - The doubleword-aligned register offset to write to
+    /// * `value` - The 64-bit value to write
+    pub(crate) fn handle_write64(&self, addr: usize, value: usize) {
+        self.handle_write32(addr, value & 0xffff_ffff);
+        self.handle_write32(addr + 4, value >> 32);
+    }
+
+    /// Writes the bytes of a distributor register selected by `mask`.
+    fn handle_write_masked(&self, addr: usize, value: u32, mask: u32) {
+        if addr >= GICD_FRAME_SIZE {
+            error!("vgicv3: write outside the distributor frame at {addr:#x}");
+            return;
+        }
+        let access = self.current_access();
+        vgic_trace!(
+            "vgicv3: vCPU {} GICD[{addr:#x}] <- {value:#x} (mask {mask:#x})",
+            access.vcpu_id
+        );
+        let mut kicks = Vec::new();
+        self.gicd_write(access, addr & !0x3, value, mask, &mut kicks);
+        self.kick_vcpus(&kicks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::GichOps;
+    use crate::mock;
+    use crate::regs::gich::GICH_VMCR;
+
+    #[test]
+    fn one_of_n_spis_follow_enabled_vcpus() {
+        let config = Vgicv3Config {
+            vcpu_num: 2,
+            ..Default::default()
+        };
+        let (vgic, host) = mock::vgic(config);
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.handle_write64(GICD_IROUTER + 8 * 40, GICD_IROUTER_IRM as usize);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        // No vCPU can take it yet.
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
+
+        let mut kicks = Vec::new();
+        let access = Access {
+            vcpu_id: 1,
+            secure: true,
+            redistributor: false,
+        };
+        vgic.gicr_write(
+            access,
+            GICR_WAKER,
+            0,
+            GICR_WAKER_PROCESSOR_SLEEP,
+            &mut kicks,
+        );
+        assert!(mock::ap_list(&vgic, 1).is_empty());
+
+        // vCPU 1 enables Group 1 while vCPU 0 has not.
+        host.write_vmcr(GICH_VMCR::VENG1::SET.value);
+        vgic.sync_lrs(1);
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+        assert_eq!(host.take_kicks(), [1]);
+        vgic.flush_lrs(0);
+        assert!(mock::ap_list(&vgic, 0).is_empty());
+        vgic.flush_lrs(1);
+        assert_eq!(host.lr(0).vintid, 40);
+    }
+
+    #[test]
+    fn special_intids_are_not_implemented_in_the_last_spi_bank() {
+        let host = mock::MockHost::new(mock::MockHost::default_vtr());
+        let config = Vgicv3Config {
+            spi_num: 989,
+            ..Default::default()
+        };
+        assert!(Vgicv3::new(config, host).is_err());
+
+        // 988 SPIs are rounded up to 31 full banks, ending at INTID 1023.
+        let (vgic, _host) = mock::vgic(Vgicv3Config {
+            spi_num: 988,
+            ..Default::default()
+        });
+        assert_eq!(vgic.spis.len(), 988);
+        assert!(vgic.irq(0, 1019).is_some());
+        assert!(vgic.irq(0, 1020).is_none());
+        vgic.handle_write32(GICD_ISENABLER + 4 * 31, u32::MAX as usize);
+        assert_eq!(
+            vgic.handle_read32(GICD_ISENABLER + 4 * 31).unwrap(),
+            0x0fff_ffff
+        );
+        vgic.handle_write8(GICD_IPRIORITYR + 1020, 0xa0);
+        assert_eq!(vgic.handle_read32(GICD_IPRIORITYR + 1020).unwrap(), 0);
+        assert_eq!(vgic.handle_read32(GICD_TYPER).unwrap() & 0x1f, 31);
+
+        let vgic = Arc::new(vgic);
+        assert!(crate::v2m::Gicv2mFrame::new(vgic.clone(), 0x802_0000.into(), 1000, 20).is_ok());
+        assert!(crate::v2m::Gicv2mFrame::new(vgic, 0x802_0000.into(), 1000, 24).is_err());
+    }
+
+    #[test]
+    fn aff3_requires_host_support() {
+        let config = Vgicv3Config {
+            vcpu_num: 2,
+            aff3: 1,
+            ..Default::default()
+        };
+        let host = mock::MockHost::new(mock::MockHost::default_vtr());
+        assert!(Vgicv3::new(config.clone(), host).is_err());
+
+        let vtr = VtrInfo {
+            a3v: true,
+            ..mock::MockHost::default_vtr()
+        };
+        let (vgic, _host) = mock::vgic_with_vtr(config, vtr);
+        assert_ne!(
+            vgic.handle_read32(GICD_TYPER).unwrap() as u32 & GICD_TYPER_A3V,
+            0
+        );
+        mock::enable_spi(&vgic, 40, 0xa0, 1);
+        assert_eq!(
+            vgic.handle_read64(GICD_IROUTER + 8 * 40).unwrap(),
+            0x1_0000_0001
+        );
+        vgic.set_irq_level(0, 40, true).unwrap();
+        assert_eq!(mock::ap_list(&vgic, 1), [40]);
+    }
+
+    #[test]
+    fn active_one_of_n_spis_stay_on_their_vcpu() {
+        let config = Vgicv3Config {
+            vcpu_num: 2,
+            ..Default::default()
+        };
+        let (vgic, host) = mock::vgic(config);
+        mock::enable_spi(&vgic, 40, 0xa0, 0);
+        vgic.handle_write64(GICD_IROUTER + 8 * 40, GICD_IROUTER_IRM as usize);
+        vgic.set_irq_level(0, 40, true).unwrap();
+        vgic.irq(0, 40).unwrap().lock().active = true;
+
+        let mut kicks = Vec::new();
+        let access = Access {
+            vcpu_id: 1,
+            secure: true,
+            redistributor: false,
+        };
+        vgic.gicr_write(
+            access,
+            GICR_WAKER,
+            0,
+            GICR_WAKER_PROCESSOR_SLEEP,
+            &mut kicks,
+        );
+        host.write_vmcr(GICH_VMCR::VENG1::SET.value);
+        vgic.sync_lrs(1);
+        assert!(mock::ap_list(&vgic, 1).is_empty());
+        assert_eq!(mock::ap_list(&vgic, 0), [40]);
     }
 }